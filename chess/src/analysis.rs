@@ -0,0 +1,198 @@
+use std::fmt;
+
+use crate::chess_piece::Color;
+use crate::error::ChessError;
+use crate::moves::Move;
+use crate::position::Position;
+use crate::search::{mate_in, search_lines, SearchLimits, SearchOptions};
+use crate::variant::{Standard, Variant};
+
+/// An evaluation the way people read it, from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Full moves to mate, negative when the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    /// Converts a search score, see `mate_in`.
+    pub fn from_search(score: i32) -> Score {
+        match mate_in(score) {
+            Some(moves) => Score::Mate(moves),
+            None => Score::Centipawns(score),
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    /// "+1.25" in pawns, or "#3" / "#-2" for mates
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Centipawns(centipawns) => write!(f, "{:+.2}", *centipawns as f64 / 100.0),
+            Score::Mate(moves) => write!(f, "#{}", moves),
+        }
+    }
+}
+
+/// One candidate move with the continuation the engine expects after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisLine {
+    pub score: Score,
+    /// The principal variation, starting with the candidate move
+    pub moves: Vec<Move>,
+    /// The same moves in standard algebraic notation
+    pub san: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub position: Position,
+    /// Deepest iteration finished, lines ending in mate stop searching earlier
+    pub depth: u32,
+    pub nodes: u64,
+    /// Best line first
+    pub lines: Vec<AnalysisLine>,
+}
+
+impl fmt::Display for Analysis {
+    /// One line per candidate: "1. +0.35 e4 e5 Nf3" with move numbers for the first move
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.position.to_fen())?;
+        for (index, line) in self.lines.iter().enumerate() {
+            writeln!(f, "{}. {:>7} {}", index + 1, line.score.to_string(), numbered_line(&self.position, &line.san))?;
+        }
+        write!(f, "depth {}, {} nodes", self.depth, self.nodes)
+    }
+}
+
+/// The best `multipv` lines of a standard chess position searched to `depth`, at least one.
+pub fn analyze(fen: &str, depth: u32, multipv: usize) -> Result<Analysis, ChessError> {
+    let position = Position::from_fen(fen)?;
+    Ok(analyze_position(&Standard, &position, SearchLimits::depth(depth), &SearchOptions::default(), multipv))
+}
+
+/// `analyze` for any variant, with other limits or search options.
+pub fn analyze_position(
+    variant: &dyn Variant,
+    position: &Position,
+    limits: SearchLimits,
+    options: &SearchOptions,
+    multipv: usize,
+) -> Analysis {
+    let results = search_lines(variant, position, limits, options, multipv.max(1));
+    let lines = results
+        .iter()
+        .filter(|result| result.best_move.is_some())
+        .map(|result| AnalysisLine {
+            score: Score::from_search(result.score),
+            moves: result.pv.clone(),
+            san: san_line(variant, position, &result.pv),
+        })
+        .collect();
+    Analysis {
+        position: position.clone(),
+        depth: results.iter().map(|result| result.depth).max().unwrap_or(0),
+        nodes: results.iter().map(|result| result.nodes).sum(),
+        lines,
+    }
+}
+
+/// The moves played one after another from `position` in standard algebraic notation. Stops at
+/// the first move that is not legal.
+pub fn san_line(variant: &dyn Variant, position: &Position, moves: &[Move]) -> Vec<String> {
+    let mut position = position.clone();
+    let mut line = vec![];
+    for mv in moves {
+        if !variant.legal_moves(&position).contains(mv) {
+            break;
+        }
+        line.push(mv.to_san(variant, &position));
+        if variant.apply_move(&mut position, *mv).is_err() {
+            break;
+        }
+    }
+    line
+}
+
+/// "12. Nf3 Nc6 13. d4" or "12... Nc6 13. d4" when black moves first.
+pub fn numbered_line(position: &Position, san: &[String]) -> String {
+    let mut number = position.fullmove_number;
+    let mut white_to_move = position.side_to_move == Color::White;
+    let mut parts = vec![];
+    for (index, mv) in san.iter().enumerate() {
+        if white_to_move {
+            parts.push(format!("{}. {}", number, mv));
+        } else if index == 0 {
+            parts.push(format!("{}... {}", number, mv));
+        } else {
+            parts.push(mv.clone());
+        }
+        if !white_to_move {
+            number += 1;
+        }
+        white_to_move = !white_to_move;
+    }
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::{analyze, numbered_line, san_line, Score};
+    use crate::moves::Move;
+    use crate::position::Position;
+    use crate::variant::Standard;
+
+    fn moves(position: &Position, uci: &[&str]) -> Vec<Move> {
+        uci.iter().map(|uci| Move::from_uci(&position.board, uci).unwrap()).collect()
+    }
+
+    #[test]
+    fn scores_read_like_people_write_them() {
+        assert_eq!(Score::Centipawns(35).to_string(), "+0.35");
+        assert_eq!(Score::Centipawns(-120).to_string(), "-1.20");
+        assert_eq!(Score::Mate(2).to_string(), "#2");
+        assert_eq!(Score::Mate(-1).to_string(), "#-1");
+    }
+
+    #[test]
+    fn lines_in_algebraic_notation() {
+        let position = Position::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+        let san = san_line(&Standard, &position, &moves(&position, &["f1c4", "g8f6", "f3g5", "d7d5", "e4d5", "f6d5"]));
+        assert_eq!(san, vec!["Bc4", "Nf6", "Ng5", "d5", "exd5", "Nxd5"]);
+        assert_eq!(numbered_line(&position, &san), "3. Bc4 Nf6 4. Ng5 d5 5. exd5 Nxd5");
+        let black = Position::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(numbered_line(&black, &["e5".to_string(), "Nf3".to_string()]), "1... e5 2. Nf3");
+    }
+
+    #[test]
+    fn several_lines_best_first() {
+        let analysis = analyze("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3", 2, 3).unwrap();
+        assert_eq!(analysis.lines.len(), 3);
+        assert_eq!(analysis.depth, 2);
+        let firsts: Vec<&String> = analysis.lines.iter().map(|line| &line.san[0]).collect();
+        assert!(firsts[0] != firsts[1] && firsts[1] != firsts[2] && firsts[0] != firsts[2]);
+        let centipawns: Vec<i32> = analysis
+            .lines
+            .iter()
+            .map(|line| match line.score {
+                Score::Centipawns(centipawns) => centipawns,
+                Score::Mate(_) => panic!("no mate here"),
+            })
+            .collect();
+        assert!(centipawns.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn mates_and_few_moves() {
+        let analysis = analyze("k7/8/1K6/8/8/8/8/7R w - - 0 1", 3, 2).unwrap();
+        assert_eq!(analysis.lines[0].score, Score::Mate(1));
+        assert_eq!(analysis.lines[0].san, vec!["Rh8#"]);
+        // The king has a single square left
+        let analysis = analyze("k7/2R5/8/1K6/8/8/8/8 b - - 0 1", 1, 5).unwrap();
+        assert_eq!(analysis.lines.len(), 1);
+        assert_eq!(analysis.lines[0].san[0], "Kb8");
+        assert!(analyze("k7/8/1K6/8/8/8/8/7R w - - 0 1", 1, 0).unwrap().lines.len() == 1);
+        assert!(analyze("not a fen", 1, 1).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::analysis::san_line;
+use crate::chess_piece::Color;
+use crate::game::{Game, NodeId};
+use crate::moves::Move;
+use crate::position::Position;
+use crate::search::{format_score, search_with_options, SearchLimits, SearchOptions};
+
+// How much a move has to lower the winning chances, from -1 to 1, against the best one to be judged
+const INACCURACY_LOSS: f64 = 0.1;
+const MISTAKE_LOSS: f64 = 0.2;
+const BLUNDER_LOSS: f64 = 0.3;
+/// How quickly an advantage in centipawns turns into winning chances
+const CHANCES_SCALE: f64 = 0.00368;
+/// Mate scores count as this many centipawns
+const SCORE_CAP: i32 = 1_000;
+
+/// How bad a move was, by how much it lowered the winning chances compared to the engine's move.
+/// Going by chances rather than centipawns, a pawn lost in an equal position counts and one
+/// lost when already a rook up does not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// The judgement for a move scoring `played` where the best move scores `best`, both search
+    /// scores for the side making the move. `None` for good enough moves.
+    pub fn from_scores(best: i32, played: i32) -> Option<Judgement> {
+        match winning_chances(best) - winning_chances(played) {
+            loss if loss >= BLUNDER_LOSS => Some(Judgement::Blunder),
+            loss if loss >= MISTAKE_LOSS => Some(Judgement::Mistake),
+            loss if loss >= INACCURACY_LOSS => Some(Judgement::Inaccuracy),
+            _ => None,
+        }
+    }
+
+    /// The PGN move assessment: "?!", "?" or "??".
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "Inaccuracy",
+            Judgement::Mistake => "Mistake",
+            Judgement::Blunder => "Blunder",
+        }
+    }
+}
+
+/// Searches every position of the main line and annotates the moves: each gets a comment with
+/// the evaluation after it, from white's point of view. Moves that lost enough against the
+/// engine's choice are marked "?!", "?" or "??", with the engine's line added as a variation.
+/// Comments already in the game are kept in front. Returns the judged moves in game order, the
+/// game is left at the end of its main line.
+pub fn annotate_game(game: &mut Game, limits: SearchLimits, options: &SearchOptions) -> Vec<(NodeId, Judgement)> {
+    let mut nodes = vec![game.root()];
+    while let Some(&main) = game.variations(*nodes.last().unwrap()).first() {
+        nodes.push(main);
+    }
+    let results: Vec<_> = nodes
+        .iter()
+        .map(|node| search_with_options(game.variant(), position(game, *node), limits, options))
+        .collect();
+
+    let mut judged = vec![];
+    for (index, pair) in nodes.windows(2).enumerate() {
+        let (parent, node) = (pair[0], pair[1]);
+        let (before, after) = (&results[index], &results[index + 1]);
+        let mover = position(game, parent).side_to_move;
+        let judgement = match before.best_move {
+            Some(best_move) if Some(best_move) != game.move_at(node) => Judgement::from_scores(before.score, -after.score),
+            _ => None,
+        };
+
+        // The result says enough once the game is over
+        let mut comment = match after.best_move {
+            Some(_) => format_score(white_score(-after.score, mover)),
+            None => String::new(),
+        };
+        if let Some(judgement) = judgement {
+            let best_line = san_line(game.variant(), position(game, parent), &before.pv);
+            comment = format!(
+                "{} ({} -> {}). {} was best.",
+                judgement.name(),
+                format_score(white_score(before.score, mover)),
+                comment,
+                best_line.first().map_or("Another move", String::as_str)
+            );
+            game.set_annotation(node, judgement.glyph()).expect("the node is in the game");
+            add_line(game, parent, &before.pv, &format_score(white_score(before.score, mover)));
+            judged.push((node, judgement));
+        }
+        if let Some(earlier) = game.comment(node) {
+            comment = format!("{} {}", earlier, comment).trim_end().to_string();
+        }
+        game.set_comment(node, &comment).expect("the node is in the game");
+    }
+    game.go_to(*nodes.last().unwrap()).expect("the node is in the game");
+    judged
+}
+
+/// Expected result from -1 for a loss to 1 for a win.
+fn winning_chances(score: i32) -> f64 {
+    let centipawns = score.clamp(-SCORE_CAP, SCORE_CAP) as f64;
+    2.0 / (1.0 + (-CHANCES_SCALE * centipawns).exp()) - 1.0
+}
+
+fn position(game: &Game, node: NodeId) -> &Position {
+    game.position_at(node).expect("the node is in the game")
+}
+
+/// A score for `mover` turned around to white's point of view.
+fn white_score(score: i32, mover: Color) -> i32 {
+    match mover {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Plays the line from `parent` as a variation and puts the evaluation on its first move.
+fn add_line(game: &mut Game, parent: NodeId, line: &[Move], evaluation: &str) {
+    game.go_to(parent).expect("the node is in the game");
+    for (index, mv) in line.iter().enumerate() {
+        if game.play(*mv).is_err() {
+            break;
+        }
+        if index == 0 && game.comment(game.current_node()).is_none() {
+            let first = game.current_node();
+            game.set_comment(first, evaluation).expect("the node is in the game");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::annotate::{annotate_game, Judgement};
+    use crate::pgn::parse_pgn;
+    use crate::search::{SearchLimits, SearchOptions};
+
+    #[test]
+    fn judging_losses() {
+        assert_eq!(Judgement::from_scores(0, -40), None);
+        assert_eq!(Judgement::from_scores(0, -70), Some(Judgement::Inaccuracy));
+        assert_eq!(Judgement::from_scores(0, -150), Some(Judgement::Mistake));
+        assert_eq!(Judgement::from_scores(100, -300), Some(Judgement::Blunder));
+        // Already winning by a lot, or mating, two pawns less hardly matter
+        assert_eq!(Judgement::from_scores(900, 700), None);
+        assert_eq!(Judgement::from_scores(crate::search::MATE_SCORE - 3, 800), None);
+        assert_eq!(Judgement::Mistake.glyph(), "?");
+    }
+
+    #[test]
+    fn blunders_get_marked_with_the_better_line() {
+        // Black leaves the back rank open, then white misses the mate
+        let text = "[FEN \"6k1/5ppp/8/8/8/8/3q1PPP/R5K1 b - - 0 1\"]\n\n1... Qh6 {Oops} 2. Rb1 *";
+        let mut pgn = parse_pgn(text).unwrap().remove(0);
+        let judged = annotate_game(&mut pgn.game, SearchLimits::depth(2), &SearchOptions::default());
+        let game = &pgn.game;
+        let mainline: Vec<_> = {
+            let mut nodes = vec![];
+            let mut node = game.root();
+            while let Some(&main) = game.variations(node).first() {
+                nodes.push(main);
+                node = main;
+            }
+            nodes
+        };
+        assert_eq!(judged, vec![(mainline[0], Judgement::Blunder), (mainline[1], Judgement::Blunder)]);
+        assert_eq!(game.annotation(mainline[1]), Some("??"));
+        assert!(game.comment(mainline[0]).unwrap().starts_with("Oops "));
+        assert!(game.comment(mainline[1]).unwrap().contains("Ra8# was best."));
+        assert_eq!(game.variations(game.root()).len(), 2);
+        assert_eq!(game.variations(mainline[0]).len(), 2);
+        assert_eq!(game.ply(), 2);
+
+        let written = pgn.to_pgn();
+        assert!(written.contains("2. Rb1??"));
+        assert!(written.contains("(2. Ra8# {#1})"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::PieceType::Pawn;
+use crate::chess_piece::{Color, Piece, PieceMovement};
+
+/// Pieces of `color` that attack (or, for own pieces standing there, defend) the square.
+///
+/// Movement only lists captures of enemy pieces, so the square is given a stand-in enemy
+/// piece first. That way pawns only count their diagonals and sliders stop at blockers.
+pub fn attackers_of(board: &Chessboard, square: Square, color: Color) -> Vec<Piece> {
+    if !board.contains(square) {
+        return vec![];
+    }
+    let attackers = |probe: &Chessboard| -> Vec<Piece> {
+        board
+            .pieces(color)
+            .into_iter()
+            .filter(|piece| piece.location != square)
+            .filter(|piece| piece.can_move(probe).contains(&square))
+            .collect()
+    };
+
+    // An enemy piece already on the square does the job of the stand-in, which saves a copy of the board
+    match board.get_piece_at(square) {
+        Some(piece) if piece.color != color => attackers(board),
+        _ => {
+            let mut probe = board.clone();
+            let stand_in = Piece { piece_type: Pawn, color: color.opposite(), location: square };
+            if probe.add_piece(stand_in).is_err() {
+                return vec![];
+            }
+            attackers(&probe)
+        }
+    }
+}
+
+pub fn is_square_attacked(board: &Chessboard, square: Square, color: Color) -> bool {
+    !attackers_of(board, square, color).is_empty()
+}
+
+/// Number of pieces of `color` attacking each square, indexed as `counts[y][x]` like the board.
+pub fn attack_counts(board: &Chessboard, color: Color) -> Vec<Vec<usize>> {
+    (0..board.height())
+        .map(|y| {
+            (0..board.width())
+                .map(|x| attackers_of(board, Square { x, y }, color).len())
+                .collect()
+        })
+        .collect()
+}
+
+/// Pieces of `color` that the opponent attacks and nothing of their own defends.
+pub fn hanging_pieces(board: &Chessboard, color: Color) -> Vec<Piece> {
+    board
+        .pieces(color)
+        .into_iter()
+        .filter(|piece| is_square_attacked(board, piece.location, color.opposite()))
+        .filter(|piece| !is_square_attacked(board, piece.location, color))
+        .collect()
+}
+
+/// Squares attacked by both colors.
+pub fn contested_squares(board: &Chessboard) -> Vec<Square> {
+    let white = attack_counts(board, Color::White);
+    let black = attack_counts(board, Color::Black);
+    let mut squares: Vec<Square> = vec![];
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            if white[y][x] > 0 && black[y][x] > 0 {
+                squares.push(Square { x, y });
+            }
+        }
+    }
+    squares
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attacks::{attack_counts, attackers_of, contested_squares, hanging_pieces, is_square_attacked};
+    use crate::board::{Chessboard, Square};
+    use crate::chess_piece::Color::{Black, White};
+    use crate::chess_piece::PieceType;
+
+    fn square(algebraic: &str) -> Square {
+        Square::algebraic_to_coords(algebraic).unwrap()
+    }
+
+    #[test]
+    fn attackers_of_occupied_square() {
+        let board = Chessboard::from_fen("8/8/8/3p4/4p3/2N5/8/4R3").unwrap();
+        let mut attackers: Vec<PieceType> = attackers_of(&board, square("e4"), White)
+            .iter()
+            .map(|piece| piece.piece_type)
+            .collect();
+        attackers.sort_by_key(|piece_type| piece_type.get_symbol().to_string());
+        assert_eq!(attackers, vec![PieceType::Knight, PieceType::Rook]);
+    }
+
+    #[test]
+    fn defenders_are_attackers_of_own_color() {
+        let board = Chessboard::from_fen("8/8/8/3p4/4p3/8/8/8").unwrap();
+        let defenders = attackers_of(&board, square("e4"), Black);
+        assert_eq!(defenders.len(), 1);
+        assert_eq!(defenders[0].location, square("d5"));
+    }
+
+    #[test]
+    fn pawns_attack_diagonals_not_forward() {
+        let board = Chessboard::from_fen("8/8/8/8/8/8/4P3/8").unwrap();
+        assert!(is_square_attacked(&board, square("d3"), White));
+        assert!(is_square_attacked(&board, square("f3"), White));
+        assert!(!is_square_attacked(&board, square("e3"), White));
+        assert!(!is_square_attacked(&board, square("e4"), White));
+    }
+
+    #[test]
+    fn sliders_do_not_attack_through_blockers() {
+        let board = Chessboard::from_fen("8/8/8/8/8/8/4P3/4R3").unwrap();
+        assert!(is_square_attacked(&board, square("e2"), White));
+        assert!(!is_square_attacked(&board, square("e3"), White));
+    }
+
+    #[test]
+    fn counting_attacks_per_square() {
+        let board = Chessboard::from_fen("8/8/8/8/8/8/8/R6R").unwrap();
+        let counts = attack_counts(&board, White);
+        assert_eq!(counts[7][3], 2);
+        assert_eq!(counts[3][0], 1);
+        assert_eq!(counts[3][3], 0);
+    }
+
+    #[test]
+    fn finding_hanging_pieces() {
+        let board = Chessboard::from_fen("8/8/8/3n4/8/8/3P4/3R4").unwrap();
+        let hanging = hanging_pieces(&board, Black);
+        assert_eq!(hanging.len(), 0);
+
+        let board = Chessboard::from_fen("8/8/8/3n4/8/8/8/3R4").unwrap();
+        let hanging = hanging_pieces(&board, Black);
+        assert_eq!(hanging.len(), 1);
+        assert_eq!(hanging[0].location, square("d5"));
+    }
+
+    #[test]
+    fn finding_contested_squares() {
+        let board = Chessboard::from_fen("8/8/8/8/4p3/8/8/3R4").unwrap();
+        assert_eq!(contested_squares(&board), vec![square("d3")]);
+    }
+}
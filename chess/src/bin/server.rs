@@ -0,0 +1,23 @@
+use std::env;
+use std::net::TcpListener;
+use std::process;
+
+use chess::server::Server;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+fn main() {
+    let address = env::args().nth(1).unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Could not listen on {}: {}", address, error);
+            process::exit(1);
+        }
+    };
+    println!("Serving the chess API on http://{}", address);
+    if let Err(error) = Server::new().serve(listener) {
+        eprintln!("Server stopped: {}", error);
+        process::exit(1);
+    }
+}
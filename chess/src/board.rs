@@ -1,118 +1,478 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
 use crate::chess_piece::Color::*;
-use crate::chess_piece::Piece;
+use crate::chess_piece::{Color, Piece};
 use crate::chess_piece::PieceType::*;
+use crate::error::ChessError;
+use crate::fairy::PieceRegistry;
 
 const BOARD_SIZE: usize = 8;
+/// Most files or ranks a board may have, one per letter of the alphabet
+pub const MAX_BOARD_SIZE: usize = 26;
+/// Fewest files or ranks a board may have, pawns need a rank to start from and one to promote on
+pub const MIN_BOARD_SIZE: usize = 2;
 const INITIAL_BOARD_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+pub const LOS_ALAMOS_FEN: &str = "rnqknr/pppppp/6/6/PPPPPP/RNQKNR";
+/// Needs the Archbishop (A) and Chancellor (C) from `PieceRegistry::with_common_fairies`
+pub const CAPABLANCA_FEN: &str = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR";
+pub const GARDNER_MINICHESS_FEN: &str = "rnbqk/ppppp/5/PPPPP/RNBQK";
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Square {
     pub x: usize,
     pub y: usize,
 }
 
-#[allow(dead_code)]
 impl Square {
-    pub fn algebraic_to_coords(algebraic: &str) -> Result<Self, &str> {
-        if let Some(letter) = algebraic.chars().nth(0) {
-            if let Some(number_char) = algebraic.chars().nth(1) {
-                if !letter.is_ascii_alphabetic() || !('a'..='h').contains(&letter) {
-                    return Err("Not a valid letter");
+    pub fn algebraic_to_coords(algebraic: &str) -> Result<Self, ChessError> {
+        Square::algebraic_to_coords_with_size(algebraic, BOARD_SIZE, BOARD_SIZE)
+    }
+
+    /// Same as `algebraic_to_coords` but for a board of any size, ranks may have several digits ("a10").
+    pub fn algebraic_to_coords_with_size(algebraic: &str, width: usize, height: usize) -> Result<Self, ChessError> {
+        let mut chars = algebraic.chars();
+        if let Some(letter) = chars.next() {
+            let number_part = chars.as_str();
+            if !number_part.is_empty() {
+                let x = (letter as usize).wrapping_sub('a' as usize);
+                if !letter.is_ascii_lowercase() || x >= width {
+                    return Err(ChessError::invalid_square(algebraic, "Not a valid letter"));
                 }
 
-                let number = match number_char.to_digit(10) {
-                    Some(n) if (1..=8).contains(&n) => n as usize,
-                    _ => return Err("Not a valid number"),
+                let number = match number_part.parse::<usize>() {
+                    Ok(n) if (1..=height).contains(&n) => n,
+                    _ => return Err(ChessError::invalid_square(algebraic, "Not a valid number")),
                 };
 
-                let x = (letter as usize) - ('a' as usize);
-                let y = 8 - number;
+                let y = height - number;
 
                 return Ok(Self { x, y });
             }
         }
-        Err("Not valid input")
+        Err(ChessError::invalid_square(algebraic, "Not valid input"))
+    }
+
+    /// From 0 for the a-file. The rank depends on the board's height, see `Chessboard::rank_of`.
+    pub fn file(&self) -> usize {
+        self.x
+    }
+
+    /// The square `dx` files to the right and `dy` rows down, as seen from white, if the
+    /// coordinates do not go negative. Use `Chessboard::offset` to stay on a particular board.
+    pub fn offset(&self, dx: i8, dy: i8) -> Option<Square> {
+        let x = self.x.checked_add_signed(isize::from(dx))?;
+        let y = self.y.checked_add_signed(isize::from(dy))?;
+        Some(Square { x, y })
+    }
+
+    /// The step from this square towards `other` if they share a file, rank or diagonal.
+    pub fn direction_to(&self, other: Square) -> Option<(i8, i8)> {
+        let dx = other.x as isize - self.x as isize;
+        let dy = other.y as isize - self.y as isize;
+        if (dx, dy) == (0, 0) || (dx != 0 && dy != 0 && dx.abs() != dy.abs()) {
+            return None;
+        }
+        Some((dx.signum() as i8, dy.signum() as i8))
+    }
+
+    /// The squares strictly between this one and `other`, empty unless they share a file, rank
+    /// or diagonal.
+    pub fn between(&self, other: Square) -> Vec<Square> {
+        let mut squares = vec![];
+        if let Some((dx, dy)) = self.direction_to(other) {
+            let mut square = *self;
+            while let Some(next) = square.offset(dx, dy).filter(|next| *next != other) {
+                squares.push(next);
+                square = next;
+            }
+        }
+        squares
     }
 }
 
-#[allow(dead_code)]
-#[derive(Clone, Copy)]
+/// Parses squares of a standard 8x8 board only, `Chessboard::algebraic_to_square` reads them
+/// for any size.
+impl FromStr for Square {
+    type Err = ChessError;
+
+    fn from_str(algebraic: &str) -> Result<Square, ChessError> {
+        Square::algebraic_to_coords(algebraic)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Chessboard {
-    pub board: [[Option<Piece>; BOARD_SIZE]; BOARD_SIZE],
+    width: usize,
+    height: usize,
+    /// Indexed as `board[y][x]`, with y = 0 being the top (black) rank
+    board: Vec<Vec<Option<Piece>>>,
+    /// Movement definitions for `PieceType::Fairy` pieces on this board
+    fairy_pieces: Arc<PieceRegistry>,
+}
+
+impl Default for Chessboard {
+    fn default() -> Self {
+        Chessboard::new()
+    }
 }
 
-#[allow(dead_code)]
 impl Chessboard {
     pub fn new() -> Chessboard {
-        Chessboard{
-            board: [[None; BOARD_SIZE]; BOARD_SIZE],
+        Chessboard::empty(BOARD_SIZE, BOARD_SIZE)
+    }
+
+    /// An empty board, with `MIN_BOARD_SIZE` to `MAX_BOARD_SIZE` files and ranks.
+    pub fn with_size(width: usize, height: usize) -> Result<Chessboard, ChessError> {
+        let sizes = MIN_BOARD_SIZE..=MAX_BOARD_SIZE;
+        if !sizes.contains(&width) || !sizes.contains(&height) {
+            return Err(ChessError::invalid_position(&format!(
+                "A board has {} to {} files and ranks, not {}x{}",
+                MIN_BOARD_SIZE, MAX_BOARD_SIZE, width, height
+            )));
+        }
+        Ok(Chessboard::empty(width, height))
+    }
+
+    fn empty(width: usize, height: usize) -> Chessboard {
+        Chessboard {
+            width,
+            height,
+            board: vec![vec![None; width]; height],
+            fairy_pieces: Arc::new(PieceRegistry::new()),
         }
     }
+
+    /// An empty board of the same size, knowing the same fairy pieces.
+    pub fn cleared(&self) -> Chessboard {
+        Chessboard { fairy_pieces: self.fairy_pieces.clone(), ..Chessboard::empty(self.width, self.height) }
+    }
+
     pub fn new_with_pieces() -> Chessboard {
-        let board = Chessboard::from_fen(INITIAL_BOARD_FEN).unwrap();
-        board
+        Chessboard::from_fen(INITIAL_BOARD_FEN).unwrap()
     }
 
     /// VERY simple FEN parsing function for initializing pieces on board.
-    /// Does not support active color, en passant, castling, Half/full move
-    pub fn from_fen(fen: &str) -> Result<Chessboard, &str>{
-        let mut board = Chessboard::new();
-        let mut row = 0; 
-        let mut col = 0;
-
-        for c in fen.chars() {
-            match c {
-                '/' => {
-                    row += 1;
-                    col = 0; 
+    /// Only reads the piece placement, any further fields (active color, castling...) are ignored.
+    /// The board size is taken from the FEN, so "rnbqk/ppppp/5/PPPPP/RNBQK" gives a 5x5 board.
+    pub fn from_fen(fen: &str) -> Result<Chessboard, ChessError>{
+        Chessboard::from_fen_with_registry(fen, Arc::new(PieceRegistry::new()))
+    }
+
+    /// Like `from_fen`, but letters that are not standard pieces are looked up in the registry.
+    pub fn from_fen_with_registry(fen: &str, fairy_pieces: Arc<PieceRegistry>) -> Result<Chessboard, ChessError>{
+        Chessboard::from_fen_with_promotions(fen, fairy_pieces).map(|(board, _)| board)
+    }
+
+    /// Like `from_fen_with_registry`, also returning the squares of pieces marked with a '~' after
+    /// their letter, which Crazyhouse FEN uses for promoted pawns.
+    pub(crate) fn from_fen_with_promotions(
+        fen: &str,
+        fairy_pieces: Arc<PieceRegistry>,
+    ) -> Result<(Chessboard, Vec<Square>), ChessError> {
+        let placement = fen.trim_start().split(' ').next().unwrap_or("");
+        let offset = fen.len() - fen.trim_start().len();
+        let rows: Vec<&str> = placement.split('/').collect();
+        let height = rows.len();
+        let width = Chessboard::fen_row_width(rows[0]);
+        if width == Some(0) {
+            return Err(ChessError::invalid_fen(offset, 0, "Empty row"));
+        }
+        let width = match width {
+            Some(width) if height <= MAX_BOARD_SIZE => width,
+            _ => return Err(ChessError::invalid_fen(offset, 0, "Board is larger than 26 files or ranks")),
+        };
+        if width < MIN_BOARD_SIZE || height < MIN_BOARD_SIZE {
+            return Err(ChessError::invalid_fen(offset, 0, "Board needs at least 2 files and 2 ranks"));
+        }
+
+        let mut board = Chessboard::empty(width, height);
+        board.fairy_pieces = fairy_pieces;
+        let mut promoted = vec![];
+        let mut row_start = offset;
+        for (row, pieces) in rows.iter().enumerate() {
+            if Chessboard::fen_row_width(pieces) != Some(width) {
+                return Err(ChessError::invalid_fen(row_start, 0, "Rows are not of equal length"));
+            }
+            let mut col = 0;
+            let mut empty = 0;
+            let mut after_piece = false;
+            for (index, c) in pieces.char_indices() {
+                if let Some(digit) = c.to_digit(10) {
+                    empty = empty * 10 + digit as usize;
+                    after_piece = false;
+                    continue;
+                }
+                if c == '~' {
+                    if !after_piece {
+                        return Err(ChessError::invalid_fen(row_start + index, 0, "'~' has to follow a piece"));
+                    }
+                    promoted.push(Square { x: col - 1, y: row });
+                    after_piece = false;
+                    continue;
                 }
-                '1'..='8' => {
-                    col += c.to_digit(10).unwrap() as usize;
+                col += empty;
+                empty = 0;
+
+                let color = if c.is_lowercase() { Black } else { White };
+                let piece_type = match c.to_ascii_lowercase() {
+                    'p' => Pawn,
+                    'n' => Knight,
+                    'b' => Bishop,
+                    'r' => Rook,
+                    'q' => Queen,
+                    'k' => King,
+                    _ if board.fairy_pieces.get(c).is_some() => Fairy(c.to_ascii_uppercase()),
+                    _ => return Err(ChessError::invalid_fen(row_start + index, 0, "Invalid piece")),
+                };
+
+                let piece = Piece {
+                    piece_type,
+                    color,
+                    location: Square { y: row, x: col},
+                };
+                board.add_piece(piece)?;
+                col += 1;
+                after_piece = true;
+            }
+            row_start += pieces.len() + 1;
+        }
+        Ok((board, promoted))
+    }
+
+    /// Number of files a FEN row describes, counting multi-digit gaps like "10" as one number.
+    /// `None` if there are more than `MAX_BOARD_SIZE`.
+    fn fen_row_width(row: &str) -> Option<usize> {
+        let mut width: usize = 0;
+        let mut empty: usize = 0;
+        for c in row.chars().filter(|c| *c != '~') {
+            match c.to_digit(10) {
+                Some(digit) => empty = empty.checked_mul(10)?.checked_add(digit as usize)?,
+                None => {
+                    width = width.checked_add(empty)?.checked_add(1)?;
+                    empty = 0;
                 }
-                _ => {
-                    let color = if c.is_lowercase() { Black } else { White };
-                    let piece_type = match c.to_ascii_lowercase() {
-                        'p' => Pawn,
-                        'n' => Knight,
-                        'b' => Bishop,
-                        'r' => Rook,
-                        'q' => Queen,
-                        'k' => King,
-                        _ => return Err("Invalid piece"), 
-                    };
-
-                    let piece = Piece {
-                        piece_type,
-                        color,
-                        location: Square { y: row, x: col},
-                    };
-                    board.add_piece(piece);
-                    col += 1; 
+            }
+            if width.saturating_add(empty) > MAX_BOARD_SIZE {
+                return None;
+            }
+        }
+        Some(width + empty)
+    }
+
+    pub fn algebraic_to_square(&self, algebraic: &str) -> Result<Square, ChessError> {
+        Square::algebraic_to_coords_with_size(algebraic, self.width, self.height)
+    }
+
+    /// The opposite of `algebraic_to_square`, "e4" for `Square { x: 4, y: 4 }` on a standard board
+    pub fn square_to_algebraic(&self, square: Square) -> String {
+        format!("{}{}", (b'a' + square.x as u8) as char, self.height - square.y)
+    }
+
+    /// From 0 for the first rank, the last being `height - 1`.
+    pub fn rank_of(&self, square: Square) -> usize {
+        self.height - 1 - square.y
+    }
+
+    /// The square's number going along the ranks, from 0 for a1 to `width * height - 1` for the
+    /// last square of the top rank.
+    pub fn square_to_index(&self, square: Square) -> usize {
+        self.rank_of(square) * self.width + square.x
+    }
+
+    /// The opposite of `square_to_index`, `None` past the last square.
+    pub fn index_to_square(&self, index: usize) -> Option<Square> {
+        (index < self.width * self.height)
+            .then(|| Square { x: index % self.width, y: self.height - 1 - index / self.width })
+    }
+
+    /// The piece placement part of a FEN, as read by `from_fen`.
+    pub fn to_fen(&self) -> String {
+        self.to_fen_with_promotions(&[])
+    }
+
+    /// `to_fen` with a '~' after the pieces on the `promoted` squares.
+    pub(crate) fn to_fen_with_promotions(&self, promoted: &[Square]) -> String {
+        let mut rows: Vec<String> = vec![];
+        for y in 0..self.height {
+            let mut row = String::new();
+            let mut empty = 0;
+            for x in 0..self.width {
+                match self.get_piece_at(Square { x, y }) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        row.push_str(&piece.fen_symbol());
+                        if promoted.contains(&piece.location) {
+                            row.push('~');
+                        }
+                    }
+                    None => empty += 1,
                 }
             }
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+            rows.push(row);
+        }
+        rows.join("/")
+    }
+
+    /// Bounds check for signed coordinates, as produced when stepping off a square
+    pub fn is_on_board(&self, x: i8, y: i8) -> bool {
+        (0..self.width as i8).contains(&x) && (0..self.height as i8).contains(&y)
+    }
+
+    pub fn contains(&self, square: Square) -> bool {
+        square.x < self.width && square.y < self.height
+    }
+
+    /// `Square::offset`, as long as the square stays on this board.
+    pub fn offset(&self, square: Square, dx: i8, dy: i8) -> Option<Square> {
+        square.offset(dx, dy).filter(|square| self.contains(*square))
+    }
+
+    /// The squares from `from` in steps of `(dx, dy)` up to the edge of the board, not including `from`.
+    pub fn ray(&self, from: Square, dx: i8, dy: i8) -> impl Iterator<Item = Square> + '_ {
+        std::iter::successors(self.offset(from, dx, dy), move |square| self.offset(*square, dx, dy))
+    }
+
+    /// The whole file, rank or diagonal through both squares from edge to edge, empty if they
+    /// are not on one.
+    pub fn line(&self, a: Square, b: Square) -> Vec<Square> {
+        let (dx, dy) = match a.direction_to(b) {
+            Some(direction) => direction,
+            None => return vec![],
+        };
+        let mut squares: Vec<Square> = self.ray(a, -dx, -dy).collect();
+        squares.reverse();
+        squares.push(a);
+        squares.extend(self.ray(a, dx, dy));
+        squares
+    }
+
+    /// The board upside down, rank 1 swapped with the last rank. Colors stay as they are.
+    pub fn flip_vertical(&self) -> Chessboard {
+        self.map_pieces(|piece| piece.with_location(Square { x: piece.location.x, y: self.height - 1 - piece.location.y }))
+    }
+
+    /// The board mirrored left to right, the a-file swapped with the last file.
+    pub fn flip_horizontal(&self) -> Chessboard {
+        self.map_pieces(|piece| piece.with_location(Square { x: self.width - 1 - piece.location.x, y: piece.location.y }))
+    }
+
+    /// White pieces turned black and black ones white, on the same squares.
+    pub fn swap_colors(&self) -> Chessboard {
+        self.map_pieces(|piece| Piece { color: piece.color.opposite(), ..piece })
+    }
+
+    /// A board of the same size with every piece replaced by `change(piece)`.
+    fn map_pieces(&self, change: impl Fn(Piece) -> Piece) -> Chessboard {
+        let mut board = self.cleared();
+        for piece in self.board.iter().flatten().flatten() {
+            let piece = change(*piece);
+            board.board[piece.location.y][piece.location.x] = Some(piece);
         }
-        Ok(board)
+        board
+    }
+
+    /// Number of files
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of ranks
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Movement definitions for `PieceType::Fairy` pieces on this board
+    pub fn fairy_pieces(&self) -> &Arc<PieceRegistry> {
+        &self.fairy_pieces
+    }
+
+    /// Contents of every square, in the order of `squares`.
+    pub(crate) fn contents(&self) -> impl Iterator<Item = Option<Piece>> + '_ {
+        self.board.iter().flatten().copied()
     }
 
+    /// Every square of the board, row by row from the top left.
+    pub fn squares(&self) -> impl Iterator<Item = Square> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| Square { x, y }))
+    }
+
+    fn check_square(&self, square: Square) -> Result<(), ChessError> {
+        if self.contains(square) {
+            Ok(())
+        } else {
+            let coords = format!("({}, {})", square.x, square.y);
+            Err(ChessError::invalid_square(coords, "Outside of the board"))
+        }
+    }
 
+    /// The piece on the square, or None if it is empty or not on the board at all
     pub fn get_piece_at(&self, square: Square) -> Option<Piece> {
-        self.board[square.y][square.x]
+        self.board.get(square.y)?.get(square.x).copied().flatten()
     }
 
-    pub fn add_piece(&mut self, piece: Piece) {
+    pub fn add_piece(&mut self, piece: Piece) -> Result<(), ChessError> {
         let piece_location = piece.location;
+        self.check_square(piece_location)?;
         self.board[piece_location.y][piece_location.x] = Some(piece);
+        Ok(())
     }
 
-    pub fn remove_piece(&mut self,square: Square) {
-        self.board[square.y][square.x] = None;
+    /// Empties the square, returning the piece that stood there.
+    pub fn remove_piece(&mut self,square: Square) -> Result<Option<Piece>, ChessError> {
+        self.check_square(square)?;
+        Ok(self.board[square.y][square.x].take())
     }
-    
+
+    /// Moves the piece standing on `from` to `to`, returning whatever was captured there.
+    pub fn move_piece(&mut self, from: Square, to: Square) -> Result<Option<Piece>, ChessError> {
+        self.check_square(to)?;
+        match self.remove_piece(from)? {
+            Some(mut piece) => {
+                let captured = self.get_piece_at(to);
+                piece.location = to;
+                self.add_piece(piece)?;
+                Ok(captured)
+            }
+            None => Err(ChessError::illegal_move("No piece on the starting square")),
+        }
+    }
+
+    pub fn pieces(&self, color: Color) -> Vec<Piece> {
+        self.board
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|piece| piece.color == color)
+            .copied()
+            .collect()
+    }
+
+    pub fn find_king(&self, color: Color) -> Option<Square> {
+        self.pieces(color)
+            .iter()
+            .find(|piece| piece.piece_type == King)
+            .map(|king| king.location)
+    }
+
     pub fn print_board(&self) {
+        println!("{}", self);
+    }
+
+    /// One line per rank with pieces as "W-K" / "B-Q" and empty squares as "*", as printed by `print_board`.
+    fn render(&self) -> String {
         let mut result = String::new();
 
-        for y in 0..BOARD_SIZE {
-            for x in 0..BOARD_SIZE {
+        for y in 0..self.height {
+            for x in 0..self.width {
                 match self.get_piece_at(Square { x, y }) {
                     Some(piece) => {
                         let symbol = match piece.color {
@@ -130,24 +490,49 @@ impl Chessboard {
                     }
                 }
 
-                if x < BOARD_SIZE - 1 {
+                if x < self.width - 1 {
                     result.push_str("  ");
                 }
             }
             result.push('\n');
 
-            if y < BOARD_SIZE - 1 {
+            if y < self.height - 1 {
                 result.push('\n');
             }
         }
-        println!("{}",result);
+        result
+    }
+}
+
+impl fmt::Display for Chessboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+/// Boards are stored as their FEN placement. Fairy pieces are looked up in
+/// `PieceRegistry::with_common_fairies` when reading them back.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Chessboard {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_fen())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Chessboard {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Chessboard::from_fen_with_registry(&fen, Arc::new(PieceRegistry::with_common_fairies()))
+            .map_err(serde::de::Error::custom)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::board::{Chessboard, Square};
-    use crate::chess_piece::{Color, PieceType};
+    use crate::board::{Chessboard, Square, GARDNER_MINICHESS_FEN, LOS_ALAMOS_FEN};
+    use crate::chess_piece::{Color, Piece, PieceType};
+    use crate::error::ChessError;
 
     fn assert_piece_at_square(chessboard: &Chessboard, square: Square, piece_type: PieceType, color: Color) {
         if let Some(piece) = chessboard.get_piece_at(square) {
@@ -234,12 +619,188 @@ mod tests {
     
     #[test]
     fn bad_algebraic_formatting() {
-        assert_eq!(Square::algebraic_to_coords("A1"), Err("Not a valid letter"));
-        assert_eq!(Square::algebraic_to_coords("I1"), Err("Not a valid letter"));
-        assert_eq!(Square::algebraic_to_coords("a0"), Err("Not a valid number"));
-        assert_eq!(Square::algebraic_to_coords("a9"), Err("Not a valid number"));
+        assert_eq!(Square::algebraic_to_coords("A1"), Err(ChessError::invalid_square("A1", "Not a valid letter")));
+        assert_eq!(Square::algebraic_to_coords("I1"), Err(ChessError::invalid_square("I1", "Not a valid letter")));
+        assert_eq!(Square::algebraic_to_coords("a0"), Err(ChessError::invalid_square("a0", "Not a valid number")));
+        assert_eq!(Square::algebraic_to_coords("a9"), Err(ChessError::invalid_square("a9", "Not a valid number")));
+    }
+
+    #[test]
+    fn board_size_taken_from_fen() {
+        let minichess = Chessboard::from_fen(GARDNER_MINICHESS_FEN).unwrap();
+        assert_eq!((minichess.width, minichess.height), (5, 5));
+        assert_piece_at_square(&minichess, Square { x: 4, y: 4 }, PieceType::King, Color::White);
+
+        let los_alamos = Chessboard::from_fen(LOS_ALAMOS_FEN).unwrap();
+        assert_eq!((los_alamos.width, los_alamos.height), (6, 6));
+        assert_piece_at_square(&los_alamos, Square { x: 3, y: 0 }, PieceType::King, Color::Black);
+    }
+
+    #[test]
+    fn multi_digit_empty_squares_in_fen() {
+        let board = Chessboard::from_fen("10/10/10/10/10/10/10/9K").unwrap();
+        assert_eq!((board.width, board.height), (10, 8));
+        assert_piece_at_square(&board, Square { x: 9, y: 7 }, PieceType::King, Color::White);
+    }
+
+    #[test]
+    fn uneven_fen_rows() {
+        assert_eq!(
+            Chessboard::from_fen("8/7/8/8/8/8/8/8").err(),
+            Some(ChessError::invalid_fen(2, 0, "Rows are not of equal length"))
+        );
+    }
+
+    #[test]
+    fn oversized_fen_rows() {
+        let too_wide = Some(ChessError::invalid_fen(0, 0, "Board is larger than 26 files or ranks"));
+        assert_eq!(Chessboard::from_fen("99999999999999999999999").err(), too_wide);
+        assert_eq!(Chessboard::from_fen("27/26").err(), too_wide);
+        assert_eq!(Chessboard::from_fen(&["1"; 27].join("/")).err(), too_wide);
+        assert_eq!(
+            Chessboard::from_fen("26/99999999999999999999999").err(),
+            Some(ChessError::invalid_fen(3, 0, "Rows are not of equal length"))
+        );
+        assert_eq!(Chessboard::from_fen("26/26").unwrap().width, 26);
+    }
+
+    #[test]
+    fn board_size_limits() {
+        let too_small = Some(ChessError::invalid_fen(0, 0, "Board needs at least 2 files and 2 ranks"));
+        assert_eq!(Chessboard::from_fen("P").err(), too_small);
+        assert_eq!(Chessboard::from_fen("1/K").err(), too_small);
+        assert_eq!(Chessboard::from_fen("K1").err(), too_small);
+        assert!(Chessboard::from_fen("k1/1K").is_ok());
+
+        assert!(Chessboard::with_size(2, 2).is_ok());
+        assert!(Chessboard::with_size(26, 26).is_ok());
+        for (width, height) in [(8, 1), (0, 8), (27, 8), (8, 27)] {
+            assert!(matches!(Chessboard::with_size(width, height), Err(ChessError::InvalidPosition { .. })));
+        }
+    }
+
+    #[test]
+    fn invalid_piece_position_in_fen() {
+        assert_eq!(
+            Chessboard::from_fen("8/8/3x4/8/8/8/8/8").err(),
+            Some(ChessError::invalid_fen(5, 0, "Invalid piece"))
+        );
+    }
+
+    #[test]
+    fn full_fen_only_uses_piece_placement() {
+        let board = Chessboard::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_piece_at_square(&board, Square { x: 4, y: 7 }, PieceType::King, Color::White);
+    }
+
+    #[test]
+    fn squares_outside_the_board() {
+        let mut board = Chessboard::new();
+        let outside = Square { x: 8, y: 3 };
+        assert!(board.get_piece_at(outside).is_none());
+        assert!(matches!(board.remove_piece(outside), Err(ChessError::InvalidSquare { .. })));
+        let piece = Piece { piece_type: PieceType::Rook, color: Color::White, location: outside };
+        assert!(matches!(board.add_piece(piece), Err(ChessError::InvalidSquare { .. })));
+        assert!(matches!(board.move_piece(Square { x: 0, y: 0 }, outside), Err(ChessError::InvalidSquare { .. })));
+    }
+
+    #[test]
+    fn algebraic_to_coordinates_with_size() {
+        assert_eq!(Square::algebraic_to_coords_with_size("a10", 10, 10), Ok(Square { x: 0, y: 0 }));
+        assert_eq!(Square::algebraic_to_coords_with_size("j1", 10, 8), Ok(Square { x: 9, y: 7 }));
+        assert_eq!(Square::algebraic_to_coords_with_size("f1", 5, 5), Err(ChessError::invalid_square("f1", "Not a valid letter")));
+        assert_eq!(Square::algebraic_to_coords_with_size("a6", 5, 5), Err(ChessError::invalid_square("a6", "Not a valid number")));
+    }
+
+    #[test]
+    fn squares_back_to_algebraic() {
+        let board = Chessboard::with_size(10, 10).unwrap();
+        assert_eq!(board.square_to_algebraic(Square { x: 0, y: 0 }), "a10");
+        assert_eq!(board.square_to_algebraic(Square { x: 0, y: 9 }), "a1");
+        assert_eq!(Chessboard::new().square_to_algebraic(Square { x: 4, y: 4 }), "e4");
+    }
+
+    #[test]
+    fn writing_fen() {
+        for fen in ["rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR", LOS_ALAMOS_FEN, "8/8/8/8/8/8/8/8", "k9/10/10/10/10/10/10/9K"] {
+            assert_eq!(Chessboard::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn squares_as_text_and_indices() {
+        let board = Chessboard::new();
+        let e4: Square = "e4".parse().unwrap();
+        assert_eq!(e4, Square { x: 4, y: 4 });
+        assert_eq!(board.square_to_algebraic(e4), "e4");
+        assert!("e9".parse::<Square>().is_err());
+        assert_eq!((e4.file(), board.rank_of(e4), board.square_to_index(e4)), (4, 3, 28));
+        assert_eq!(board.index_to_square(0), Some(Square { x: 0, y: 7 }));
+        assert_eq!(board.index_to_square(63).map(|square| board.square_to_algebraic(square)), Some("h8".to_string()));
+        assert_eq!(board.index_to_square(64), None);
+        assert!(board.squares().all(|square| board.index_to_square(board.square_to_index(square)) == Some(square)));
+        assert_eq!(board.squares().count(), 64);
+
+        let tall = Chessboard::with_size(8, 10).unwrap();
+        let a10 = Square { x: 0, y: 0 };
+        assert_eq!((tall.rank_of(a10), tall.square_to_index(a10)), (9, 72));
+        assert_eq!(tall.index_to_square(79).map(|square| tall.square_to_algebraic(square)), Some("h10".to_string()));
+        assert!(tall.squares().all(|square| tall.index_to_square(tall.square_to_index(square)) == Some(square)));
+        assert_eq!(Chessboard::from_fen(GARDNER_MINICHESS_FEN).unwrap().squares().count(), 25);
+    }
+
+    #[test]
+    fn stepping_and_rays() {
+        let board = Chessboard::new();
+        let square = |algebraic: &str| Square::algebraic_to_coords(algebraic).unwrap();
+        assert_eq!(square("a1").offset(-1, 0), None);
+        assert_eq!(board.offset(square("h4"), 1, 0), None);
+        assert_eq!(board.offset(square("b1"), 1, -2), Some(square("c3")));
+        let ray: Vec<String> = board.ray(square("c1"), 1, -1).map(|square| board.square_to_algebraic(square)).collect();
+        assert_eq!(ray, vec!["d2", "e3", "f4", "g5", "h6"]);
+
+        assert_eq!(square("a1").between(square("d4")), vec![square("b2"), square("c3")]);
+        assert_eq!(square("e8").between(square("e5")), vec![square("e7"), square("e6")]);
+        assert!(square("a1").between(square("b3")).is_empty());
+        assert!(square("a1").between(square("b2")).is_empty());
+        assert_eq!(square("g1").direction_to(square("b6")), Some((-1, -1)));
+        let line: Vec<String> = board.line(square("c3"), square("d2")).iter().map(|square| board.square_to_algebraic(*square)).collect();
+        assert_eq!(line, vec!["a5", "b4", "c3", "d2", "e1"]);
+        assert!(board.line(square("a1"), square("b3")).is_empty());
+    }
+
+    #[test]
+    fn flipping_and_mirroring() {
+        let board = Chessboard::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R").unwrap();
+        assert_eq!(board.flip_vertical().to_fen(), "RNBQKB1R/PPPP1PPP/5N2/4P3/4p3/8/pppp1ppp/rnbqkbnr");
+        assert_eq!(board.flip_horizontal().to_fen(), "rnbkqbnr/ppp1pppp/8/3p4/3P4/2N5/PPP1PPPP/R1BKQBNR");
+        assert_eq!(board.swap_colors().to_fen(), "RNBQKBNR/PPPP1PPP/8/4P3/4p3/5n2/pppp1ppp/rnbqkb1r");
+        assert_eq!(board.flip_horizontal().flip_horizontal().to_fen(), board.to_fen());
+        // The black pawn from e5 ends up on e4, knowing where it stands
+        let e4 = Square::algebraic_to_coords("e4").unwrap();
+        let pawn = board.flip_vertical().get_piece_at(e4).unwrap();
+        assert_eq!((pawn.location, pawn.color), (e4, Color::Black));
+
+        let minichess = Chessboard::from_fen(GARDNER_MINICHESS_FEN).unwrap();
+        assert_eq!(minichess.flip_vertical().swap_colors().to_fen(), GARDNER_MINICHESS_FEN);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn boards_serialize_as_fen() {
+        // Plain from_fen does not know the Archbishop and Chancellor, deserializing does
+        let without_registry = Chessboard::from_fen(crate::board::CAPABLANCA_FEN).unwrap_err();
+        assert!(matches!(without_registry, ChessError::InvalidFen { .. }));
+
+        let json = serde_json::to_string(&Chessboard::from_fen(GARDNER_MINICHESS_FEN).unwrap()).unwrap();
+        assert_eq!(json, format!("\"{}\"", GARDNER_MINICHESS_FEN));
+        let board: Chessboard = serde_json::from_str(&format!("\"{}\"", crate::board::CAPABLANCA_FEN)).unwrap();
+        assert_eq!(board.width, 10);
+        assert!(serde_json::from_str::<Chessboard>("\"8/8/3x4\"").is_err());
+
+        let square: Square = serde_json::from_str(r#"{"x":4,"y":6}"#).unwrap();
+        assert_eq!(square, Square { x: 4, y: 6 });
     }
-    
 }
 
 
@@ -0,0 +1,286 @@
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::{Color, Piece, PieceType};
+use crate::error::ChessError;
+use crate::game::Game;
+use crate::moves::{home_rank, is_in_check, king_start_file};
+use crate::position::{CastlingRights, Position};
+use crate::variant::Variant;
+
+/// Sets up a position piece by piece, for editors and puzzles composed by hand. Anything goes
+/// while editing, `build` then checks the position could come up in a game.
+#[derive(Debug, Clone)]
+pub struct PositionBuilder {
+    position: Position,
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        PositionBuilder::new()
+    }
+}
+
+impl PositionBuilder {
+    /// An empty 8x8 board with white to move.
+    pub fn new() -> PositionBuilder {
+        PositionBuilder { position: Position::new(Chessboard::new(), Color::White) }
+    }
+
+    /// An empty board of another size, see `Chessboard::with_size` for the sizes allowed.
+    pub fn with_size(width: usize, height: usize) -> Result<PositionBuilder, ChessError> {
+        Ok(PositionBuilder { position: Position::new(Chessboard::with_size(width, height)?, Color::White) })
+    }
+
+    /// Starts from an existing position, to edit it.
+    pub fn from_position(position: Position) -> PositionBuilder {
+        PositionBuilder { position }
+    }
+
+    /// Puts a piece on the square, replacing whatever stood there.
+    pub fn piece(&mut self, square: Square, piece_type: PieceType, color: Color) -> Result<&mut Self, ChessError> {
+        self.position.board.add_piece(Piece { piece_type, color, location: square })?;
+        self.position.set_promoted(square, false);
+        Ok(self)
+    }
+
+    pub fn remove(&mut self, square: Square) -> Result<&mut Self, ChessError> {
+        self.position.board.remove_piece(square)?;
+        self.position.set_promoted(square, false);
+        Ok(self)
+    }
+
+    pub fn side_to_move(&mut self, color: Color) -> &mut Self {
+        self.position.side_to_move = color;
+        self
+    }
+
+    pub fn castling(&mut self, castling: CastlingRights) -> &mut Self {
+        self.position.castling = castling;
+        self
+    }
+
+    /// The square a pawn just skipped with its double step, `None` when there is none.
+    pub fn en_passant(&mut self, square: Option<Square>) -> Result<&mut Self, ChessError> {
+        if let Some(square) = square {
+            if !self.position.board.contains(square) {
+                return Err(ChessError::invalid_square(format!("({}, {})", square.x, square.y), "Not on the board"));
+            }
+        }
+        self.position.en_passant = square;
+        Ok(self)
+    }
+
+    pub fn move_numbers(&mut self, halfmove_clock: u32, fullmove_number: u32) -> &mut Self {
+        self.position.halfmove_clock = halfmove_clock;
+        self.position.fullmove_number = fullmove_number.max(1);
+        self
+    }
+
+    /// The position as it stands, whether it is valid or not.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Checks the position and returns it if it could come up in a game: one king a side, no
+    /// pawns on the first or last rank, castling rights only with the king and rook at home,
+    /// an en passant square only behind a pawn that just moved two squares, and the side that
+    /// just moved not left in check.
+    pub fn build(&self) -> Result<Position, ChessError> {
+        validate(&self.position)?;
+        Ok(self.position.clone())
+    }
+
+    /// `build`, then a game from the position under the given rules.
+    pub fn build_game(&self, variant: Box<dyn Variant>) -> Result<Game, ChessError> {
+        Ok(Game::from_position(self.build()?, variant))
+    }
+}
+
+fn validate(position: &Position) -> Result<(), ChessError> {
+    let board = &position.board;
+    for color in [Color::White, Color::Black] {
+        let pieces = board.pieces(color);
+        match pieces.iter().filter(|piece| piece.piece_type == PieceType::King).count() {
+            0 => return Err(ChessError::invalid_position(&format!("{:?} has no king", color))),
+            1 => {}
+            _ => return Err(ChessError::invalid_position(&format!("{:?} has more than one king", color))),
+        }
+        if pieces
+            .iter()
+            .any(|piece| piece.piece_type == PieceType::Pawn && (piece.location.y == 0 || piece.location.y == board.height() - 1))
+        {
+            return Err(ChessError::invalid_position(&format!("{:?} has a pawn on the first or last rank", color)));
+        }
+        validate_castling(position, color)?;
+    }
+    validate_en_passant(position)?;
+    if is_in_check(board, position.side_to_move.opposite()) {
+        return Err(ChessError::invalid_position("The side not to move is in check"));
+    }
+    Ok(())
+}
+
+fn validate_castling(position: &Position, color: Color) -> Result<(), ChessError> {
+    let board = &position.board;
+    let rank = home_rank(board, color);
+    let king_at_home = board.find_king(color) == Some(Square { x: king_start_file(board), y: rank });
+    let rook_on = |x: usize| {
+        board
+            .get_piece_at(Square { x, y: rank })
+            .is_some_and(|piece| piece.piece_type == PieceType::Rook && piece.color == color)
+    };
+    for (allowed, rook_x, side) in [
+        (position.castling.king_side(color), board.width() - 1, "king side"),
+        (position.castling.queen_side(color), 0, "queen side"),
+    ] {
+        if allowed && !(king_at_home && rook_on(rook_x)) {
+            return Err(ChessError::invalid_position(&format!(
+                "{:?} can only castle {} with the king and rook on their home squares",
+                color, side
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn validate_en_passant(position: &Position) -> Result<(), ChessError> {
+    let target = match position.en_passant {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let board = &position.board;
+    let mover = position.side_to_move.opposite();
+    // The pawn that just moved went from `from` over the target to `to`
+    let (from_y, to_y) = match mover {
+        Color::White => (board.height() - 2, board.height().checked_sub(4)),
+        Color::Black => (1, Some(3).filter(|y| *y < board.height())),
+    };
+    let pawn_moved = to_y.is_some_and(|to_y| {
+        (from_y + to_y) / 2 == target.y
+            && board
+                .get_piece_at(Square { x: target.x, y: to_y })
+                .is_some_and(|piece| piece.piece_type == PieceType::Pawn && piece.color == mover)
+    });
+    let path_empty =
+        board.get_piece_at(target).is_none() && board.get_piece_at(Square { x: target.x, y: from_y }).is_none();
+    if !pawn_moved || !path_empty {
+        return Err(ChessError::invalid_position(&format!(
+            "{} is not behind a pawn that just moved two squares",
+            board.square_to_algebraic(target)
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Square;
+    use crate::builder::PositionBuilder;
+    use crate::chess_piece::{Color, PieceType};
+    use crate::error::ChessError;
+    use crate::position::{CastlingRights, Position};
+    use crate::variant::Standard;
+
+    fn square(algebraic: &str) -> Square {
+        Square::algebraic_to_coords(algebraic).unwrap()
+    }
+
+    fn kings() -> PositionBuilder {
+        let mut builder = PositionBuilder::new();
+        builder.piece(square("e1"), PieceType::King, Color::White).unwrap();
+        builder.piece(square("e8"), PieceType::King, Color::Black).unwrap();
+        builder
+    }
+
+    fn reason(builder: &PositionBuilder) -> String {
+        match builder.build() {
+            Err(ChessError::InvalidPosition { reason }) => reason,
+            other => panic!("expected an invalid position, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn building_a_playable_position() {
+        let mut builder = kings();
+        builder
+            .piece(square("h1"), PieceType::Rook, Color::White)
+            .unwrap()
+            .piece(square("d5"), PieceType::Pawn, Color::Black)
+            .unwrap()
+            .piece(square("e5"), PieceType::Pawn, Color::White)
+            .unwrap();
+        builder
+            .castling(CastlingRights { white_king_side: true, ..CastlingRights::none() })
+            .en_passant(Some(square("d6")))
+            .unwrap()
+            .move_numbers(0, 30);
+        let position = builder.build().unwrap();
+        assert_eq!(position.to_fen(), "4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 30");
+
+        let mut game = builder.build_game(Box::new(Standard)).unwrap();
+        game.play(crate::moves::Move::new(square("e5"), square("d6"))).unwrap();
+        assert!(game.board().get_piece_at(square("d5")).is_none());
+    }
+
+    #[test]
+    fn kings_are_counted() {
+        let mut builder = PositionBuilder::new();
+        builder.piece(square("e1"), PieceType::King, Color::White).unwrap();
+        assert_eq!(reason(&builder), "Black has no king");
+        builder.piece(square("e8"), PieceType::King, Color::Black).unwrap();
+        builder.piece(square("a1"), PieceType::King, Color::White).unwrap();
+        assert_eq!(reason(&builder), "White has more than one king");
+        builder.remove(square("a1")).unwrap();
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn no_pawns_on_the_back_ranks() {
+        let mut builder = kings();
+        builder.piece(square("a8"), PieceType::Pawn, Color::White).unwrap();
+        assert_eq!(reason(&builder), "White has a pawn on the first or last rank");
+        builder.remove(square("a8")).unwrap().piece(square("h1"), PieceType::Pawn, Color::Black).unwrap();
+        assert_eq!(reason(&builder), "Black has a pawn on the first or last rank");
+    }
+
+    #[test]
+    fn castling_rights_need_the_king_and_rook_at_home() {
+        let mut builder = kings();
+        builder.piece(square("a8"), PieceType::Rook, Color::Black).unwrap();
+        builder.castling(CastlingRights { black_queen_side: true, ..CastlingRights::none() });
+        assert!(builder.build().is_ok());
+        builder.castling(CastlingRights { black_king_side: true, ..CastlingRights::none() });
+        assert_eq!(reason(&builder), "Black can only castle king side with the king and rook on their home squares");
+
+        let moved_king = PositionBuilder::from_position(Position::from_fen("r3k2r/8/8/8/8/8/4K3/R6R w KQkq - 0 1").unwrap());
+        assert_eq!(reason(&moved_king), "White can only castle king side with the king and rook on their home squares");
+        let king_on_f1 = PositionBuilder::from_position(Position::from_fen("4k3/8/8/8/8/8/8/R4K1R w Q - 0 1").unwrap());
+        assert_eq!(reason(&king_on_f1), "White can only castle queen side with the king and rook on their home squares");
+    }
+
+    #[test]
+    fn en_passant_only_behind_a_double_step() {
+        let builder = |fen: &str| PositionBuilder::from_position(Position::from_fen(fen).unwrap());
+        assert!(builder("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").build().is_ok());
+        assert_eq!(reason(&builder("4k3/8/8/8/8/4P3/8/4K3 b - e3 0 1")), "e3 is not behind a pawn that just moved two squares");
+        assert_eq!(reason(&builder("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1")), "e3 is not behind a pawn that just moved two squares");
+        assert_eq!(reason(&builder("4k3/8/8/8/4P3/4N3/8/4K3 b - e3 0 1")), "e3 is not behind a pawn that just moved two squares");
+        assert_eq!(reason(&builder("4k3/8/8/8/4p3/8/8/4K3 b - e3 0 1")), "e3 is not behind a pawn that just moved two squares");
+        assert!(PositionBuilder::new().en_passant(Some(Square { x: 8, y: 0 })).is_err());
+    }
+
+    #[test]
+    fn board_sizes() {
+        assert_eq!(PositionBuilder::with_size(10, 8).unwrap().position().board.width(), 10);
+        assert!(matches!(PositionBuilder::with_size(8, 1), Err(ChessError::InvalidPosition { .. })));
+        assert!(matches!(PositionBuilder::with_size(27, 8), Err(ChessError::InvalidPosition { .. })));
+    }
+
+    #[test]
+    fn the_side_that_just_moved_is_not_in_check() {
+        let mut builder = kings();
+        builder.piece(square("e4"), PieceType::Rook, Color::White).unwrap();
+        assert_eq!(reason(&builder), "The side not to move is in check");
+        builder.side_to_move(Color::Black);
+        assert!(builder.build().is_ok());
+    }
+}
@@ -1,7 +1,8 @@
 use crate::board::{Chessboard, Square};
-use crate::moves::{bishop_movements, king_movements, knight_movements, pawn_movements, queen_movements, rook_movements};
+use crate::moves::{bishop_movements, fairy_movements, king_movements, knight_movements, pawn_movements, queen_movements, rook_movements};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]     
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -9,23 +10,58 @@ pub enum PieceType {
     Bishop,
     Queen,
     King,
+    /// A piece registered in the board's `PieceRegistry`, identified by its uppercase FEN letter
+    Fairy(char),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Color {
     Black,
     White,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match *self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+
+    /// Index used for per-color arrays (pockets, check counters...)
+    pub fn index(&self) -> usize {
+        match *self {
+            Color::Black => 0,
+            Color::White => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub piece_type: PieceType,
     pub color: Color,
     pub location: Square,
 }
 
+impl Piece {
+    /// The FEN letter, uppercase for white and lowercase for black
+    pub fn fen_symbol(&self) -> String {
+        match self.color {
+            Color::White => self.piece_type.get_symbol().to_string(),
+            Color::Black => self.piece_type.get_symbol().to_lowercase(),
+        }
+    }
+
+    pub fn with_location(&self, location: Square) -> Piece {
+        Piece { location, ..*self }
+    }
+}
+
 impl PieceMovement for Piece {
-    fn can_move(&self, board: Chessboard) -> Vec<Square>{
+    fn can_move(&self, board: &Chessboard) -> Vec<Square>{
         match self.piece_type {
             PieceType::Pawn => pawn_movements(board, *self),
             PieceType::Rook => rook_movements(board, *self),
@@ -33,15 +69,42 @@ impl PieceMovement for Piece {
             PieceType::Bishop => bishop_movements(board, *self), 
             PieceType::Queen => queen_movements(board, *self),
             PieceType::King => king_movements(board, *self),
+            PieceType::Fairy(_) => fairy_movements(board, *self),
         }
     }
 }
 
 pub trait PieceMovement {
-    fn can_move(&self, board: Chessboard) -> Vec<Square>;
+    fn can_move(&self, board: &Chessboard) -> Vec<Square>;
 }
 
 impl PieceType {
+    /// The standard piece for a FEN letter of either case
+    pub fn from_symbol(symbol: char) -> Option<PieceType> {
+        match symbol.to_ascii_lowercase() {
+            'p' => Some(PieceType::Pawn),
+            'n' => Some(PieceType::Knight),
+            'b' => Some(PieceType::Bishop),
+            'r' => Some(PieceType::Rook),
+            'q' => Some(PieceType::Queen),
+            'k' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+
+    /// Material value in centipawns. Fairy pieces have no agreed value, so they count as a rook.
+    pub fn value(&self) -> i32 {
+        match *self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 20000,
+            PieceType::Fairy(_) => 500,
+        }
+    }
+
     pub fn get_symbol(&self) -> &str {
         match *self {
             PieceType::Pawn => "P",
@@ -50,6 +113,13 @@ impl PieceType {
             PieceType::Bishop => "B",
             PieceType::Queen => "Q",
             PieceType::King => "K",
+            PieceType::Fairy(symbol) => {
+                const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+                match LETTERS.find(symbol) {
+                    Some(index) => &LETTERS[index..index + 1],
+                    None => "?",
+                }
+            }
         }
     }
 }
@@ -0,0 +1,23 @@
+use chess::tools::analyze_position;
+
+use crate::cli::Options;
+
+const DEFAULT_DEPTH: u32 = 4;
+const DEFAULT_LINES: usize = 3;
+
+/// Prints the best `--lines` moves of `--fen` in the given `--variant`, each with its score and
+/// the continuation the engine expects.
+pub fn run(options: &Options) -> Result<(), String> {
+    let game = options.game()?;
+    let lines = options.parsed("lines")?.unwrap_or(DEFAULT_LINES);
+    if lines == 0 {
+        return Err("--lines must be at least 1".to_string());
+    }
+    let limits = options.search_limits(DEFAULT_DEPTH)?;
+    let analysis = analyze_position(game.variant(), game.position(), limits, &options.search_options()?, lines);
+    if analysis.lines.is_empty() {
+        return Err("The game is already over".to_string());
+    }
+    println!("{}", analysis);
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::fs;
+
+use chess::tools::{annotate_game, parse_pgn, Judgement};
+
+use crate::cli::Options;
+
+const DEFAULT_DEPTH: u32 = 3;
+
+/// Annotates every game in `--pgn` and writes them to `--output` or stdout. How many moves of
+/// each kind were found goes to stderr.
+pub fn run(options: &Options) -> Result<(), String> {
+    let path = options.get("pgn").ok_or("--pgn is required")?;
+    let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let mut games = parse_pgn(&text).map_err(|error| format!("{}: {}", path, error))?;
+    let limits = options.search_limits(DEFAULT_DEPTH)?;
+    let search_options = options.search_options()?;
+
+    let mut annotated = vec![];
+    for (index, pgn) in games.iter_mut().enumerate() {
+        let judged = annotate_game(&mut pgn.game, limits, &search_options);
+        let count = |judgement: Judgement| judged.iter().filter(|(_, other)| *other == judgement).count();
+        eprintln!(
+            "Game {} ({} - {}): {} inaccuracies, {} mistakes, {} blunders",
+            index + 1,
+            pgn.tag("White").unwrap_or("?"),
+            pgn.tag("Black").unwrap_or("?"),
+            count(Judgement::Inaccuracy),
+            count(Judgement::Mistake),
+            count(Judgement::Blunder)
+        );
+        annotated.push(pgn.to_pgn());
+    }
+
+    let output = annotated.join("\n");
+    match options.get("output") {
+        Some(file) => fs::write(file, output).map_err(|error| format!("Could not write {}: {}", file, error)),
+        None => {
+            print!("{}", output);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use chess::search::{search_with_options, SearchLimits, SearchOptions};
+use chess::variant::Standard;
+use chess::Position;
+
+use crate::cli::Options;
+
+const DEFAULT_DEPTH: u32 = 3;
+
+/// Middlegame and endgame positions with captures, checks and quiet play.
+const BENCH_FENS: [&str; 6] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r2q1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R2Q1RK1 w - - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "4rrk1/pp3ppp/2p5/8/2P1n3/1P2B3/P4PPP/3RR1K1 b - - 0 20",
+];
+
+/// Searches the bench positions with the move ordering heuristics, pruning and extensions added
+/// one at a time and prints the nodes each configuration needed.
+pub fn run(options: &Options) -> Result<(), String> {
+    let depth = options.parsed("depth")?.unwrap_or(DEFAULT_DEPTH);
+    let positions = BENCH_FENS
+        .iter()
+        .map(|fen| Position::from_fen(fen).map_err(|error| error.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Each configuration adds one feature to the one before
+    let mut search_options = SearchOptions::plain();
+    let mut configurations = vec![("plain alpha-beta".to_string(), search_options.clone())];
+    for feature in SearchOptions::FEATURES {
+        if let Some(enabled) = search_options.feature_mut(feature) {
+            *enabled = true;
+        }
+        configurations.push((format!("+ {}", feature), search_options.clone()));
+    }
+
+    println!("Depth {} on {} positions", depth, positions.len());
+    for (name, search_options) in configurations {
+        let started = Instant::now();
+        let nodes: u64 = positions
+            .iter()
+            .map(|position| search_with_options(&Standard, position, SearchLimits::depth(depth), &search_options).nodes)
+            .sum();
+        println!("{:<18} {:>12} nodes {:>8.2}s", name, nodes, started.elapsed().as_secs_f64());
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::Path;
+
+use chess::tools::{parse_pgn, strip_move_number, GameDatabase};
+use chess::Move;
+
+use crate::cli::Options;
+
+/// How many of the games reaching the position `explore` lists by default
+const DEFAULT_GAMES: usize = 5;
+
+/// Adds the games in `--pgn` to the database in `--db`, which is created if it does not exist.
+pub fn import(options: &Options) -> Result<(), String> {
+    let db_path = options.get("db").ok_or("--db is required")?;
+    let pgn_path = options.get("pgn").ok_or("--pgn is required")?;
+    let mut database = load(db_path)?;
+    let text = fs::read_to_string(pgn_path).map_err(|error| format!("Could not read {}: {}", pgn_path, error))?;
+    let games = parse_pgn(&text).map_err(|error| format!("{}: {}", pgn_path, error))?;
+    let added = database.import(&games).map_err(|error| format!("{}: {}", pgn_path, error))?;
+    fs::write(db_path, database.to_text()).map_err(|error| format!("Could not write {}: {}", db_path, error))?;
+    println!("Added {} games, {} in {}", added, database.games().len(), db_path);
+    Ok(())
+}
+
+/// Shows the moves played from `--fen`, or the start, after the SAN `--moves`, with how the
+/// games went on, then the first `--games` games that reached it.
+pub fn explore(options: &Options) -> Result<(), String> {
+    let db_path = options.get("db").ok_or("--db is required")?;
+    if !Path::new(db_path).exists() {
+        return Err(format!("No database at {}", db_path));
+    }
+    let database = load(db_path)?;
+    let mut game = options.game()?;
+    for san in options.get("moves").unwrap_or("").split_whitespace() {
+        let san = strip_move_number(san);
+        if san.is_empty() {
+            continue;
+        }
+        let mv = Move::from_san(game.variant(), game.position(), san).map_err(|error| error.to_string())?;
+        game.play(mv).map_err(|error| error.to_string())?;
+    }
+    let count = options.parsed("games")?.unwrap_or(DEFAULT_GAMES);
+
+    let explorer = database.explore(game.position());
+    if explorer.stats.games == 0 {
+        println!("No games reach {}", game.position().to_fen());
+        return Ok(());
+    }
+    println!("{}\n{}", game.position().to_fen(), explorer);
+    for found in database.games_reaching(game.position()).iter().take(count) {
+        println!("{} - {} {} ({}, {})", found.white, found.black, found.result, found.event, found.date);
+    }
+    Ok(())
+}
+
+fn load(path: &str) -> Result<GameDatabase, String> {
+    if !Path::new(path).exists() {
+        return Ok(GameDatabase::new());
+    }
+    let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    GameDatabase::from_text(&text).map_err(|error| format!("{}: {}", path, error))
+}
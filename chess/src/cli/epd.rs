@@ -0,0 +1,101 @@
+use std::fs;
+use std::io::{self, Write};
+use std::time::Duration;
+
+use chess::search::{format_score, SearchLimits, SearchOptions};
+use chess::tools::{parse_epd, EpdEntry};
+use chess::variant::Standard;
+use chess::Move;
+
+use crate::cli::Options;
+
+const DEFAULT_TIME: Duration = Duration::from_secs(1);
+
+/// Runs the engine on every test in `--file`, a second per position unless `--depth`, `--time`
+/// or `--nodes` say otherwise, and prints which passed.
+pub fn run(options: &Options) -> Result<(), String> {
+    let path = options.get("file").ok_or("--file is required")?;
+    let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let entries = parse_epd(&text).map_err(|error| format!("{}: {}", path, error))?;
+    let limits = if ["depth", "time", "nodes"].iter().any(|name| options.get(name).is_some()) {
+        options.search_limits(0)?
+    } else {
+        SearchLimits::time(DEFAULT_TIME)
+    };
+    let search_options = options.search_options()?;
+    run_suite(&entries, limits, &search_options, &mut io::stdout()).map_err(|error| error.to_string())?;
+    Ok(())
+}
+
+/// One line per test with the engine's move and the expected ones, then the total. Positions
+/// without `bm` or `am` are left out. Returns how many passed.
+pub fn run_suite(
+    entries: &[EpdEntry],
+    limits: SearchLimits,
+    options: &SearchOptions,
+    output: &mut impl Write,
+) -> io::Result<usize> {
+    let tests: Vec<&EpdEntry> = entries.iter().filter(|entry| entry.is_test()).collect();
+    let mut passed = 0;
+    for entry in &tests {
+        let result = entry.run(limits, options);
+        let played = match result.search.best_move {
+            Some(mv) => mv.to_san(&Standard, &entry.position),
+            None => "-".to_string(),
+        };
+        let san = |moves: &[Move]| {
+            moves.iter().map(|mv| mv.to_san(&Standard, &entry.position)).collect::<Vec<_>>().join(" ")
+        };
+        let mut expected = vec![];
+        if !entry.best_moves.is_empty() {
+            expected.push(format!("bm {}", san(&entry.best_moves)));
+        }
+        if !entry.avoid_moves.is_empty() {
+            expected.push(format!("am {}", san(&entry.avoid_moves)));
+        }
+        if result.passed {
+            passed += 1;
+        }
+        writeln!(
+            output,
+            "{:<12} {:<4} {:<8} {:>7}  depth {:<3} {}",
+            entry.id,
+            if result.passed { "ok" } else { "FAIL" },
+            played,
+            format_score(result.search.score),
+            result.search.depth,
+            expected.join("; ")
+        )?;
+    }
+    let percent = match tests.len() {
+        0 => 0.0,
+        total => 100.0 * passed as f64 / total as f64,
+    };
+    writeln!(output, "Passed {} of {} ({:.1}%)", passed, tests.len(), percent)?;
+    Ok(passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::search::{SearchLimits, SearchOptions};
+    use chess::tools::parse_epd;
+
+    use crate::cli::epd::run_suite;
+
+    #[test]
+    fn reporting_a_suite() {
+        let suite = "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Ra8#; id \"mate\";\n\
+            6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Rb1; id \"wrong\";\n\
+            6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - id \"no test\";\n";
+        let entries = parse_epd(suite).unwrap();
+        let mut output = vec![];
+        let passed = run_suite(&entries, SearchLimits::depth(2), &SearchOptions::default(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(passed, 1);
+        assert!(output.lines().next().unwrap().starts_with("mate         ok   Ra8#"));
+        assert!(output.contains("wrong        FAIL Ra8#"));
+        assert!(output.contains("bm Rb1"));
+        assert!(!output.contains("no test"));
+        assert!(output.ends_with("Passed 1 of 2 (50.0%)\n"));
+    }
+}
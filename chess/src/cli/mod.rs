@@ -0,0 +1,164 @@
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chess::eval::EvalParams;
+use chess::fairy::PieceRegistry;
+use chess::position::STANDARD_START_FEN;
+use chess::search::{SearchLimits, SearchOptions};
+use chess::variant::variant_from_name;
+use chess::{Game, Position};
+
+pub mod analyze;
+pub mod annotate;
+pub mod bench;
+pub mod database;
+pub mod epd;
+pub mod play;
+pub mod puzzle;
+pub mod tournament;
+pub mod tune;
+pub mod uci;
+
+/// Command line options as `--name value` pairs, in the order given.
+pub struct Options {
+    pairs: Vec<(String, String)>,
+}
+
+impl Options {
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut pairs = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => return Err(format!("Unexpected argument '{}'", arg)),
+            };
+            match args.next() {
+                Some(value) => pairs.push((name.to_string(), value.clone())),
+                None => return Err(format!("Missing value for --{}", name)),
+            }
+        }
+        Ok(Options { pairs })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs.iter().rev().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    pub fn parsed<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.get(name) {
+            Some(value) => value.parse().map(Some).map_err(|_| format!("Invalid value '{}' for --{}", value, name)),
+            None => Ok(None),
+        }
+    }
+
+    /// `--depth`, `--time` (seconds) and `--nodes`, any combination. Without any, `default_depth` is used.
+    pub fn search_limits(&self, default_depth: u32) -> Result<SearchLimits, String> {
+        let depth: Option<u32> = self.parsed("depth")?;
+        let time: Option<f64> = self.parsed("time")?;
+        let nodes: Option<u64> = self.parsed("nodes")?;
+        let mut limits = match (depth, time, nodes) {
+            (None, None, None) => SearchLimits::depth(default_depth),
+            (Some(depth), _, _) => SearchLimits::depth(depth),
+            _ => SearchLimits::depth(chess::search::MAX_DEPTH),
+        };
+        if let Some(seconds) = time {
+            if !(seconds > 0.0 && seconds.is_finite()) {
+                return Err("--time must be a positive number of seconds".to_string());
+            }
+            limits.time = Some(Duration::from_secs_f64(seconds));
+        }
+        limits.nodes = nodes;
+        Ok(limits)
+    }
+
+    /// `--eval` and `--threads`, the defaults for the rest.
+    pub fn search_options(&self) -> Result<SearchOptions, String> {
+        let threads = self.parsed("threads")?.unwrap_or(1);
+        if threads == 0 {
+            return Err("--threads must be at least 1".to_string());
+        }
+        Ok(SearchOptions { eval: self.eval_params()?, threads, ..SearchOptions::default() })
+    }
+
+    /// The game set up with `--variant` and `--fen`, standard chess from the start without them.
+    pub fn game(&self) -> Result<Game, String> {
+        let variant_name = self.get("variant").unwrap_or("standard");
+        let variant = variant_from_name(variant_name).ok_or(format!("Unknown variant '{}'", variant_name))?;
+        let fen = self.get("fen").unwrap_or(STANDARD_START_FEN);
+        let position = Position::from_fen_with_registry(fen, Arc::new(PieceRegistry::with_common_fairies()))
+            .map_err(|error| error.to_string())?;
+        Ok(Game::from_position(position, variant))
+    }
+
+    /// Evaluation weights from the file given with `--eval`, the standard ones without it.
+    pub fn eval_params(&self) -> Result<EvalParams, String> {
+        match self.get("eval") {
+            Some(path) => load_eval_params(path),
+            None => Ok(EvalParams::STANDARD),
+        }
+    }
+}
+
+/// Reads evaluation weights written by `chess tune`.
+pub fn load_eval_params(path: &str) -> Result<EvalParams, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    EvalParams::from_config(&text).map_err(|error| format!("{}: {}", path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess::eval::EvalParams;
+    use chess::search::SearchLimits;
+
+    use crate::cli::Options;
+
+    fn options(args: &[&str]) -> Result<Options, String> {
+        Options::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parsing_options() {
+        let options = options(&["--side", "black", "--depth", "4"]).unwrap();
+        assert_eq!(options.get("side"), Some("black"));
+        assert_eq!(options.parsed::<u32>("depth"), Ok(Some(4)));
+        assert_eq!(options.get("fen"), None);
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert!(options(&["play"]).is_err());
+        assert!(options(&["--depth"]).is_err());
+        assert!(options(&["--depth", "deep"]).unwrap().parsed::<u32>("depth").is_err());
+    }
+
+    #[test]
+    fn games_from_options() {
+        assert_eq!(options(&[]).unwrap().game().unwrap().position().to_fen(), chess::position::STANDARD_START_FEN);
+        let game = options(&["--variant", "atomic", "--fen", "4k3/8/8/8/8/8/8/4K3 w - - 0 1"]).unwrap().game().unwrap();
+        assert_eq!(game.variant().name(), "Atomic");
+        assert!(options(&["--variant", "losers"]).unwrap().game().is_err());
+        assert!(options(&["--fen", "4k3/8/8/8/8/8/8/4K3 x - - 0 1"]).unwrap().game().is_err());
+    }
+
+    #[test]
+    fn search_limits_from_options() {
+        assert_eq!(options(&[]).unwrap().search_limits(3), Ok(SearchLimits::depth(3)));
+        assert_eq!(options(&["--nodes", "1000"]).unwrap().search_limits(3), Ok(SearchLimits::nodes(1000)));
+        let limits = options(&["--time", "0.5", "--depth", "5"]).unwrap().search_limits(3).unwrap();
+        assert_eq!((limits.depth, limits.time), (5, Some(Duration::from_millis(500))));
+        assert!(options(&["--time", "-1"]).unwrap().search_limits(3).is_err());
+    }
+
+    #[test]
+    fn eval_params_from_options() {
+        assert_eq!(options(&[]).unwrap().eval_params(), Ok(EvalParams::STANDARD));
+        assert!(options(&["--eval", "/no/such/weights"]).unwrap().eval_params().is_err());
+        assert_eq!(options(&["--threads", "4"]).unwrap().search_options().map(|options| options.threads), Ok(4));
+        assert!(options(&["--threads", "0"]).unwrap().search_options().is_err());
+    }
+}
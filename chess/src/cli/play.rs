@@ -0,0 +1,189 @@
+use std::io::{self, BufRead, Write};
+
+use chess::rules::explain_illegal_move;
+use chess::search::{format_score, search_with_options, SearchLimits, SearchOptions};
+use chess::{Color, Game, Move, Outcome};
+
+use crate::cli::Options;
+
+const DEFAULT_DEPTH: u32 = 3;
+
+/// A game against the engine, set up from `--side`, `--fen`, `--variant`, the search options and limits.
+pub struct PlaySession {
+    pub game: Game,
+    pub human: Color,
+    pub limits: SearchLimits,
+    pub options: SearchOptions,
+}
+
+impl PlaySession {
+    pub fn from_options(options: &Options) -> Result<PlaySession, String> {
+        let human = match options.get("side").unwrap_or("white") {
+            "white" => Color::White,
+            "black" => Color::Black,
+            other => return Err(format!("Unknown side '{}', use white or black", other)),
+        };
+        let limits = options.search_limits(DEFAULT_DEPTH)?;
+        let search_options = options.search_options()?;
+        Ok(PlaySession { game: options.game()?, human, limits, options: search_options })
+    }
+
+    /// Alternates between reading the user's moves and answering them until the game ends,
+    /// the user quits or the input runs out.
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            if let Some(outcome) = self.game.outcome() {
+                writeln!(output, "{}", self.game.board())?;
+                let result = match outcome {
+                    Outcome::Win(color) if color == self.human => "You win!",
+                    Outcome::Win(_) => "The engine wins.",
+                    Outcome::Draw => "Draw.",
+                };
+                writeln!(output, "Game over: {}", result)?;
+                return Ok(());
+            }
+
+            if self.game.side_to_move() != self.human {
+                self.engine_move(output)?;
+                continue;
+            }
+
+            writeln!(output, "{}", self.game.board())?;
+            write!(output, "Your move ({}): ", color_name(self.human))?;
+            output.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            match line.trim() {
+                "quit" => return Ok(()),
+                "undo" => self.undo(output)?,
+                "moves" => {
+                    let board = self.game.board();
+                    let moves: Vec<String> = self.game.legal_moves().iter().map(|mv| mv.to_uci(board)).collect();
+                    writeln!(output, "{}", moves.join(" "))?;
+                }
+                "" => {}
+                text => match Move::from_uci(self.game.board(), text) {
+                    Err(error) => writeln!(output, "{} (type 'moves' to list the legal moves)", error)?,
+                    // Tell why the move is illegal rather than only that it is
+                    Ok(mv) => match explain_illegal_move(self.game.variant(), self.game.position(), mv) {
+                        Some(reason) => {
+                            writeln!(output, "{} (type 'moves' to list the legal moves)", reason.to_text(self.game.board()))?
+                        }
+                        None => {
+                            if let Err(error) = self.game.play(mv) {
+                                writeln!(output, "{}", error)?;
+                            }
+                        }
+                    },
+                },
+            }
+        }
+    }
+
+    fn engine_move(&mut self, output: &mut impl Write) -> io::Result<()> {
+        let result = search_with_options(self.game.variant(), self.game.position(), self.limits, &self.options);
+        let best_move = match result.best_move {
+            Some(mv) => mv,
+            None => return Ok(()),
+        };
+        let notation = best_move.to_uci(self.game.board());
+        if let Err(error) = self.game.play(best_move) {
+            writeln!(output, "The engine tried an illegal move {}: {}", notation, error)?;
+            return Ok(());
+        }
+        // Scores are shown from white's point of view, as usual
+        let score = match self.human {
+            Color::White => -result.score,
+            Color::Black => result.score,
+        };
+        writeln!(
+            output,
+            "Engine plays {} ({}, depth {}, {} nodes)",
+            notation,
+            format_score(score),
+            result.depth,
+            result.nodes
+        )
+    }
+
+    /// Takes back the engine's reply and the user's move before it.
+    fn undo(&mut self, output: &mut impl Write) -> io::Result<()> {
+        if self.game.ply() < 2 {
+            return writeln!(output, "Nothing to take back");
+        }
+        self.game.undo();
+        self.game.undo();
+        writeln!(output, "Took back the last move")
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::play::PlaySession;
+    use crate::cli::Options;
+
+    fn session(args: &[&str]) -> PlaySession {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        PlaySession::from_options(&Options::parse(&args).unwrap()).unwrap()
+    }
+
+    fn run(session: &mut PlaySession, input: &str) -> String {
+        let mut output = vec![];
+        session.run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn engine_answers_the_users_move() {
+        let mut session = session(&["--depth", "1"]);
+        let output = run(&mut session, "e2e4\nquit\n");
+        assert!(output.contains("Engine plays "));
+        assert_eq!(session.game.ply(), 2);
+    }
+
+    #[test]
+    fn engine_moves_first_when_user_plays_black() {
+        let mut session = session(&["--side", "black", "--nodes", "200"]);
+        run(&mut session, "");
+        assert_eq!(session.game.ply(), 1);
+    }
+
+    #[test]
+    fn illegal_input_is_explained() {
+        let mut session = session(&["--depth", "1"]);
+        let output = run(&mut session, "e2e5\nxyz\n");
+        assert!(output.contains("A pawn can not move from e2 to e5"));
+        assert!(output.contains("invalid notation 'xyz'"));
+        assert_eq!(session.game.ply(), 0);
+    }
+
+    #[test]
+    fn undo_takes_back_both_moves() {
+        let mut session = session(&["--depth", "1"]);
+        run(&mut session, "d2d4\nundo\n");
+        assert_eq!(session.game.ply(), 0);
+    }
+
+    #[test]
+    fn game_over_is_announced() {
+        let mut session = session(&["--fen", "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "--depth", "1"]);
+        let output = run(&mut session, "a1a8\n");
+        assert!(output.contains("Game over: You win!"));
+    }
+
+    #[test]
+    fn invalid_setup() {
+        let args: Vec<String> = ["--side", "red"].iter().map(|arg| arg.to_string()).collect();
+        assert!(PlaySession::from_options(&Options::parse(&args).unwrap()).is_err());
+    }
+}
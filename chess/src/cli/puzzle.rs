@@ -0,0 +1,166 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use chess::rules::explain_illegal_move;
+use chess::tools::{load_puzzles, numbered_line, san_line, Puzzle, PuzzleAttempt, PuzzleScore, PuzzleVerdict};
+use chess::variant::{Standard, Variant};
+use chess::{Color, Move};
+
+use crate::cli::Options;
+
+/// Puzzles from `--file` in the Lichess CSV format, optionally only those with `--theme`, at
+/// most `--count` of them.
+pub struct PuzzleSession {
+    pub puzzles: Vec<Puzzle>,
+    pub score: PuzzleScore,
+}
+
+impl PuzzleSession {
+    pub fn from_options(options: &Options) -> Result<PuzzleSession, String> {
+        let path = options.get("file").ok_or("--file is required")?;
+        let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        let mut puzzles = load_puzzles(&text).map_err(|error| format!("{}: {}", path, error))?;
+        if let Some(theme) = options.get("theme") {
+            puzzles.retain(|puzzle| puzzle.themes.iter().any(|other| other == theme));
+        }
+        if let Some(count) = options.parsed("count")? {
+            puzzles.truncate(count);
+        }
+        if puzzles.is_empty() {
+            return Err(format!("No puzzles in {}", path));
+        }
+        Ok(PuzzleSession { puzzles, score: PuzzleScore::default() })
+    }
+
+    /// Presents the puzzles one after another and checks the user's moves, until all are done,
+    /// the user quits or the input runs out. The first wrong move fails a puzzle.
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        let count = self.puzzles.len();
+        for (index, puzzle) in self.puzzles.iter().enumerate() {
+            let rating = puzzle.rating.map(|rating| format!(", rating {}", rating)).unwrap_or_default();
+            writeln!(output, "\nPuzzle {} of {} ({}{})", index + 1, count, puzzle.id, rating)?;
+            let mut attempt = PuzzleAttempt::new(puzzle);
+            let solved = loop {
+                let position = attempt.position().clone();
+                writeln!(output, "{}", position.board())?;
+                write!(output, "{} to move: ", color_name(position.side_to_move()))?;
+                output.flush()?;
+                let line = match lines.next() {
+                    Some(line) => line?,
+                    None => return Ok(()),
+                };
+                let mv = match line.trim() {
+                    "quit" => return Ok(()),
+                    "skip" => {
+                        show_solution(output, &attempt)?;
+                        break false;
+                    }
+                    "moves" => {
+                        let moves: Vec<String> =
+                            Standard.legal_moves(&position).iter().map(|mv| mv.to_uci(position.board())).collect();
+                        writeln!(output, "{}", moves.join(" "))?;
+                        continue;
+                    }
+                    "" => continue,
+                    text => match Move::from_uci(position.board(), text) {
+                        Err(error) => {
+                            writeln!(output, "{}", error)?;
+                            continue;
+                        }
+                        // Tell why the move is illegal rather than only that it is
+                        Ok(mv) => match explain_illegal_move(&Standard, &position, mv) {
+                            Some(reason) => {
+                                writeln!(output, "{} (type 'moves' to list the legal moves)", reason.to_text(position.board()))?;
+                                continue;
+                            }
+                            None => mv,
+                        },
+                    },
+                };
+                match attempt.play(mv) {
+                    Ok(PuzzleVerdict::Correct { reply }) => {
+                        let san = san_line(&Standard, &position, &[mv, reply]);
+                        writeln!(output, "Correct! The reply is {}", san.last().map_or("", String::as_str))?;
+                    }
+                    Ok(PuzzleVerdict::Solved) => {
+                        writeln!(output, "Solved!")?;
+                        break true;
+                    }
+                    Ok(PuzzleVerdict::Wrong { .. }) => {
+                        write!(output, "Wrong. ")?;
+                        show_solution(output, &attempt)?;
+                        break false;
+                    }
+                    Err(error) => writeln!(output, "{} (type 'moves' to list the legal moves)", error)?,
+                }
+            };
+            self.score.record(solved);
+            let score = &self.score;
+            writeln!(
+                output,
+                "Score: {} of {} solved, streak {} (best {})",
+                score.solved,
+                score.attempted(),
+                score.streak,
+                score.best_streak
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn show_solution(output: &mut impl Write, attempt: &PuzzleAttempt) -> io::Result<()> {
+    let san = san_line(&Standard, attempt.position(), attempt.remaining());
+    writeln!(output, "The solution was {}", numbered_line(attempt.position(), &san))
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "White",
+        Color::Black => "Black",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::tools::{load_puzzles, PuzzleScore};
+
+    use crate::cli::puzzle::PuzzleSession;
+
+    const PUZZLES: &str = "mate1,6k1/5ppp/8/8/8/8/1R6/R5K1 b - - 0 1,g8h8 a1a8\n\
+        mate2,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760\n";
+
+    fn run(input: &str) -> (PuzzleScore, String) {
+        let mut session = PuzzleSession { puzzles: load_puzzles(PUZZLES).unwrap(), score: PuzzleScore::default() };
+        let mut output = vec![];
+        session.run(input.as_bytes(), &mut output).unwrap();
+        (session.score, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn solving_puzzles() {
+        let (score, output) = run("b2b8\na2e6\nf7f8\n");
+        assert!(output.contains("Puzzle 2 of 2 (mate2, rating 1760)"));
+        assert!(output.contains("Correct! The reply is Kd8"));
+        assert_eq!((score.solved, score.failed, score.best_streak), (2, 0, 2));
+        assert!(output.contains("Score: 2 of 2 solved, streak 2 (best 2)"));
+    }
+
+    #[test]
+    fn wrong_and_skipped_puzzles() {
+        let (score, output) = run("a1a7\nskip\n");
+        assert!(output.contains("Wrong. The solution was 2. Ra8#"));
+        assert!(output.contains("The solution was 18. Be6+ Kd8 19. Qf8#"));
+        assert_eq!((score.solved, score.failed), (0, 2));
+    }
+
+    #[test]
+    fn illegal_moves_are_explained() {
+        let (score, output) = run("a1a9\nh1h2\na1g1\nquit\n");
+        assert!(output.contains("invalid notation 'a1a9'"));
+        assert!(output.contains("There is no piece on h1 (type 'moves' to list the legal moves)"));
+        assert!(output.contains("can not capture its own"));
+        assert_eq!(score.attempted(), 0);
+    }
+}
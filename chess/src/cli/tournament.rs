@@ -0,0 +1,179 @@
+use std::fs;
+use std::time::Duration;
+
+use chess::search::{SearchLimits, SearchOptions};
+use chess::tools::{load_openings, EnginePlayer, Player, Sprt, SprtResult, Tournament, UciPlayer};
+use chess::uci::UciEngine;
+use chess::{Color, Outcome};
+
+use crate::cli::{load_eval_params, Options};
+
+const DEFAULT_GAMES: usize = 10;
+const DEFAULT_DEPTH: u32 = 3;
+
+/// Builds a player from a spec like "depth=3", "nodes=20000,name=small,eval=tuned.txt,threads=2" or
+/// "uci=/usr/bin/stockfish,time=0.1": comma separated settings, with `uci` pointing at an
+/// external engine. Without `uci` the built-in engine plays, with the weights from `eval` if given
+/// and search features switched with their names from `SearchOptions::FEATURES`, like "lmr=off".
+pub fn player_from_spec(spec: &str) -> Result<Box<dyn Player>, String> {
+    let mut limits = SearchLimits::depth(DEFAULT_DEPTH);
+    let mut explicit_depth = false;
+    let mut name = None;
+    let mut executable = None;
+    let mut eval = None;
+    let mut threads = None;
+    let mut features = vec![];
+    for setting in spec.split(',').filter(|setting| !setting.is_empty()) {
+        let (key, value) = setting.split_once('=').ok_or(format!("Expected key=value in '{}'", setting))?;
+        let invalid = || format!("Invalid value '{}' for {}", value, key);
+        match key {
+            "depth" => {
+                limits.depth = value.parse().map_err(|_| invalid())?;
+                explicit_depth = true;
+            }
+            "nodes" => limits.nodes = Some(value.parse().map_err(|_| invalid())?),
+            "time" => {
+                let seconds: f64 = value.parse().map_err(|_| invalid())?;
+                if !(seconds > 0.0 && seconds.is_finite()) {
+                    return Err(invalid());
+                }
+                limits.time = Some(Duration::from_secs_f64(seconds));
+            }
+            "name" => name = Some(value.to_string()),
+            "uci" => executable = Some(value.to_string()),
+            "eval" => eval = Some(load_eval_params(value)?),
+            "threads" => threads = Some(value.parse().ok().filter(|threads| *threads > 0).ok_or_else(invalid)?),
+            _ if SearchOptions::FEATURES.contains(&key) => {
+                let enabled = match value {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(invalid()),
+                };
+                features.push((key, enabled));
+            }
+            _ => return Err(format!("Unknown engine setting '{}'", key)),
+        }
+    }
+    if !explicit_depth && (limits.time.is_some() || limits.nodes.is_some()) {
+        limits.depth = chess::search::MAX_DEPTH;
+    }
+
+    match executable {
+        Some(_) if eval.is_some() || threads.is_some() || !features.is_empty() => {
+            Err("eval, threads and search features only apply to the built-in engine".to_string())
+        }
+        Some(path) => {
+            let mut engine = UciEngine::start(&path, &[]).map_err(|error| error.to_string())?;
+            if let Some(name) = name {
+                engine.name = name;
+            }
+            Ok(Box::new(UciPlayer { engine, limits }))
+        }
+        None => {
+            let mut player = EnginePlayer::new(&name.unwrap_or_else(|| spec.to_string()), limits);
+            player.options.eval = eval.unwrap_or_default();
+            player.options.threads = threads.unwrap_or(1);
+            for (feature, enabled) in features {
+                if let Some(option) = player.options.feature_mut(feature) {
+                    *option = enabled;
+                }
+            }
+            Ok(Box::new(player))
+        }
+    }
+}
+
+/// Runs a match between `--first` and `--second` and prints every game and the final score.
+pub fn run(options: &Options) -> Result<(), String> {
+    let mut first = player_from_spec(options.get("first").unwrap_or(""))?;
+    let mut second = player_from_spec(options.get("second").unwrap_or(""))?;
+
+    let mut tournament = Tournament::new(options.parsed("games")?.unwrap_or(DEFAULT_GAMES));
+    if let Some(path) = options.get("openings") {
+        let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+        tournament.openings = load_openings(&text).map_err(|error| format!("{}: {}", path, error))?;
+    }
+    if let Some(bounds) = options.get("sprt") {
+        let parsed: Vec<f64> = bounds.split(',').filter_map(|bound| bound.trim().parse().ok()).collect();
+        match parsed.as_slice() {
+            [elo0, elo1] if elo0 < elo1 => tournament.sprt = Some(Sprt::new(*elo0, *elo1)),
+            _ => return Err("--sprt takes two Elo bounds like 0,10".to_string()),
+        }
+    }
+    if let Some(plies) = options.parsed("max-plies")? {
+        tournament.adjudication.max_plies = plies;
+    }
+
+    println!("{} vs {}", first.name(), second.name());
+    let mut played = 0;
+    let score = tournament
+        .run(first.as_mut(), second.as_mut(), |summary, score| {
+            played += 1;
+            let result = match summary.outcome {
+                Outcome::Win(Color::White) => "1-0",
+                Outcome::Win(Color::Black) => "0-1",
+                Outcome::Draw => "1/2-1/2",
+            };
+            println!(
+                "Game {}: {} - {} {} ({:?}, {} plies)  score {}-{}-{}",
+                played,
+                summary.white,
+                summary.black,
+                result,
+                summary.termination,
+                summary.record.moves.len(),
+                score.wins,
+                score.losses,
+                score.draws
+            );
+        })
+        .map_err(|error| error.to_string())?;
+
+    println!(
+        "\nScore of {} vs {}: {} - {} - {} [{:.3}]",
+        first.name(),
+        second.name(),
+        score.wins,
+        score.losses,
+        score.draws,
+        score.score()
+    );
+    match score.elo() {
+        Some((elo, margin)) => println!("Elo difference: {:.1} +/- {:.1}", elo, margin),
+        None => println!("Elo difference: not measurable yet"),
+    }
+    if let Some(sprt) = tournament.sprt {
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.test(&score) {
+            SprtResult::AcceptH1 => "H1 accepted",
+            SprtResult::AcceptH0 => "H0 accepted",
+            SprtResult::Continue => "no decision yet",
+        };
+        println!("SPRT: llr {:.2} ({:.2}, {:.2}), {}", sprt.llr(&score), lower, upper, verdict);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::tournament::player_from_spec;
+
+    #[test]
+    fn builtin_engine_specs() {
+        assert_eq!(player_from_spec("depth=2").unwrap().name(), "depth=2");
+        assert_eq!(player_from_spec("nodes=500,name=tiny").unwrap().name(), "tiny");
+        assert_eq!(player_from_spec("depth=2,lmr=off,null-move=on").unwrap().name(), "depth=2,lmr=off,null-move=on");
+    }
+
+    #[test]
+    fn invalid_specs() {
+        assert!(player_from_spec("depth").is_err());
+        assert!(player_from_spec("depth=deep").is_err());
+        assert!(player_from_spec("time=0").is_err());
+        assert!(player_from_spec("speed=3").is_err());
+        assert!(player_from_spec("uci=/no/such/engine").is_err());
+        assert!(player_from_spec("eval=/no/such/weights").is_err());
+        assert!(player_from_spec("threads=0").is_err());
+        assert!(player_from_spec("lmr=maybe").is_err());
+    }
+}
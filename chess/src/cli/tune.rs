@@ -0,0 +1,34 @@
+use std::fs;
+
+use chess::tools::{load_labeled_positions, Tuner};
+
+use crate::cli::Options;
+
+const DEFAULT_PASSES: usize = 500;
+
+/// Tunes the evaluation on the positions in `--positions`, starting from `--eval` or the standard
+/// weights, and writes the result to `--output` or stdout. Progress goes to stderr.
+pub fn run(options: &Options) -> Result<(), String> {
+    let path = options.get("positions").ok_or("--positions is required")?;
+    let text = fs::read_to_string(path).map_err(|error| format!("Could not read {}: {}", path, error))?;
+    let positions = load_labeled_positions(&text).map_err(|error| format!("{}: {}", path, error))?;
+    if positions.is_empty() {
+        return Err(format!("No positions in {}", path));
+    }
+    let start = options.eval_params()?;
+    let passes = options.parsed("passes")?.unwrap_or(DEFAULT_PASSES);
+
+    let tuner = Tuner::new(&positions);
+    let k = tuner.best_k(&start);
+    eprintln!("{} positions, K = {:.2}, error {:.6}", positions.len(), k, tuner.error(&start, k));
+    let tuned = tuner.tune(&start, k, passes, |pass, error| eprintln!("Pass {}: error {:.6}", pass, error));
+
+    let config = tuned.to_config();
+    match options.get("output") {
+        Some(output) => fs::write(output, config).map_err(|error| format!("Could not write {}: {}", output, error)),
+        None => {
+            print!("{}", config);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use chess::eval::EvalParams;
+use chess::search::{search_with_options, SearchLimits, SearchOptions, MAX_DEPTH};
+use chess::{ChessError, Color, Game, Move};
+
+use crate::cli::load_eval_params;
+
+const ENGINE_NAME: &str = "TDD chess";
+/// Share of the remaining clock spent on one move when the GUI only gives clock times
+const MOVES_TO_GO: u32 = 30;
+const MAX_THREADS: usize = 64;
+
+/// Speaks UCI on the given streams so GUIs and tournament runners can use the engine.
+pub fn run(input: impl BufRead, output: &mut impl Write) -> io::Result<()> {
+    let mut game = Game::new();
+    let mut options = SearchOptions::default();
+    for line in input.lines() {
+        let line = line?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                writeln!(output, "id name {}", ENGINE_NAME)?;
+                writeln!(output, "id author TDD-2024")?;
+                writeln!(output, "option name Threads type spin default 1 min 1 max {}", MAX_THREADS)?;
+                writeln!(output, "option name EvalFile type string default <empty>")?;
+                writeln!(output, "uciok")?;
+            }
+            Some("isready") => writeln!(output, "readyok")?,
+            Some("ucinewgame") => game = Game::new(),
+            Some("position") => match parse_position(&tokens[1..]) {
+                Ok(position) => game = position,
+                Err(error) => writeln!(output, "info string {}", error)?,
+            },
+            Some("setoption") => match parse_option(&tokens[1..]) {
+                Some((name, value)) if name == "Threads" => match value.parse() {
+                    Ok(threads) if (1..=MAX_THREADS).contains(&threads) => options.threads = threads,
+                    _ => writeln!(output, "info string Threads must be between 1 and {}", MAX_THREADS)?,
+                },
+                Some((name, value)) if name == "EvalFile" => match value.as_str() {
+                    "" | "<empty>" => options.eval = EvalParams::STANDARD,
+                    path => match load_eval_params(path) {
+                        Ok(params) => options.eval = params,
+                        Err(error) => writeln!(output, "info string {}", error)?,
+                    },
+                },
+                _ => writeln!(output, "info string Unknown option")?,
+            },
+            Some("go") => go(&game, &options, &tokens[1..], output)?,
+            Some("quit") => break,
+            _ => {}
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// "startpos" or "fen <fields>", optionally followed by "moves" and the moves played since.
+fn parse_position(tokens: &[&str]) -> Result<Game, ChessError> {
+    let moves_at = tokens.iter().position(|token| *token == "moves").unwrap_or(tokens.len());
+    let mut game = match tokens.first() {
+        Some(&"startpos") => Game::new(),
+        Some(&"fen") => Game::from_fen(&tokens[1..moves_at].join(" "))?,
+        _ => return Err(ChessError::invalid_notation(tokens.join(" "), "Expected startpos or fen")),
+    };
+    for uci in tokens.iter().skip(moves_at + 1) {
+        let mv = Move::from_uci(game.board(), uci)?;
+        game.play(mv)?;
+    }
+    Ok(game)
+}
+
+/// Name and value from "setoption name <name> [value <value>]".
+fn parse_option(tokens: &[&str]) -> Option<(String, String)> {
+    let (&first, rest) = tokens.split_first()?;
+    if first != "name" {
+        return None;
+    }
+    let value_at = rest.iter().position(|token| *token == "value").unwrap_or(rest.len());
+    let name = rest[..value_at].join(" ");
+    let value = rest.get(value_at + 1..).unwrap_or_default().join(" ");
+    Some((name, value))
+}
+
+/// Limits from "go": depth, nodes, movetime, or the clock of the side to move.
+fn parse_limits(tokens: &[&str], side_to_move: Color) -> SearchLimits {
+    let value = |name: &str| -> Option<u64> {
+        let at = tokens.iter().position(|token| *token == name)?;
+        tokens.get(at + 1)?.parse().ok()
+    };
+    let (clock, increment) = match side_to_move {
+        Color::White => (value("wtime"), value("winc")),
+        Color::Black => (value("btime"), value("binc")),
+    };
+    let movetime = value("movetime").or(clock.map(|clock| clock / MOVES_TO_GO as u64 + increment.unwrap_or(0) / 2));
+    SearchLimits {
+        depth: value("depth").map_or(MAX_DEPTH, |depth| depth as u32),
+        time: movetime.map(Duration::from_millis),
+        nodes: value("nodes"),
+    }
+}
+
+fn go(game: &Game, options: &SearchOptions, tokens: &[&str], output: &mut impl Write) -> io::Result<()> {
+    let mut limits = parse_limits(tokens, game.side_to_move());
+    if limits == SearchLimits::depth(MAX_DEPTH) {
+        // "go infinite" or a bare "go", there is no "stop" handling so search a sensible depth
+        limits = SearchLimits::default();
+    }
+    let result = search_with_options(game.variant(), game.position(), limits, options);
+    let score = match result.mate_in() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    let board = game.board();
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_uci(board)).collect();
+    writeln!(output, "info depth {} score {} nodes {} pv {}", result.depth, score, result.nodes, pv.join(" "))?;
+    match result.best_move {
+        Some(mv) => writeln!(output, "bestmove {}", mv.to_uci(board)),
+        None => writeln!(output, "bestmove 0000"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chess::search::SearchLimits;
+    use chess::Color;
+
+    use crate::cli::uci::{parse_limits, parse_option, parse_position, run};
+
+    fn session(input: &str) -> String {
+        let mut output = vec![];
+        run(input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        let output = session("uci\nisready\nquit\n");
+        assert!(output.contains("id name TDD chess"));
+        assert!(output.ends_with("uciok\nreadyok\n"));
+    }
+
+    #[test]
+    fn searching_a_position() {
+        let output = session("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 2\n");
+        assert!(output.contains("score mate 1"));
+        assert!(output.ends_with("bestmove a1a8\n"));
+    }
+
+    #[test]
+    fn positions_with_moves() {
+        let game = parse_position(&["startpos", "moves", "e2e4", "e7e5"]).unwrap();
+        assert_eq!(game.ply(), 2);
+        let game = parse_position(&["fen", "4k3/8/8/8/8/8/8/4K2R", "w", "K", "-", "0", "1", "moves", "e1g1"]).unwrap();
+        assert_eq!(game.position().to_fen(), "4k3/8/8/8/8/8/8/5RK1 b - - 1 1");
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_err());
+    }
+
+    #[test]
+    fn limits_from_go() {
+        assert_eq!(parse_limits(&["depth", "5"], Color::White), SearchLimits::depth(5));
+        assert_eq!(parse_limits(&["nodes", "100"], Color::White), SearchLimits::nodes(100));
+        let limits = parse_limits(&["wtime", "60000", "btime", "3000", "binc", "1000"], Color::Black);
+        assert_eq!(limits.time, Some(Duration::from_millis(600)));
+    }
+
+    #[test]
+    fn options() {
+        let option = parse_option(&["name", "EvalFile", "value", "my", "weights.txt"]);
+        assert_eq!(option, Some(("EvalFile".to_string(), "my weights.txt".to_string())));
+        assert_eq!(parse_option(&["name", "Clear", "Hash"]), Some(("Clear Hash".to_string(), String::new())));
+        assert_eq!(parse_option(&["value", "3"]), None);
+        let output = session("setoption name EvalFile value /no/such/weights\n");
+        assert!(output.starts_with("info string Could not read /no/such/weights"));
+    }
+}
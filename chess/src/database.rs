@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::ChessError;
+use crate::fairy::PieceRegistry;
+use crate::game::{Game, GameRecord};
+use crate::moves::{en_passant_moves, Move};
+use crate::pgn::Pgn;
+use crate::position::{Position, STANDARD_START_FEN};
+use crate::variant::variant_from_name;
+
+/// First line of a saved database, for the version of the format
+const HEADER: &str = "# chess game database 1";
+
+/// A game as the database keeps it: the players and result from its tags and the main line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseGame {
+    pub white: String,
+    pub black: String,
+    pub event: String,
+    pub date: String,
+    /// "1-0", "0-1", "1/2-1/2" or "*"
+    pub result: String,
+    pub variant: String,
+    pub record: GameRecord,
+}
+
+/// Games won, drawn and lost, counted from white's side. Unfinished games only count in `games`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResultStats {
+    pub games: usize,
+    pub white_wins: usize,
+    pub draws: usize,
+    pub black_wins: usize,
+}
+
+impl ResultStats {
+    fn add(&mut self, result: &str) {
+        self.games += 1;
+        match result {
+            "1-0" => self.white_wins += 1,
+            "0-1" => self.black_wins += 1,
+            "1/2-1/2" => self.draws += 1,
+            _ => {}
+        }
+    }
+
+    /// White's score from 0 to 1 over the finished games, half a point for a draw.
+    pub fn white_score(&self) -> Option<f64> {
+        let finished = self.white_wins + self.draws + self.black_wins;
+        (finished > 0).then(|| (self.white_wins as f64 + self.draws as f64 / 2.0) / finished as f64)
+    }
+}
+
+/// A move played in the explored position and how the games with it ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplorerMove {
+    pub mv: Move,
+    pub san: String,
+    pub stats: ResultStats,
+}
+
+/// What the database knows about a position, like an opening book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explorer {
+    /// All games reaching the position, including those ending there
+    pub stats: ResultStats,
+    /// Most played first
+    pub moves: Vec<ExplorerMove>,
+}
+
+impl fmt::Display for Explorer {
+    /// "e4     120 games  38% / 31% / 31%" per move, with the white wins, draws and black wins
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |count: usize, stats: &ResultStats| match stats.games {
+            0 => 0,
+            games => (100 * count + games / 2) / games,
+        };
+        let line = |f: &mut fmt::Formatter, name: &str, stats: &ResultStats| {
+            writeln!(
+                f,
+                "{:<8} {:>6} games {:>4}% / {:>3}% / {:>3}%",
+                name,
+                stats.games,
+                percent(stats.white_wins, stats),
+                percent(stats.draws, stats),
+                percent(stats.black_wins, stats)
+            )
+        };
+        for explored in &self.moves {
+            line(f, &explored.san, &explored.stats)?;
+        }
+        line(f, "total", &self.stats)?;
+        write!(f, "White wins / draws / black wins")
+    }
+}
+
+/// Games indexed by every position of their main lines, so all games reaching a position can
+/// be found without replaying them. Positions are matched by `Position::hash_key`, which leaves
+/// out the move counters, so transpositions are found too. An en passant square only counts
+/// when a pawn can take there. Saved as text with `to_text`.
+#[derive(Debug, Clone, Default)]
+pub struct GameDatabase {
+    games: Vec<DatabaseGame>,
+    /// Position key to game index and the ply at which the game reached the position
+    index: HashMap<u64, Vec<(usize, usize)>>,
+}
+
+impl GameDatabase {
+    pub fn new() -> GameDatabase {
+        GameDatabase::default()
+    }
+
+    pub fn games(&self) -> &[DatabaseGame] {
+        &self.games
+    }
+
+    /// Adds the main line of each game with its tags. Returns how many were added.
+    pub fn import(&mut self, pgns: &[Pgn]) -> Result<usize, ChessError> {
+        for pgn in pgns {
+            let tag = |name: &str| pgn.tag(name).unwrap_or("?").replace(['\t', '\n'], " ");
+            let mut record = pgn.game.record();
+            record.moves.truncate(pgn.game.mainline().len());
+            self.add(DatabaseGame {
+                white: tag("White"),
+                black: tag("Black"),
+                event: tag("Event"),
+                date: tag("Date"),
+                result: pgn.tag("Result").unwrap_or("*").to_string(),
+                variant: pgn.game.variant().name().to_string(),
+                record,
+            })?;
+        }
+        Ok(pgns.len())
+    }
+
+    /// Adds one game, replaying it to index its positions.
+    pub fn add(&mut self, game: DatabaseGame) -> Result<(), ChessError> {
+        let number = self.games.len();
+        for (ply, key) in position_keys(&game)?.into_iter().enumerate() {
+            let hits = self.index.entry(key).or_default();
+            // A repetition only counts the first time the position came up
+            if hits.last().is_none_or(|(last, _)| *last != number) {
+                hits.push((number, ply));
+            }
+        }
+        self.games.push(game);
+        Ok(())
+    }
+
+    /// Every game that reached the position, in the order they were added.
+    pub fn games_reaching(&self, position: &Position) -> Vec<&DatabaseGame> {
+        self.hits(position).iter().map(|(game, _)| &self.games[*game]).collect()
+    }
+
+    /// The results of all games reaching the position and the moves played next.
+    pub fn explore(&self, position: &Position) -> Explorer {
+        let mut stats = ResultStats::default();
+        let mut moves: Vec<ExplorerMove> = vec![];
+        for (number, ply) in self.hits(position) {
+            let game = &self.games[*number];
+            stats.add(&game.result);
+            let mv = match game.record.moves.get(*ply).and_then(|uci| Move::from_uci(&position.board, uci).ok()) {
+                Some(mv) => mv,
+                None => continue,
+            };
+            match moves.iter_mut().find(|explored| explored.mv == mv) {
+                Some(explored) => explored.stats.add(&game.result),
+                None => {
+                    let mut move_stats = ResultStats::default();
+                    move_stats.add(&game.result);
+                    let san = match variant_from_name(&game.variant) {
+                        Some(variant) => mv.to_san(variant.as_ref(), position),
+                        None => mv.to_uci(&position.board),
+                    };
+                    moves.push(ExplorerMove { mv, san, stats: move_stats });
+                }
+            }
+        }
+        // Stable, so equally common moves stay in the order they were first played
+        moves.sort_by_key(|explored| std::cmp::Reverse(explored.stats.games));
+        Explorer { stats, moves }
+    }
+
+    fn hits(&self, position: &Position) -> &[(usize, usize)] {
+        self.index.get(&index_key(position)).map_or(&[], Vec::as_slice)
+    }
+
+    /// The games, one per line, followed by the index. The standard starting position's key
+    /// is written too: keys are only stable for the same build, and a database whose key no
+    /// longer matches is indexed again by `from_text`.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\nkey {:016x}\n", HEADER, start_key());
+        for game in &self.games {
+            let fields = [
+                game.variant.as_str(),
+                &game.white,
+                &game.black,
+                &game.event,
+                &game.date,
+                &game.result,
+                &game.record.fen,
+                &game.record.moves.join(" "),
+            ];
+            text.push_str(&format!("game\t{}\n", fields.join("\t")));
+        }
+        let mut keys: Vec<&u64> = self.index.keys().collect();
+        keys.sort();
+        for key in keys {
+            let hits: Vec<String> = self.index[key].iter().map(|(game, ply)| format!("{}:{}", game, ply)).collect();
+            text.push_str(&format!("position\t{:016x}\t{}\n", key, hits.join(" ")));
+        }
+        text
+    }
+
+    /// Reads a database written by `to_text`.
+    pub fn from_text(text: &str) -> Result<GameDatabase, ChessError> {
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(ChessError::invalid_config(1, "Not a game database"));
+        }
+        let same_keys = match lines.next() {
+            Some((_, line)) => line.strip_prefix("key ").and_then(|key| u64::from_str_radix(key, 16).ok()) == Some(start_key()),
+            None => return Err(ChessError::invalid_config(2, "Missing the key line")),
+        };
+
+        let mut database = GameDatabase::new();
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["game", variant, white, black, event, date, result, fen, moves] => {
+                    let game = DatabaseGame {
+                        white: white.to_string(),
+                        black: black.to_string(),
+                        event: event.to_string(),
+                        date: date.to_string(),
+                        result: result.to_string(),
+                        variant: variant.to_string(),
+                        record: GameRecord {
+                            fen: fen.to_string(),
+                            moves: moves.split_whitespace().map(str::to_string).collect(),
+                        },
+                    };
+                    if same_keys {
+                        database.games.push(game);
+                    } else {
+                        database.add(game).map_err(|error| ChessError::invalid_config(number, &error.to_string()))?;
+                    }
+                }
+                ["position", ..] if !same_keys => {}
+                ["position", key, hits] => {
+                    let key = u64::from_str_radix(key, 16).map_err(|_| ChessError::invalid_config(number, "Invalid key"))?;
+                    let mut parsed = vec![];
+                    for hit in hits.split_whitespace() {
+                        let (game, ply) = hit
+                            .split_once(':')
+                            .and_then(|(game, ply)| Some((game.parse().ok()?, ply.parse().ok()?)))
+                            .filter(|(game, _): &(usize, usize)| *game < database.games.len())
+                            .ok_or_else(|| ChessError::invalid_config(number, "Invalid game reference"))?;
+                        parsed.push((game, ply));
+                    }
+                    database.index.insert(key, parsed);
+                }
+                [""] => {}
+                _ => return Err(ChessError::invalid_config(number, "Expected a game or a position")),
+            }
+        }
+        Ok(database)
+    }
+}
+
+/// The key of the position before each move of the game and the one after the last.
+fn position_keys(game: &DatabaseGame) -> Result<Vec<u64>, ChessError> {
+    let variant = variant_from_name(&game.variant)
+        .ok_or_else(|| ChessError::invalid_notation(game.variant.clone(), "Unknown variant"))?;
+    let position = Position::from_fen_with_registry(&game.record.fen, Arc::new(PieceRegistry::with_common_fairies()))?;
+    let mut replay = Game::from_position(position, variant);
+    let mut keys = vec![index_key(replay.position())];
+    for uci in &game.record.moves {
+        let mv = Move::from_uci(replay.board(), uci)?;
+        replay.play(mv)?;
+        keys.push(index_key(replay.position()));
+    }
+    Ok(keys)
+}
+
+/// `Position::hash_key` without an en passant square no pawn can capture on, so that a double
+/// step does not keep the position from matching the same one reached another way.
+fn index_key(position: &Position) -> u64 {
+    if position.en_passant.is_some() && en_passant_moves(position).is_empty() {
+        return Position { en_passant: None, ..position.clone() }.hash_key();
+    }
+    position.hash_key()
+}
+
+fn start_key() -> u64 {
+    index_key(&Position::from_fen(STANDARD_START_FEN).expect("the standard starting position is valid"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::{GameDatabase, ResultStats};
+    use crate::pgn::parse_pgn;
+    use crate::position::{Position, STANDARD_START_FEN};
+
+    const GAMES: &str = r#"[White "Anna"]
+[Black "Ben"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 1-0
+
+[White "Carl"]
+[Black "Anna"]
+[Result "1/2-1/2"]
+
+1. e4 c5 2. Nf3 1/2-1/2
+
+[White "Ben"]
+[Black "Carl"]
+[Result "0-1"]
+
+1. Nf3 Nc6 2. e4 e5 3. Nc3 0-1
+
+[White "Dora"]
+[Black "Ben"]
+[Result "*"]
+
+1. d4 *
+"#;
+
+    fn database() -> GameDatabase {
+        let mut database = GameDatabase::new();
+        assert_eq!(database.import(&parse_pgn(GAMES).unwrap()), Ok(4));
+        database
+    }
+
+    fn after(moves: &str) -> Position {
+        let text = format!("{} *", moves);
+        parse_pgn(&text).unwrap().remove(0).game.position().clone()
+    }
+
+    #[test]
+    fn exploring_the_start() {
+        let explorer = database().explore(&Position::from_fen(STANDARD_START_FEN).unwrap());
+        assert_eq!(explorer.stats, ResultStats { games: 4, white_wins: 1, draws: 1, black_wins: 1 });
+        let moves: Vec<(&str, usize)> = explorer.moves.iter().map(|explored| (explored.san.as_str(), explored.stats.games)).collect();
+        assert_eq!(moves, vec![("e4", 2), ("Nf3", 1), ("d4", 1)]);
+        assert_eq!(explorer.moves[0].stats.white_score(), Some(0.75));
+        assert!(explorer.to_string().starts_with("e4            2 games   50% /  50% /   0%"));
+    }
+
+    #[test]
+    fn transpositions_are_found() {
+        let database = database();
+        let position = after("1. e4 e5 2. Nf3 Nc6");
+        let players: Vec<&str> = database.games_reaching(&position).iter().map(|game| game.white.as_str()).collect();
+        assert_eq!(players, vec!["Anna", "Ben"]);
+        let explorer = database.explore(&position);
+        let moves: Vec<&str> = explorer.moves.iter().map(|explored| explored.san.as_str()).collect();
+        assert_eq!(moves, vec!["Bb5", "Nc3"]);
+        // The final position of a game counts with no move after it
+        let end = database.explore(&after("1. d4"));
+        assert_eq!((end.stats.games, end.moves.len()), (1, 0));
+        assert!(database.games_reaching(&after("1. a4")).is_empty());
+    }
+
+    #[test]
+    fn saving_and_loading() {
+        let database = database();
+        let text = database.to_text();
+        let loaded = GameDatabase::from_text(&text).unwrap();
+        assert_eq!(loaded.games(), database.games());
+        let position = after("1. e4");
+        assert_eq!(loaded.explore(&position), database.explore(&position));
+
+        // With keys from another build the games are indexed again
+        let start = text.lines().nth(1).unwrap().to_string();
+        let rekeyed = GameDatabase::from_text(&text.replace(&start, "key 0000000000000000")).unwrap();
+        assert_eq!(rekeyed.explore(&position), database.explore(&position));
+
+        assert!(GameDatabase::from_text("not a database").is_err());
+        assert!(GameDatabase::from_text(&text.replace("0:", "9:")).is_err());
+        assert!(GameDatabase::from_text(&format!("{}something else\n", text)).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+/// Wins, draws and losses from the first player's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average points per game, a draw being worth half a point.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Variance of the points of a single game around the average.
+    fn variance(&self) -> f64 {
+        variance(self.wins as f64, self.draws as f64, self.losses as f64)
+    }
+
+    /// Elo difference and the half width of its 95% confidence interval. `None` until both
+    /// players have scored something, before that the difference is infinite.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let score = self.score();
+        if self.games() == 0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((score - 1.96 * deviation).max(0.001));
+        let high = elo_from_score((score + 1.96 * deviation).min(0.999));
+        Some((elo_from_score(score), (high - low) / 2.0))
+    }
+}
+
+fn variance(wins: f64, draws: f64, losses: f64) -> f64 {
+    let games = wins + draws + losses;
+    let score = (wins + draws / 2.0) / games;
+    (wins * (1.0 - score).powi(2) + draws * (0.5 - score).powi(2) + losses * score.powi(2)) / games
+}
+
+/// Elo difference that makes the expected score of the stronger player `score`.
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SprtResult {
+    /// The first player is at least `elo1` stronger
+    AcceptH1,
+    /// The first player is at most `elo0` stronger
+    AcceptH0,
+    Continue,
+}
+
+/// Sequential probability ratio test between "the difference is `elo0`" and "it is `elo1`",
+/// with `alpha` and `beta` the accepted rates of false positives and false negatives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    /// Bounds the log-likelihood ratio has to cross to accept H0 and H1.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// Log-likelihood ratio of the results so far, treating game scores as normally distributed.
+    /// Half a game is added to each count so that one-sided results still have a variance.
+    pub fn llr(&self, results: &MatchScore) -> f64 {
+        if results.games() == 0 {
+            return 0.0;
+        }
+        let (wins, draws, losses) = (results.wins as f64 + 0.5, results.draws as f64 + 0.5, results.losses as f64 + 0.5);
+        let games = wins + draws + losses;
+        let score = (wins + draws / 2.0) / games;
+        let (score0, score1) = (score_from_elo(self.elo0), score_from_elo(self.elo1));
+        games * (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance(wins, draws, losses))
+    }
+
+    pub fn test(&self, results: &MatchScore) -> SprtResult {
+        let (lower, upper) = self.bounds();
+        let llr = self.llr(results);
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elo::{elo_from_score, score_from_elo, MatchScore, Sprt, SprtResult};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.5
+    }
+
+    #[test]
+    fn elo_from_scores() {
+        assert!(close(elo_from_score(0.5), 0.0));
+        assert!(close(elo_from_score(0.64), 100.0));
+        assert!(close(elo_from_score(0.36), -100.0));
+        assert!((score_from_elo(elo_from_score(0.7)) - 0.7).abs() < 1e-9);
+    }
+
+    #[test]
+    fn elo_with_error_bars() {
+        let even = MatchScore { wins: 30, draws: 40, losses: 30 };
+        let (elo, margin) = even.elo().unwrap();
+        assert!(close(elo, 0.0));
+        assert!(margin > 40.0 && margin < 70.0);
+
+        let more_games = MatchScore { wins: 300, draws: 400, losses: 300 };
+        assert!(more_games.elo().unwrap().1 < margin / 3.0);
+    }
+
+    #[test]
+    fn no_elo_for_a_clean_sweep() {
+        assert_eq!(MatchScore { wins: 10, draws: 0, losses: 0 }.elo(), None);
+        assert_eq!(MatchScore::default().elo(), None);
+    }
+
+    #[test]
+    fn sprt_decisions() {
+        let sprt = Sprt::new(0.0, 10.0);
+        assert_eq!(sprt.test(&MatchScore { wins: 10, draws: 10, losses: 10 }), SprtResult::Continue);
+        assert_eq!(sprt.test(&MatchScore { wins: 700, draws: 600, losses: 500 }), SprtResult::AcceptH1);
+        assert_eq!(sprt.test(&MatchScore { wins: 500, draws: 600, losses: 700 }), SprtResult::AcceptH0);
+    }
+
+    #[test]
+    fn sprt_bounds() {
+        let (lower, upper) = Sprt::new(0.0, 5.0).bounds();
+        assert!((lower + 2.944).abs() < 0.001);
+        assert!((upper - 2.944).abs() < 0.001);
+    }
+}
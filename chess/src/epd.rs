@@ -0,0 +1,190 @@
+use crate::error::ChessError;
+use crate::moves::Move;
+use crate::position::Position;
+use crate::search::{search_with_options, SearchLimits, SearchOptions, SearchResult};
+use crate::variant::Standard;
+
+/// One line of an Extended Position Description file: a position with the moves to find or to
+/// avoid, as in test suites like WAC or STS.
+#[derive(Debug, Clone)]
+pub struct EpdEntry {
+    pub position: Position,
+    /// The `id` operation, the line number when there is none
+    pub id: String,
+    /// `bm`: any of these passes
+    pub best_moves: Vec<Move>,
+    /// `am`: none of these may be played
+    pub avoid_moves: Vec<Move>,
+}
+
+impl EpdEntry {
+    /// True if there is a best or avoid move to test the engine with.
+    pub fn is_test(&self) -> bool {
+        !self.best_moves.is_empty() || !self.avoid_moves.is_empty()
+    }
+
+    /// True if playing `mv` here passes the test.
+    pub fn passes(&self, mv: Move) -> bool {
+        self.is_test() && (self.best_moves.is_empty() || self.best_moves.contains(&mv)) && !self.avoid_moves.contains(&mv)
+    }
+
+    /// Searches the position within the limits and checks the engine's move.
+    pub fn run(&self, limits: SearchLimits, options: &SearchOptions) -> EpdResult {
+        let search = search_with_options(&Standard, &self.position, limits, options);
+        let passed = search.best_move.is_some_and(|mv| self.passes(mv));
+        EpdResult { passed, search }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EpdResult {
+    pub passed: bool,
+    pub search: SearchResult,
+}
+
+/// Reads EPD lines: the first four FEN fields, then operations ending in ';', like
+/// `bm Qg6; id "WAC.003";`. Moves are in standard algebraic notation and have to be legal.
+/// `hmvc` and `fmvn` set the move counters, other operations are ignored, as are empty lines
+/// and lines starting with '#'.
+pub fn parse_epd(text: &str) -> Result<Vec<EpdEntry>, ChessError> {
+    let mut entries = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // The operations are what follows the position fields
+        let mut operations = line;
+        let mut fen = vec![];
+        while fen.len() < 4 && !operations.is_empty() {
+            operations = operations.trim_start();
+            let end = operations.find(char::is_whitespace).unwrap_or(operations.len());
+            fen.push(&operations[..end]);
+            operations = &operations[end..];
+        }
+        if fen.len() < 4 {
+            return Err(ChessError::invalid_notation(line, "Expected the four position fields"));
+        }
+        let mut entry = EpdEntry {
+            position: Position::from_fen(&fen.join(" "))?,
+            id: (number + 1).to_string(),
+            best_moves: vec![],
+            avoid_moves: vec![],
+        };
+        for operation in split_operations(operations) {
+            let (opcode, operands) = match operation.split_first() {
+                Some((opcode, operands)) => (opcode.as_str(), operands),
+                None => continue,
+            };
+            match opcode {
+                "bm" | "am" => {
+                    let mut moves = vec![];
+                    for san in operands {
+                        moves.push(Move::from_san(&Standard, &entry.position, san)?);
+                    }
+                    match opcode {
+                        "bm" => entry.best_moves = moves,
+                        _ => entry.avoid_moves = moves,
+                    }
+                }
+                "id" => entry.id = operands.join(" "),
+                "hmvc" | "fmvn" => {
+                    let number = operands
+                        .first()
+                        .and_then(|number| number.parse().ok())
+                        .ok_or(ChessError::invalid_notation(operation.join(" "), "Expected a number"))?;
+                    match opcode {
+                        "hmvc" => entry.position.halfmove_clock = number,
+                        _ => entry.position.fullmove_number = number.max(1),
+                    }
+                }
+                _ => {}
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Splits the operations at ';' into their opcode and operands, unquoting strings which may
+/// hold spaces and ';' themselves.
+fn split_operations(text: &str) -> Vec<Vec<String>> {
+    let mut operations = vec![];
+    let mut operation: Vec<String> = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' | ' ' | '\t' if !quoted => {
+                if !token.is_empty() {
+                    operation.push(std::mem::take(&mut token));
+                }
+                if c == ';' && !operation.is_empty() {
+                    operations.push(std::mem::take(&mut operation));
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        operation.push(token);
+    }
+    if !operation.is_empty() {
+        operations.push(operation);
+    }
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::epd::parse_epd;
+    use crate::moves::Move;
+    use crate::search::{SearchLimits, SearchOptions};
+    use crate::variant::Standard;
+
+    const SUITE: &str = "\
+# A few easy ones
+2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";
+8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - bm Rxb2; c0 \"Wins; a pawn\";
+
+6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - am Kf1 Rb1; hmvc 12; fmvn 40;
+";
+
+    #[test]
+    fn reading_epd() {
+        let entries = parse_epd(SUITE).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].id, "WAC.001");
+        let qg6 = Move::from_san(&Standard, &entries[0].position, "Qg6").unwrap();
+        assert_eq!(entries[0].best_moves, vec![qg6]);
+        assert_eq!(entries[1].id, "3");
+        assert_eq!(entries[1].best_moves.len(), 1);
+        assert_eq!(entries[2].avoid_moves.len(), 2);
+        assert_eq!(entries[2].position.to_fen(), "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 12 40");
+        assert!(entries.iter().all(|entry| entry.is_test()));
+    }
+
+    #[test]
+    fn invalid_epd() {
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w -").is_err());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Ra9;").is_err());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Rb1;").is_ok());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Rb1 Kh3;").is_err());
+        assert!(parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - hmvc x;").is_err());
+    }
+
+    #[test]
+    fn passing_and_failing() {
+        let entries = parse_epd(SUITE).unwrap();
+        let options = SearchOptions::default();
+        let mate = parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Ra8#; id \"mate\";").unwrap();
+        assert!(mate[0].run(SearchLimits::depth(2), &options).passed);
+        // The engine mates rather than play either king move
+        assert!(entries[2].run(SearchLimits::depth(2), &options).passed);
+        let kf1 = entries[2].avoid_moves[0];
+        assert!(!entries[2].passes(kf1));
+        let wrong = parse_epd("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - bm Rb1;").unwrap();
+        assert!(!wrong[0].run(SearchLimits::depth(2), &options).passed);
+    }
+}
@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChessError {
+    /// A square that is not on the board, either as algebraic text or coordinates
+    InvalidSquare { square: String, reason: String },
+    /// `field` is the index of the space separated FEN field, `position` the character offset in the whole string
+    InvalidFen { position: usize, field: usize, reason: String },
+    IllegalMove { reason: String },
+    InvalidPieceDefinition { reason: String },
+    /// A move written in a notation that could not be read
+    InvalidNotation { notation: String, reason: String },
+    /// An external engine that could not be started or did not follow the protocol
+    Engine { reason: String },
+    /// Moving through a game's history to a ply or node that does not exist
+    InvalidNavigation { reason: String },
+    /// A settings or data file that could not be read, `line` counts from 1 and is 0 for the file as a whole
+    InvalidConfig { line: usize, reason: String },
+    /// A position that could not come up in a game, like a pawn on the back rank
+    InvalidPosition { reason: String },
+}
+
+impl ChessError {
+    pub fn invalid_square(square: impl Into<String>, reason: &str) -> ChessError {
+        ChessError::InvalidSquare { square: square.into(), reason: reason.to_string() }
+    }
+
+    pub fn invalid_fen(position: usize, field: usize, reason: &str) -> ChessError {
+        ChessError::InvalidFen { position, field, reason: reason.to_string() }
+    }
+
+    pub fn illegal_move(reason: &str) -> ChessError {
+        ChessError::IllegalMove { reason: reason.to_string() }
+    }
+
+    pub fn invalid_piece_definition(reason: &str) -> ChessError {
+        ChessError::InvalidPieceDefinition { reason: reason.to_string() }
+    }
+
+    pub fn invalid_notation(notation: impl Into<String>, reason: &str) -> ChessError {
+        ChessError::InvalidNotation { notation: notation.into(), reason: reason.to_string() }
+    }
+
+    pub fn engine(reason: &str) -> ChessError {
+        ChessError::Engine { reason: reason.to_string() }
+    }
+
+    pub fn invalid_navigation(reason: &str) -> ChessError {
+        ChessError::InvalidNavigation { reason: reason.to_string() }
+    }
+
+    pub fn invalid_config(line: usize, reason: &str) -> ChessError {
+        ChessError::InvalidConfig { line, reason: reason.to_string() }
+    }
+
+    pub fn invalid_position(reason: &str) -> ChessError {
+        ChessError::InvalidPosition { reason: reason.to_string() }
+    }
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChessError::InvalidSquare { square, reason } => write!(f, "invalid square '{}': {}", square, reason),
+            ChessError::InvalidFen { position, field, reason } => {
+                write!(f, "invalid FEN at character {} (field {}): {}", position, field + 1, reason)
+            }
+            ChessError::IllegalMove { reason } => write!(f, "illegal move: {}", reason),
+            ChessError::InvalidPieceDefinition { reason } => write!(f, "invalid piece definition: {}", reason),
+            ChessError::InvalidNotation { notation, reason } => write!(f, "invalid notation '{}': {}", notation, reason),
+            ChessError::Engine { reason } => write!(f, "engine error: {}", reason),
+            ChessError::InvalidNavigation { reason } => write!(f, "invalid navigation: {}", reason),
+            ChessError::InvalidConfig { line: 0, reason } => write!(f, "invalid config: {}", reason),
+            ChessError::InvalidConfig { line, reason } => write!(f, "invalid config on line {}: {}", line, reason),
+            ChessError::InvalidPosition { reason } => write!(f, "invalid position: {}", reason),
+        }
+    }
+}
+
+impl Error for ChessError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ChessError;
+
+    #[test]
+    fn error_messages() {
+        assert_eq!(
+            ChessError::invalid_square("i9", "Not a valid letter").to_string(),
+            "invalid square 'i9': Not a valid letter"
+        );
+        assert_eq!(
+            ChessError::invalid_fen(3, 0, "Invalid piece").to_string(),
+            "invalid FEN at character 3 (field 1): Invalid piece"
+        );
+        assert_eq!(ChessError::illegal_move("Game is already over").to_string(), "illegal move: Game is already over");
+    }
+}
@@ -0,0 +1,327 @@
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::{Color, Piece, PieceType};
+use crate::error::ChessError;
+use crate::position::Position;
+
+// Piece-square bonuses in centipawns for white, laid out like a FEN with rank 8 first.
+// Black uses the same tables upside down.
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+   -50,-40,-30,-30,-30,-30,-40,-50,
+   -40,-20,  0,  0,  0,  0,-20,-40,
+   -30,  0, 10, 15, 15, 10,  0,-30,
+   -30,  5, 15, 20, 20, 15,  5,-30,
+   -30,  0, 15, 20, 20, 15,  0,-30,
+   -30,  5, 10, 15, 15, 10,  5,-30,
+   -40,-20,  0,  5,  5,  0,-20,-40,
+   -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+   -20,-10,-10,-10,-10,-10,-10,-20,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -10,  0,  5, 10, 10,  5,  0,-10,
+   -10,  5,  5, 10, 10,  5,  5,-10,
+   -10,  0, 10, 10, 10, 10,  0,-10,
+   -10, 10, 10, 10, 10, 10, 10,-10,
+   -10,  5,  0,  0,  0,  0,  5,-10,
+   -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+   -20,-10,-10, -5, -5,-10,-10,-20,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -10,  0,  5,  5,  5,  5,  0,-10,
+    -5,  0,  5,  5,  5,  5,  0, -5,
+     0,  0,  5,  5,  5,  5,  0, -5,
+   -10,  5,  5,  5,  5,  5,  0,-10,
+   -10,  0,  5,  0,  0,  0,  0,-10,
+   -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -20,-30,-30,-40,-40,-30,-30,-20,
+   -10,-20,-20,-20,-20,-20,-20,-10,
+    20, 20,  0,  0,  0,  0, 20, 20,
+    20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+/// Tables in the order `table_slot` gives out
+const TABLE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+
+/// The weights of the evaluation: piece values and piece-square tables for pawn, knight,
+/// bishop, rook, queen and king. Tables are laid out like a FEN with rank 8 first, from white's side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams {
+    pub piece_values: [i32; 6],
+    pub tables: [[i32; 64]; 6],
+}
+
+impl EvalParams {
+    /// The hand-written weights the engine uses unless told otherwise.
+    pub const STANDARD: EvalParams = EvalParams {
+        piece_values: [100, 320, 330, 500, 900, 20000],
+        tables: [PAWN_TABLE, KNIGHT_TABLE, BISHOP_TABLE, ROOK_TABLE, QUEEN_TABLE, KING_TABLE],
+    };
+
+    /// Bonus for standing on the square. Boards of other sizes are scaled onto the 8x8 tables,
+    /// fairy pieces get the knight's table as most of them include its jump.
+    pub fn piece_square_value(&self, board: &Chessboard, piece: Piece) -> i32 {
+        self.tables[table_slot(piece.piece_type)][table_square(board, piece)]
+    }
+
+    pub fn piece_value(&self, piece_type: PieceType) -> i32 {
+        match piece_type {
+            PieceType::Fairy(_) => piece_type.value(),
+            _ => self.piece_values[table_slot(piece_type)],
+        }
+    }
+
+    /// Material and piece placement in centipawns, positive when white is better.
+    pub fn evaluate_white(&self, position: &Position) -> i32 {
+        let mut score = 0;
+        for color in [Color::White, Color::Black] {
+            let sign = match color {
+                Color::White => 1,
+                Color::Black => -1,
+            };
+            for piece in position.board.pieces(color) {
+                score += sign * (self.piece_value(piece.piece_type) + self.piece_square_value(&position.board, piece));
+            }
+            for piece_type in position.pocket(color) {
+                score += sign * self.piece_value(*piece_type);
+            }
+        }
+        score
+    }
+
+    /// The evaluation from the point of view of the side to move, as the search wants it.
+    pub fn evaluate(&self, position: &Position) -> i32 {
+        match position.side_to_move {
+            Color::White => self.evaluate_white(position),
+            Color::Black => -self.evaluate_white(position),
+        }
+    }
+
+    /// Reads weights written by `to_config`: a `values` line with the six piece values, then
+    /// each table's name followed by its 64 numbers. Lines starting with `#` are comments.
+    pub fn from_config(text: &str) -> Result<EvalParams, ChessError> {
+        let mut tokens = text
+            .lines()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                let line = line.split('#').next().unwrap_or("");
+                line.split_whitespace().map(move |token| (index + 1, token))
+            })
+            .peekable();
+        let mut params = EvalParams::STANDARD;
+        let mut seen = [false; 7];
+        while let Some((line, name)) = tokens.next() {
+            let (section, values): (usize, &mut [i32]) = match name {
+                "values" => (6, &mut params.piece_values),
+                _ => match TABLE_NAMES.iter().position(|table| *table == name) {
+                    Some(slot) => (slot, &mut params.tables[slot]),
+                    None => return Err(ChessError::invalid_config(line, &format!("Unknown section '{}'", name))),
+                },
+            };
+            if seen[section] {
+                return Err(ChessError::invalid_config(line, &format!("'{}' given twice", name)));
+            }
+            seen[section] = true;
+            let expected = values.len();
+            for (read, value) in values.iter_mut().enumerate() {
+                let (line, token) = match tokens.next_if(|(_, token)| token.parse::<i32>().is_ok()) {
+                    Some(next) => next,
+                    None => {
+                        let reason = format!("'{}' has {} numbers, expected {}", name, read, expected);
+                        return Err(ChessError::invalid_config(line, &reason));
+                    }
+                };
+                *value = token.parse().map_err(|_| ChessError::invalid_config(line, "Not a number"))?;
+            }
+        }
+        match seen.iter().position(|seen| !seen) {
+            Some(6) => Err(ChessError::invalid_config(0, "Missing the piece values")),
+            Some(slot) => Err(ChessError::invalid_config(0, &format!("Missing the {} table", TABLE_NAMES[slot]))),
+            None => Ok(params),
+        }
+    }
+
+    /// Writes the weights in the format `from_config` reads.
+    pub fn to_config(&self) -> String {
+        let mut config = String::from("# Evaluation weights in centipawns, tables from white's side with rank 8 first\n");
+        let values: Vec<String> = self.piece_values.iter().map(|value| value.to_string()).collect();
+        config += &format!("values {}\n", values.join(" "));
+        for (name, table) in TABLE_NAMES.iter().zip(&self.tables) {
+            config += &format!("\n{}\n", name);
+            for rank in table.chunks(8) {
+                let rank: Vec<String> = rank.iter().map(|value| format!("{:4}", value)).collect();
+                config += &format!("{}\n", rank.join(""));
+            }
+        }
+        config
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams::STANDARD
+    }
+}
+
+/// Which of the six tables and piece values a piece type uses.
+pub(crate) fn table_slot(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight | PieceType::Fairy(_) => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+/// Index into a table for the piece's square, seen from its own side.
+pub(crate) fn table_square(board: &Chessboard, piece: Piece) -> usize {
+    let Square { x, y } = piece.location;
+    let x = x * 8 / board.width();
+    let y = match piece.color {
+        Color::White => y * 8 / board.height(),
+        Color::Black => (board.height() - 1 - y) * 8 / board.height(),
+    };
+    y * 8 + x
+}
+
+/// Material and piece placement in centipawns with the standard weights, positive when white is better.
+pub fn evaluate_white(position: &Position) -> i32 {
+    EvalParams::STANDARD.evaluate_white(position)
+}
+
+/// The standard evaluation from the point of view of the side to move.
+pub fn evaluate(position: &Position) -> i32 {
+    EvalParams::STANDARD.evaluate(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::{evaluate, evaluate_white, EvalParams};
+    use crate::position::{Position, STANDARD_START_FEN};
+
+    fn eval_white(fen: &str) -> i32 {
+        evaluate_white(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn start_position_is_equal() {
+        assert_eq!(eval_white(STANDARD_START_FEN), 0);
+    }
+
+    #[test]
+    fn extra_material_counts() {
+        assert!(eval_white("4k3/8/8/8/8/8/8/3QK3 w - - 0 1") > 800);
+        assert!(eval_white("3qk3/8/8/8/8/8/8/4K3 w - - 0 1") < -800);
+    }
+
+    #[test]
+    fn central_knight_beats_corner_knight() {
+        assert!(eval_white("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1") > eval_white("4k3/8/8/8/8/8/8/N3K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn evaluation_is_relative_to_side_to_move() {
+        let white = Position::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let black = Position::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert_eq!(evaluate(&white), -evaluate(&black));
+    }
+
+    #[test]
+    fn pocket_pieces_count_as_material() {
+        assert_eq!(eval_white("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1") - eval_white("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), 320);
+    }
+
+    #[test]
+    fn config_round_trip() {
+        let mut params = EvalParams::STANDARD;
+        params.piece_values[1] = 305;
+        params.tables[3][8] = 25;
+        let config = params.to_config();
+        assert_eq!(EvalParams::from_config(&config), Ok(params));
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let config = EvalParams::STANDARD.to_config();
+        assert!(EvalParams::from_config(&config.replace("values 100", "values")).is_err());
+        assert!(EvalParams::from_config(&config.replace("rook", "castle")).is_err());
+        assert!(EvalParams::from_config(&config.replace("\nqueen", "\n#queen")).is_err());
+        assert!(EvalParams::from_config("values 1 2 3 4 5 6").is_err());
+    }
+
+    #[test]
+    fn weights_change_the_evaluation() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mut params = EvalParams::STANDARD;
+        params.piece_values[4] += 50;
+        assert_eq!(params.evaluate_white(&position), evaluate_white(&position) + 50);
+    }
+
+    #[test]
+    fn evaluation_is_color_symmetric() {
+        let fens = [
+            STANDARD_START_FEN,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "6k1/5ppp/8/8/8/8/3q1PPP/R5K1 b - - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 30",
+            "8/8/8/8/8/5k2/6q1/7K b - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3[QNp] b - - 0 1",
+            "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1",
+            "rnbqk/pp1pp/5/P1pPP/RNBQK b - - 0 2",
+        ];
+        let mut params = EvalParams::STANDARD;
+        params.tables[0][12] = 40;
+        params.tables[5][60] = -15;
+        for fen in fens {
+            let position = Position::from_fen(fen).unwrap();
+            let flipped = position.color_flipped();
+            assert_eq!(evaluate(&position), evaluate(&flipped), "{}", fen);
+            assert_eq!(evaluate_white(&position), -evaluate_white(&flipped), "{}", fen);
+            assert_eq!(params.evaluate(&position), params.evaluate(&flipped), "{}", fen);
+        }
+    }
+}
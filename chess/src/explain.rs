@@ -0,0 +1,395 @@
+use crate::attacks::attackers_of;
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::{Color, Piece, PieceMovement, PieceType};
+use crate::moves::{home_rank, is_castling, is_in_check, promotion_rank, Move};
+use crate::position::Position;
+use crate::variant::Variant;
+
+/// Why a move can not be played, for telling a learner what went wrong. The pieces named
+/// carry their squares.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IllegalReason {
+    GameOver,
+    NoPiece { square: Square },
+    /// The piece belongs to the side not to move
+    NotYourPiece { piece: Piece },
+    /// The piece never moves like that, wherever the other pieces stand
+    WrongPattern { piece: Piece, to: Square },
+    /// A pawn moving diagonally onto an empty square
+    NothingToCapture { piece: Piece, to: Square },
+    /// Another piece stands in the way, for pawns also on the square moved to
+    Blocked { piece: Piece, blocker: Piece },
+    /// The square moved to holds a piece of the same color
+    OwnPiece { piece: Piece, target: Piece },
+    /// Moving the piece would open the line from `pinner` to its own king
+    Pinned { piece: Piece, pinner: Piece },
+    /// The king would be attacked after the move, by walking into it or by leaving a check unanswered
+    KingInCheck { attacker: Piece },
+    /// The king or that rook has moved before
+    CastlingRightLost { color: Color, king_side: bool },
+    /// The king may not castle out of, through or into an attack
+    CastlingThroughCheck { square: Square, attacker: Piece },
+    /// A pawn reaching the last rank has to say what it becomes
+    MissingPromotion,
+    /// Dropping a piece that is not in the pocket, or in a variant without drops
+    NotInPocket { piece_type: PieceType },
+    /// Dropping onto a square that is taken
+    SquareOccupied { occupant: Piece },
+    /// A move that is fine by the usual rules, but not by those of the variant
+    VariantRule { variant: String },
+}
+
+impl IllegalReason {
+    /// The reason as a sentence, with the squares named as on this board.
+    pub fn to_text(&self, board: &Chessboard) -> String {
+        let name = |square: &Square| board.square_to_algebraic(*square);
+        match self {
+            IllegalReason::GameOver => "The game is already over".to_string(),
+            IllegalReason::NoPiece { square } => format!("There is no piece on {}", name(square)),
+            IllegalReason::NotYourPiece { piece } => format!(
+                "The {} belongs to {} and it is {}'s move",
+                describe(piece, board),
+                color_name(piece.color),
+                color_name(piece.color.opposite())
+            ),
+            IllegalReason::WrongPattern { piece, to } => {
+                format!("A {} can not move from {} to {}", piece_name(piece.piece_type), name(&piece.location), name(to))
+            }
+            IllegalReason::NothingToCapture { piece, to } => {
+                format!("The {} can only move diagonally to capture and there is nothing on {}", describe(piece, board), name(to))
+            }
+            IllegalReason::Blocked { piece, blocker } => {
+                format!("The {} is blocked by the {}", describe(piece, board), describe(blocker, board))
+            }
+            IllegalReason::OwnPiece { piece, target } => {
+                format!("The {} can not capture its own {}", describe(piece, board), describe(target, board))
+            }
+            IllegalReason::Pinned { piece, pinner } => {
+                format!("The {} is pinned to its king by the {}", describe(piece, board), describe(pinner, board))
+            }
+            IllegalReason::KingInCheck { attacker } => {
+                format!("The king would be in check from the {}", describe(attacker, board))
+            }
+            IllegalReason::CastlingRightLost { color, king_side } => format!(
+                "{} can no longer castle {}, the king or that rook has moved",
+                capitalized(color_name(*color)),
+                if *king_side { "king side" } else { "queen side" }
+            ),
+            IllegalReason::CastlingThroughCheck { square, attacker } => {
+                format!("The king can not castle while {} is attacked by the {}", name(square), describe(attacker, board))
+            }
+            IllegalReason::MissingPromotion => "A pawn reaching the last rank has to promote, say to which piece".to_string(),
+            IllegalReason::NotInPocket { piece_type } => format!("There is no {} in hand to drop", piece_name(*piece_type)),
+            IllegalReason::SquareOccupied { occupant } => {
+                format!("There is already a {} there", describe(occupant, board))
+            }
+            IllegalReason::VariantRule { variant } => format!("The rules of {} do not allow this move", variant),
+        }
+    }
+}
+
+/// Finds out why the move can not be played, `None` if it can. Checks the way a teacher would
+/// explain it: whose piece it is, how it moves, what stands in its way, and only then the king.
+pub fn explain_illegal_move(variant: &dyn Variant, position: &Position, mv: Move) -> Option<IllegalReason> {
+    if variant.outcome(position).is_some() {
+        return Some(IllegalReason::GameOver);
+    }
+    if variant.legal_moves(position).contains(&mv) {
+        return None;
+    }
+    let board = &position.board;
+    let mover = position.side_to_move;
+    let (from, to, promotion) = match mv {
+        Move::Normal { from, to, promotion } => (from, to, promotion),
+        Move::Drop { piece_type, to } => {
+            if !variant.allows_drops() || !position.pocket(mover).contains(&piece_type) {
+                return Some(IllegalReason::NotInPocket { piece_type });
+            }
+            if let Some(occupant) = board.get_piece_at(to) {
+                return Some(IllegalReason::SquareOccupied { occupant });
+            }
+            return Some(king_safety(variant, position, mv, None));
+        }
+    };
+    let piece = match board.get_piece_at(from) {
+        Some(piece) => piece,
+        None => return Some(IllegalReason::NoPiece { square: from }),
+    };
+    if piece.color != mover {
+        return Some(IllegalReason::NotYourPiece { piece });
+    }
+    if board.contains(to) && is_castling(piece, from, to) && from.y == home_rank(board, mover) {
+        return Some(explain_castling(variant, position, piece, to));
+    }
+    if let Some(reason) = explain_path(position, piece, to) {
+        return Some(reason);
+    }
+    if !variant.pseudo_legal_moves(position).contains(&mv) {
+        let promotes = piece.piece_type == PieceType::Pawn && to.y == promotion_rank(board, mover);
+        return Some(match (promotes, promotion) {
+            (true, None) => IllegalReason::MissingPromotion,
+            _ => IllegalReason::WrongPattern { piece, to },
+        });
+    }
+    Some(king_safety(variant, position, mv, Some(piece)))
+}
+
+/// Whether the piece can get from its square to `to` at all, ignoring king safety.
+fn explain_path(position: &Position, piece: Piece, to: Square) -> Option<IllegalReason> {
+    let board = &position.board;
+    if !board.contains(to) || to == piece.location {
+        return Some(IllegalReason::WrongPattern { piece, to });
+    }
+    if piece.piece_type == PieceType::Pawn {
+        return explain_pawn(position, piece, to);
+    }
+    // Hoppers need something to jump over, so their pattern is only known with the pieces around
+    let reachable = alone(board, piece).contains(&to);
+    if !reachable && !matches!(piece.piece_type, PieceType::Fairy(_)) {
+        return Some(IllegalReason::WrongPattern { piece, to });
+    }
+    let blocker = piece.location.between(to).into_iter().find_map(|square| board.get_piece_at(square));
+    if let Some(blocker) = blocker.filter(|_| reachable) {
+        return Some(IllegalReason::Blocked { piece, blocker });
+    }
+    match board.get_piece_at(to) {
+        Some(target) if target.color == piece.color => Some(IllegalReason::OwnPiece { piece, target }),
+        _ => None,
+    }
+}
+
+fn explain_pawn(position: &Position, pawn: Piece, to: Square) -> Option<IllegalReason> {
+    let board = &position.board;
+    let target = board.get_piece_at(to);
+    // On an empty board the pawn only shows its captures with something to take
+    let probe = if to.x == pawn.location.x {
+        alone(board, pawn)
+    } else {
+        let mut board = board.cleared();
+        board.add_piece(pawn).ok()?;
+        board.add_piece(Piece { piece_type: PieceType::Pawn, color: pawn.color.opposite(), location: to }).ok()?;
+        pawn.can_move(&board)
+    };
+    if !probe.contains(&to) {
+        return Some(IllegalReason::WrongPattern { piece: pawn, to });
+    }
+    if to.x == pawn.location.x {
+        let path = pawn.location.between(to).into_iter().chain([to]);
+        return path
+            .filter_map(|square| board.get_piece_at(square))
+            .next()
+            .map(|blocker| IllegalReason::Blocked { piece: pawn, blocker });
+    }
+    match target {
+        Some(target) if target.color == pawn.color => Some(IllegalReason::OwnPiece { piece: pawn, target }),
+        None if position.en_passant != Some(to) => Some(IllegalReason::NothingToCapture { piece: pawn, to }),
+        _ => None,
+    }
+}
+
+fn explain_castling(variant: &dyn Variant, position: &Position, king: Piece, to: Square) -> IllegalReason {
+    let board = &position.board;
+    let color = king.color;
+    let king_side = to.x > king.location.x;
+    let rook_square = Square { x: if king_side { board.width() - 1 } else { 0 }, y: king.location.y };
+    let rook = board.get_piece_at(rook_square).filter(|rook| rook.piece_type == PieceType::Rook && rook.color == color);
+    let allowed = if king_side { position.castling.king_side(color) } else { position.castling.queen_side(color) };
+    if !allowed || rook.is_none() {
+        return IllegalReason::CastlingRightLost { color, king_side };
+    }
+    if let Some(blocker) = king.location.between(rook_square).into_iter().find_map(|square| board.get_piece_at(square)) {
+        return IllegalReason::Blocked { piece: king, blocker };
+    }
+    let mut crossed = vec![king.location];
+    crossed.extend(king.location.between(to));
+    crossed.push(to);
+    for square in crossed {
+        if let Some(attacker) = attackers_of(board, square, color.opposite()).first() {
+            return IllegalReason::CastlingThroughCheck { square, attacker: *attacker };
+        }
+    }
+    IllegalReason::VariantRule { variant: variant.name().to_string() }
+}
+
+/// For a move the piece can make: a pin or check it would leave the king in, or else the variant's rules.
+fn king_safety(variant: &dyn Variant, position: &Position, mv: Move, piece: Option<Piece>) -> IllegalReason {
+    let mover = position.side_to_move;
+    let mut next = position.clone();
+    if variant.apply_move(&mut next, mv).is_ok() && is_in_check(&next.board, mover) {
+        if let Some(king) = next.board.find_king(mover) {
+            let attackers = attackers_of(&next.board, king, mover.opposite());
+            let before = position.board.find_king(mover).map(|king| attackers_of(&position.board, king, mover.opposite()));
+            if let Some(piece) = piece.filter(|piece| piece.piece_type != PieceType::King) {
+                let pinner = attackers.iter().find(|attacker| {
+                    !before.as_ref().is_some_and(|before| before.contains(attacker))
+                        && attacker.location.between(king).contains(&piece.location)
+                });
+                if let Some(pinner) = pinner {
+                    return IllegalReason::Pinned { piece, pinner: *pinner };
+                }
+            }
+            if let Some(attacker) = attackers.first() {
+                return IllegalReason::KingInCheck { attacker: *attacker };
+            }
+        }
+    }
+    IllegalReason::VariantRule { variant: variant.name().to_string() }
+}
+
+/// Where the piece could go on a board of the same size with nothing else on it.
+fn alone(board: &Chessboard, piece: Piece) -> Vec<Square> {
+    let mut empty = board.cleared();
+    match empty.add_piece(piece) {
+        Ok(()) => piece.can_move(&empty),
+        Err(_) => vec![],
+    }
+}
+
+fn piece_name(piece_type: PieceType) -> String {
+    match piece_type {
+        PieceType::Pawn => "pawn".to_string(),
+        PieceType::Knight => "knight".to_string(),
+        PieceType::Bishop => "bishop".to_string(),
+        PieceType::Rook => "rook".to_string(),
+        PieceType::Queen => "queen".to_string(),
+        PieceType::King => "king".to_string(),
+        PieceType::Fairy(_) => format!("{} piece", piece_type.get_symbol()),
+    }
+}
+
+/// "white knight on f3"
+fn describe(piece: &Piece, board: &Chessboard) -> String {
+    format!("{} {} on {}", color_name(piece.color), piece_name(piece.piece_type), board.square_to_algebraic(piece.location))
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Square;
+    use crate::chess_piece::{Color, PieceType};
+    use crate::explain::{explain_illegal_move, IllegalReason};
+    use crate::moves::Move;
+    use crate::position::{Position, STANDARD_START_FEN};
+    use crate::variant::{Atomic, Crazyhouse, Standard};
+
+    fn explain(fen: &str, from: &str, to: &str) -> Option<IllegalReason> {
+        let position = Position::from_fen(fen).unwrap();
+        let square = |algebraic| position.board.algebraic_to_square(algebraic).unwrap();
+        explain_illegal_move(&Standard, &position, Move::new(square(from), square(to)))
+    }
+
+    fn text(fen: &str, from: &str, to: &str) -> String {
+        let board = Position::from_fen(fen).unwrap().board;
+        explain(fen, from, to).expect("the move is illegal").to_text(&board)
+    }
+
+    fn square(algebraic: &str) -> Square {
+        algebraic.parse().unwrap()
+    }
+
+    #[test]
+    fn legal_moves_need_no_explanation() {
+        assert_eq!(explain(STANDARD_START_FEN, "e2", "e4"), None);
+        assert_eq!(explain(STANDARD_START_FEN, "g1", "f3"), None);
+    }
+
+    #[test]
+    fn whose_piece_and_how_it_moves() {
+        assert_eq!(explain(STANDARD_START_FEN, "e4", "e5"), Some(IllegalReason::NoPiece { square: square("e4") }));
+        assert_eq!(text(STANDARD_START_FEN, "e7", "e5"), "The black pawn on e7 belongs to black and it is white's move");
+        assert_eq!(text(STANDARD_START_FEN, "g1", "g3"), "A knight can not move from g1 to g3");
+        assert_eq!(text(STANDARD_START_FEN, "e2", "e5"), "A pawn can not move from e2 to e5");
+        assert_eq!(text(STANDARD_START_FEN, "e2", "d3"), "The white pawn on e2 can only move diagonally to capture and there is nothing on d3");
+        assert_eq!(text(STANDARD_START_FEN, "g1", "e2"), "The white knight on g1 can not capture its own white pawn on e2");
+    }
+
+    #[test]
+    fn blockers_are_named() {
+        assert_eq!(text(STANDARD_START_FEN, "a1", "a4"), "The white rook on a1 is blocked by the white pawn on a2");
+        assert_eq!(text(STANDARD_START_FEN, "f1", "c4"), "The white bishop on f1 is blocked by the white pawn on e2");
+        let blocked_pawn = "4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1";
+        assert_eq!(text(blocked_pawn, "e2", "e4"), "The white pawn on e2 is blocked by the black knight on e3");
+        assert_eq!(text(blocked_pawn, "e2", "e3"), "The white pawn on e2 is blocked by the black knight on e3");
+    }
+
+    #[test]
+    fn pins_and_checks() {
+        let pinned = "rnbqk1nr/pppp1ppp/8/4p3/1b1P4/2N5/PPP1PPPP/R1BQKBNR w KQkq - 2 3";
+        assert_eq!(text(pinned, "c3", "e4"), "The white knight on c3 is pinned to its king by the black bishop on b4");
+
+        let checked = "4k3/8/8/8/7q/8/P7/4K3 w - - 0 1";
+        assert_eq!(text(checked, "a2", "a3"), "The king would be in check from the black queen on h4");
+        let walk_in = "4k3/8/8/8/8/8/3r4/4K3 w - - 0 1";
+        assert_eq!(
+            explain(walk_in, "e1", "d1"),
+            Some(IllegalReason::KingInCheck { attacker: Position::from_fen(walk_in).unwrap().board.get_piece_at(square("d2")).unwrap() })
+        );
+    }
+
+    #[test]
+    fn castling() {
+        let no_rights = "r3k2r/8/8/8/8/8/8/R3K2R w Qk - 0 1";
+        assert_eq!(text(no_rights, "e1", "g1"), "White can no longer castle king side, the king or that rook has moved");
+        assert_eq!(explain(no_rights, "e1", "c1"), None);
+        assert_eq!(text(STANDARD_START_FEN, "e1", "g1"), "The white king on e1 is blocked by the white bishop on f1");
+        let through_check = "4k3/8/8/8/2b5/8/8/R3K2R w KQ - 0 1";
+        assert_eq!(text(through_check, "e1", "g1"), "The king can not castle while f1 is attacked by the black bishop on c4");
+        let out_of_check = "4k3/8/8/8/1b6/8/8/R3K2R w KQ - 0 1";
+        assert_eq!(
+            explain(out_of_check, "e1", "c1"),
+            Some(IllegalReason::CastlingThroughCheck {
+                square: square("e1"),
+                attacker: Position::from_fen(out_of_check).unwrap().board.get_piece_at(square("b4")).unwrap()
+            })
+        );
+    }
+
+    #[test]
+    fn promotions_drops_and_variants() {
+        let promotion = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(explain(promotion, "b7", "b8"), Some(IllegalReason::MissingPromotion));
+
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1").unwrap();
+        let drop = |piece_type, to: &str| Move::Drop { piece_type, to: square(to) };
+        assert_eq!(explain_illegal_move(&Crazyhouse, &position, drop(PieceType::Knight, "e4")), None);
+        assert_eq!(
+            explain_illegal_move(&Crazyhouse, &position, drop(PieceType::Queen, "e4")),
+            Some(IllegalReason::NotInPocket { piece_type: PieceType::Queen })
+        );
+        assert!(matches!(
+            explain_illegal_move(&Crazyhouse, &position, drop(PieceType::Knight, "e1")),
+            Some(IllegalReason::SquareOccupied { occupant }) if occupant.color == Color::White
+        ));
+
+        // Capturing next to your own king blows it up in Atomic
+        let atomic = Position::from_fen("4k3/8/8/8/8/8/3p4/3QK3 w - - 0 1").unwrap();
+        let capture = Move::new(square("d1"), square("d2"));
+        assert_eq!(explain_illegal_move(&Standard, &atomic, capture), None);
+        assert_eq!(
+            explain_illegal_move(&Atomic, &atomic, capture).map(|reason| reason.to_text(&atomic.board)),
+            Some("The rules of Atomic do not allow this move".to_string())
+        );
+        let mate = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert_eq!(explain_illegal_move(&Standard, &mate, Move::new(square("g8"), square("h8"))), Some(IllegalReason::GameOver));
+    }
+
+    #[test]
+    fn squares_named_as_on_the_board() {
+        let tall = "4k3/8/8/8/8/8/8/8/P7/R3K3 w - - 0 1";
+        assert_eq!(text(tall, "a5", "a6"), "There is no piece on a5");
+        assert_eq!(text(tall, "a10", "a9"), "There is no piece on a10");
+        assert_eq!(text(tall, "a1", "a4"), "The white rook on a1 is blocked by the white pawn on a2");
+        assert_eq!(text(tall, "e1", "e3"), "A king can not move from e1 to e3");
+    }
+}
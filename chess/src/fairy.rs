@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::Color::{Black, White};
+use crate::chess_piece::Piece;
+use crate::error::ChessError;
+
+const STANDARD_SYMBOLS: &str = "PNBRQK";
+
+/// Movement of a user-defined piece. Implemented by `Betza` definitions, and by any
+/// `Fn(&Chessboard, Piece) -> Vec<Square>` for pieces that do not fit the notation.
+pub trait FairyMovement: Send + Sync {
+    fn movements(&self, board: &Chessboard, piece: Piece) -> Vec<Square>;
+}
+
+impl<F> FairyMovement for F
+where
+    F: Fn(&Chessboard, Piece) -> Vec<Square> + Send + Sync,
+{
+    fn movements(&self, board: &Chessboard, piece: Piece) -> Vec<Square> {
+        self(board, piece)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Atom {
+    leap: (i8, i8),
+    /// None for riders that keep going until blocked
+    range: Option<u8>,
+    move_only: bool,
+    capture_only: bool,
+    /// Jumps over the first piece in its path and lands right behind it
+    hopper: bool,
+    forward: bool,
+    backward: bool,
+    sideways: bool,
+}
+
+/// A piece movement written in Betza notation, e.g. "BN" for the Archbishop or "gQ" for the Grasshopper.
+///
+/// Supported atoms are W, F, D, N, A, H, C, Z, G plus the shorthands R, B, Q and K. Doubling an atom
+/// ("NN") or following it by a number ("W3") makes it a rider. Prefixes: m (move only), c (capture only),
+/// g (grasshopper hop), f/b (forward/backward), v (vertical) and s (sideways).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Betza {
+    atoms: Vec<Atom>,
+}
+
+impl Betza {
+    pub fn parse(notation: &str) -> Result<Betza, ChessError> {
+        let chars: Vec<char> = notation.chars().collect();
+        let mut atoms: Vec<Atom> = vec![];
+        let mut index = 0;
+
+        while index < chars.len() {
+            let mut template = Atom {
+                leap: (0, 0),
+                range: Some(1),
+                move_only: false,
+                capture_only: false,
+                hopper: false,
+                forward: false,
+                backward: false,
+                sideways: false,
+            };
+            while index < chars.len() && chars[index].is_ascii_lowercase() {
+                match chars[index] {
+                    'm' => template.move_only = true,
+                    'c' => template.capture_only = true,
+                    'g' => template.hopper = true,
+                    'f' => template.forward = true,
+                    'b' => template.backward = true,
+                    'v' => {
+                        template.forward = true;
+                        template.backward = true;
+                    }
+                    's' => template.sideways = true,
+                    _ => return Err(ChessError::invalid_piece_definition("Unknown Betza modifier")),
+                }
+                index += 1;
+            }
+
+            let letter = match chars.get(index) {
+                Some(letter) => *letter,
+                None => return Err(ChessError::invalid_piece_definition("Betza modifier without an atom")),
+            };
+            index += 1;
+
+            let mut range = Some(1);
+            if chars.get(index) == Some(&letter) {
+                range = None;
+                index += 1;
+            } else {
+                let digits: String = chars[index..].iter().take_while(|c| c.is_ascii_digit()).collect();
+                if !digits.is_empty() {
+                    index += digits.len();
+                    range = match digits.parse::<u8>() {
+                        Ok(0) | Err(_) => return Err(ChessError::invalid_piece_definition("Not a valid Betza range")),
+                        Ok(n) => Some(n),
+                    };
+                }
+            }
+
+            let expanded: Vec<((i8, i8), Option<u8>)> = match letter {
+                'R' => vec![((1, 0), None)],
+                'B' => vec![((1, 1), None)],
+                'Q' => vec![((1, 0), None), ((1, 1), None)],
+                'K' => vec![((1, 0), range), ((1, 1), range)],
+                _ => vec![(Betza::leap(letter)?, range)],
+            };
+            for (leap, range) in expanded {
+                atoms.push(Atom { leap, range, ..template });
+            }
+        }
+
+        if atoms.is_empty() {
+            return Err(ChessError::invalid_piece_definition("Empty Betza notation"));
+        }
+        Ok(Betza { atoms })
+    }
+
+    fn leap(letter: char) -> Result<(i8, i8), ChessError> {
+        match letter {
+            'W' => Ok((1, 0)),
+            'F' => Ok((1, 1)),
+            'D' => Ok((2, 0)),
+            'N' => Ok((2, 1)),
+            'A' => Ok((2, 2)),
+            'H' => Ok((3, 0)),
+            'C' => Ok((3, 1)),
+            'Z' => Ok((3, 2)),
+            'G' => Ok((3, 3)),
+            _ => Err(ChessError::invalid_piece_definition("Unknown Betza atom")),
+        }
+    }
+
+    /// All (x, y) steps of an atom, with `forward` being the y direction the piece advances in
+    fn directions(atom: &Atom, forward: i8) -> Vec<(i8, i8)> {
+        let (a, b) = atom.leap;
+        let mut directions: Vec<(i8, i8)> = vec![];
+        for (x, y) in [(a, b), (b, a)] {
+            for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let step = (x * sx, y * sy);
+                if !directions.contains(&step) {
+                    directions.push(step);
+                }
+            }
+        }
+
+        let restricted = atom.forward || atom.backward || atom.sideways;
+        directions
+            .into_iter()
+            .filter(|(_, y)| {
+                !restricted
+                    || (atom.forward && y.signum() == forward)
+                    || (atom.backward && y.signum() == -forward)
+                    || (atom.sideways && *y == 0)
+            })
+            .collect()
+    }
+}
+
+impl FairyMovement for Betza {
+    fn movements(&self, board: &Chessboard, piece: Piece) -> Vec<Square> {
+        let forward = match piece.color {
+            White => -1,
+            Black => 1,
+        };
+        let mut possible_moves: Vec<Square> = vec![];
+
+        for atom in &self.atoms {
+            for (c, r) in Betza::directions(atom, forward) {
+                let mut hurdle_found = false;
+
+                for (steps, square) in (1u8..).zip(board.ray(piece.location, c, r)) {
+                    let location = board.get_piece_at(square);
+                    if atom.hopper && !hurdle_found {
+                        // Empty squares before the hurdle are not destinations
+                        hurdle_found = location.is_some();
+                    } else if location.is_none() {
+                        if !atom.capture_only && !possible_moves.contains(&square) {
+                            possible_moves.push(square);
+                        }
+                        if atom.hopper {
+                            break;
+                        }
+                    } else {
+                        if let Some(obstacle_piece) = location {
+                            let capturable = obstacle_piece.color != piece.color && !atom.move_only;
+                            if capturable && !possible_moves.contains(&obstacle_piece.location) {
+                                possible_moves.push(obstacle_piece.location);
+                            }
+                        }
+                        break;
+                    }
+                    if !atom.hopper && atom.range.is_some_and(|range| steps >= range) {
+                        break;
+                    }
+                }
+            }
+        }
+        possible_moves
+    }
+}
+
+pub struct FairyPiece {
+    pub name: String,
+    pub movement: Arc<dyn FairyMovement>,
+}
+
+/// User-defined pieces, keyed by their uppercase FEN letter.
+#[derive(Clone, Default)]
+pub struct PieceRegistry {
+    pieces: HashMap<char, Arc<FairyPiece>>,
+}
+
+impl fmt::Debug for PieceRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut symbols: Vec<&char> = self.pieces.keys().collect();
+        symbols.sort();
+        f.debug_struct("PieceRegistry").field("pieces", &symbols).finish()
+    }
+}
+
+impl PieceRegistry {
+    pub fn new() -> PieceRegistry {
+        PieceRegistry::default()
+    }
+
+    /// Archbishop (A), Chancellor (C), Amazon (M), Camel (L) and Grasshopper (G).
+    pub fn with_common_fairies() -> PieceRegistry {
+        let mut registry = PieceRegistry::new();
+        for (symbol, name, betza) in [
+            ('A', "Archbishop", "BN"),
+            ('C', "Chancellor", "RN"),
+            ('M', "Amazon", "QN"),
+            ('L', "Camel", "C"),
+            ('G', "Grasshopper", "gQ"),
+        ] {
+            registry.register_betza(symbol, name, betza).unwrap();
+        }
+        registry
+    }
+
+    pub fn register(&mut self, symbol: char, name: &str, movement: Arc<dyn FairyMovement>) -> Result<(), ChessError> {
+        let symbol = symbol.to_ascii_uppercase();
+        if !symbol.is_ascii_uppercase() {
+            return Err(ChessError::invalid_piece_definition("Piece symbol must be a letter"));
+        }
+        if STANDARD_SYMBOLS.contains(symbol) {
+            return Err(ChessError::invalid_piece_definition("Symbol is used by a standard piece"));
+        }
+        self.pieces.insert(symbol, Arc::new(FairyPiece { name: name.to_string(), movement }));
+        Ok(())
+    }
+
+    pub fn register_betza(&mut self, symbol: char, name: &str, notation: &str) -> Result<(), ChessError> {
+        let betza = Betza::parse(notation)?;
+        self.register(symbol, name, Arc::new(betza))
+    }
+
+    pub fn get(&self, symbol: char) -> Option<&FairyPiece> {
+        self.pieces.get(&symbol.to_ascii_uppercase()).map(|piece| piece.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::board::{Chessboard, Square, CAPABLANCA_FEN};
+    use crate::chess_piece::{Color, Piece, PieceMovement, PieceType};
+    use crate::error::ChessError;
+    use crate::fairy::{Betza, PieceRegistry};
+
+    fn test_fairy_moves(registry: PieceRegistry, fen: &str, piece_pos_alg: &str, expected: Vec<&str>) {
+        let board = Chessboard::from_fen_with_registry(fen, Arc::new(registry)).unwrap();
+        let piece = board.get_piece_at(board.algebraic_to_square(piece_pos_alg).unwrap()).unwrap();
+        let mut possible_moves = piece.can_move(&board);
+        let mut expected: Vec<Square> = expected.iter().map(|alg| board.algebraic_to_square(alg).unwrap()).collect();
+        let sort_key = |square: &Square| (square.y, square.x);
+        possible_moves.sort_by_key(sort_key);
+        expected.sort_by_key(sort_key);
+        assert_eq!(possible_moves, expected);
+    }
+
+    #[test]
+    fn bad_betza_notation() {
+        assert_eq!(Betza::parse(""), Err(ChessError::invalid_piece_definition("Empty Betza notation")));
+        assert_eq!(Betza::parse("X"), Err(ChessError::invalid_piece_definition("Unknown Betza atom")));
+        assert_eq!(Betza::parse("mf"), Err(ChessError::invalid_piece_definition("Betza modifier without an atom")));
+        assert_eq!(Betza::parse("xW"), Err(ChessError::invalid_piece_definition("Unknown Betza modifier")));
+        assert_eq!(Betza::parse("W0"), Err(ChessError::invalid_piece_definition("Not a valid Betza range")));
+    }
+
+    #[test]
+    fn betza_rook_matches_builtin_rook() {
+        let mut registry = PieceRegistry::new();
+        registry.register_betza('E', "Betza rook", "R").unwrap();
+        let board = Chessboard::from_fen_with_registry("8/8/4p3/8/2P1E3/8/8/8", Arc::new(registry)).unwrap();
+        let location = Square::algebraic_to_coords("e4").unwrap();
+        let betza_rook = board.get_piece_at(location).unwrap();
+        let rook = Piece { piece_type: PieceType::Rook, ..betza_rook };
+        let mut betza_moves = betza_rook.can_move(&board);
+        let mut rook_moves = rook.can_move(&board);
+        betza_moves.sort_by_key(|square| (square.y, square.x));
+        rook_moves.sort_by_key(|square| (square.y, square.x));
+        assert_eq!(betza_moves, rook_moves);
+    }
+
+    #[test]
+    fn archbishop_moves_like_bishop_and_knight() {
+        test_fairy_moves(PieceRegistry::with_common_fairies(), "8/8/8/8/8/8/8/A7", "a1", vec![
+            "b2", "c3", "d4", "e5", "f6", "g7", "h8", "b3", "c2"
+        ]);
+    }
+
+    #[test]
+    fn chancellor_captures_but_stops_at_pieces() {
+        test_fairy_moves(PieceRegistry::with_common_fairies(), "8/8/8/8/8/P7/1p6/C1N5", "a1", vec![
+            "a2", "b1", "b3", "c2"
+        ]);
+    }
+
+    #[test]
+    fn camel_leaps_three_by_one() {
+        test_fairy_moves(PieceRegistry::with_common_fairies(), "8/8/8/8/4L3/8/8/8", "e4", vec![
+            "b3", "b5", "d1", "d7", "f1", "f7", "h3", "h5"
+        ]);
+    }
+
+    #[test]
+    fn grasshopper_lands_right_behind_hurdle() {
+        test_fairy_moves(PieceRegistry::with_common_fairies(), "8/8/8/8/G2P3p/8/8/1p6", "a4", vec!["e4"]);
+    }
+
+    #[test]
+    fn grasshopper_captures_behind_hurdle() {
+        test_fairy_moves(PieceRegistry::with_common_fairies(), "8/8/8/8/G1Pp4/8/8/8", "a4", vec!["d4"]);
+    }
+
+    #[test]
+    fn directional_modifiers_follow_piece_color() {
+        let mut registry = PieceRegistry::new();
+        registry.register_betza('S', "Pawn without double step", "fWfcF").unwrap();
+        test_fairy_moves(registry.clone(), "8/8/8/3p1p2/4S3/8/8/8", "e4", vec!["e5", "d5", "f5"]);
+        test_fairy_moves(registry, "8/8/8/8/4s3/3P1P2/8/8", "e4", vec!["e3", "d3", "f3"]);
+    }
+
+    #[test]
+    fn limited_range_rider() {
+        let mut registry = PieceRegistry::new();
+        registry.register_betza('X', "Short rook", "vW2").unwrap();
+        test_fairy_moves(registry, "8/8/8/8/4X3/8/8/8", "e4", vec!["e2", "e3", "e5", "e6"]);
+    }
+
+    #[test]
+    fn custom_movement_closure() {
+        let mut registry = PieceRegistry::new();
+        let corner_jumper = |board: &Chessboard, _piece: Piece| {
+            vec![Square { x: 0, y: 0 }, Square { x: board.width() - 1, y: board.height() - 1 }]
+        };
+        registry.register('J', "Corner jumper", Arc::new(corner_jumper)).unwrap();
+        test_fairy_moves(registry, "8/8/8/8/4J3/8/8/8", "e4", vec!["a8", "h1"]);
+    }
+
+    #[test]
+    fn standard_symbols_can_not_be_registered() {
+        let mut registry = PieceRegistry::new();
+        assert_eq!(
+            registry.register_betza('q', "Fake queen", "Q"),
+            Err(ChessError::invalid_piece_definition("Symbol is used by a standard piece"))
+        );
+    }
+
+    #[test]
+    fn unregistered_fairy_piece_in_fen() {
+        assert_eq!(Chessboard::from_fen("8/8/8/8/4A3/8/8/8").err(), Some(ChessError::invalid_fen(9, 0, "Invalid piece")));
+    }
+
+    #[test]
+    fn capablanca_starting_position() {
+        let board = Chessboard::from_fen_with_registry(CAPABLANCA_FEN, Arc::new(PieceRegistry::with_common_fairies())).unwrap();
+        assert_eq!((board.width(), board.height()), (10, 8));
+        let archbishop = board.get_piece_at(board.algebraic_to_square("c1").unwrap()).unwrap();
+        assert_eq!(archbishop.piece_type, PieceType::Fairy('A'));
+        assert_eq!(archbishop.color, Color::White);
+        let chancellor = board.get_piece_at(board.algebraic_to_square("h8").unwrap()).unwrap();
+        assert_eq!(chancellor.piece_type, PieceType::Fairy('C'));
+        assert_eq!(chancellor.color, Color::Black);
+    }
+}
@@ -0,0 +1,510 @@
+use std::sync::Arc;
+
+use crate::board::Chessboard;
+use crate::chess_piece::Color;
+use crate::error::ChessError;
+use crate::fairy::PieceRegistry;
+use crate::moves::Move;
+use crate::position::{Position, STANDARD_START_FEN};
+use crate::variant::{Outcome, Standard, Variant};
+
+/// Identifies a position in the move tree of a `Game`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// A game as its starting FEN and the moves of one line in UCI notation, the compact
+/// form for storing or sending games.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    pub fen: String,
+    pub moves: Vec<String>,
+}
+
+struct Node {
+    position: Position,
+    /// The move that led here, `None` for the starting position
+    mv: Option<Move>,
+    parent: Option<NodeId>,
+    /// The first child continues the main line, the others are variations
+    children: Vec<NodeId>,
+    /// The child `redo` follows, the last one visited
+    selected: usize,
+    comment: Option<String>,
+    /// Move assessment like "!" or "?!", or a numeric annotation glyph like "$14"
+    annotation: Option<String>,
+}
+
+impl Node {
+    fn new(position: Position, mv: Option<Move>, parent: Option<NodeId>) -> Node {
+        Node { position, mv, parent, children: vec![], selected: 0, comment: None, annotation: None }
+    }
+}
+
+/// A game and its history as a tree of moves. Playing a move after going back
+/// adds a variation instead of throwing away what was played before.
+pub struct Game {
+    nodes: Vec<Node>,
+    current: NodeId,
+    variant: Box<dyn Variant>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new()
+    }
+}
+
+impl Game {
+    /// Standard chess from the initial position, white to move.
+    pub fn new() -> Game {
+        Game::from_fen(STANDARD_START_FEN).expect("the standard starting position is valid")
+    }
+
+    /// Standard chess from the given position.
+    pub fn from_fen(fen: &str) -> Result<Game, ChessError> {
+        Ok(Game::from_position(Position::from_fen(fen)?, Box::new(Standard)))
+    }
+
+    pub fn from_position(position: Position, variant: Box<dyn Variant>) -> Game {
+        Game { nodes: vec![Node::new(position, None, None)], current: NodeId(0), variant }
+    }
+
+    /// Replays a record under the given rules, ending at its last move. Fairy pieces
+    /// in the FEN are looked up in `PieceRegistry::with_common_fairies`.
+    pub fn from_record(record: &GameRecord, variant: Box<dyn Variant>) -> Result<Game, ChessError> {
+        let position = Position::from_fen_with_registry(&record.fen, Arc::new(PieceRegistry::with_common_fairies()))?;
+        let mut game = Game::from_position(position, variant);
+        for uci in &record.moves {
+            let mv = Move::from_uci(game.board(), uci)?;
+            game.play(mv)?;
+        }
+        Ok(game)
+    }
+
+    /// The starting position and the current line, including moves after the current position.
+    pub fn record(&self) -> GameRecord {
+        let mut moves = vec![];
+        let mut node = self.node(NodeId(0));
+        for mv in self.line() {
+            moves.push(mv.to_uci(&node.position.board));
+            let child = node.children.iter().find(|child| self.node(**child).mv == Some(mv));
+            node = self.node(*child.expect("the line follows existing nodes"));
+        }
+        GameRecord { fen: self.node(NodeId(0)).position.to_fen(), moves }
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    fn check_node(&self, id: NodeId) -> Result<(), ChessError> {
+        if id.0 < self.nodes.len() {
+            Ok(())
+        } else {
+            Err(ChessError::invalid_navigation("Unknown node"))
+        }
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.node(self.current).position
+    }
+
+    pub fn board(&self) -> &Chessboard {
+        &self.position().board
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.position().side_to_move
+    }
+
+    pub fn variant(&self) -> &dyn Variant {
+        self.variant.as_ref()
+    }
+
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.variant.legal_moves(self.position())
+    }
+
+    /// Plays the move from the current position. If it was played here before the game
+    /// follows it again, otherwise it starts a new variation.
+    pub fn play(&mut self, mv: Move) -> Result<(), ChessError> {
+        let parent = self.current;
+        let existing = self.node(parent).children.iter().position(|child| self.node(*child).mv == Some(mv));
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                let mut position = self.position().clone();
+                self.variant.play(&mut position, mv)?;
+                self.nodes.push(Node::new(position, Some(mv), Some(parent)));
+                let id = NodeId(self.nodes.len() - 1);
+                self.nodes[parent.0].children.push(id);
+                self.nodes[parent.0].children.len() - 1
+            }
+        };
+        self.nodes[parent.0].selected = index;
+        self.current = self.nodes[parent.0].children[index];
+        Ok(())
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        if let Some(outcome) = self.variant.outcome(self.position()) {
+            return Some(outcome);
+        }
+        if self.repetitions() >= 3 {
+            return Some(Outcome::Draw);
+        }
+        None
+    }
+
+    /// How many times the current position has occurred on the way here, itself included.
+    pub fn repetitions(&self) -> usize {
+        let current = self.position();
+        self.path(self.current)
+            .iter()
+            .filter(|id| self.node(**id).position.is_repetition_of(current))
+            .count()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.node(self.current).parent.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.node(self.current).children.is_empty()
+    }
+
+    /// Steps back one move and returns it. The move stays in the tree for `redo`.
+    pub fn undo(&mut self) -> Option<Move> {
+        let node = self.node(self.current);
+        let parent = node.parent?;
+        let mv = node.mv;
+        self.current = parent;
+        mv
+    }
+
+    /// Steps forward along the last visited continuation.
+    pub fn redo(&mut self) -> Option<Move> {
+        let node = self.node(self.current);
+        let child = *node.children.get(node.selected)?;
+        self.current = child;
+        self.node(child).mv
+    }
+
+    /// Number of moves played to reach the current position.
+    pub fn ply(&self) -> usize {
+        self.path(self.current).len() - 1
+    }
+
+    /// Goes back or forward along the current line to the position after `ply` moves.
+    pub fn go_to_ply(&mut self, ply: usize) -> Result<(), ChessError> {
+        while self.ply() > ply {
+            self.undo();
+        }
+        while self.ply() < ply {
+            if self.redo().is_none() {
+                return Err(ChessError::invalid_navigation("The line is not that long"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn go_to_start(&mut self) {
+        self.current = NodeId(0);
+    }
+
+    pub fn go_to_end(&mut self) {
+        while self.redo().is_some() {}
+    }
+
+    /// Jumps to any position in the tree. `redo` from the start will then lead back to it.
+    pub fn go_to(&mut self, id: NodeId) -> Result<(), ChessError> {
+        self.check_node(id)?;
+        let path = self.path(id);
+        for pair in path.windows(2) {
+            let (parent, child) = (pair[0], pair[1]);
+            let index = self.node(parent).children.iter().position(|id| *id == child).unwrap_or(0);
+            self.nodes[parent.0].selected = index;
+        }
+        self.current = id;
+        Ok(())
+    }
+
+    pub fn current_node(&self) -> NodeId {
+        self.current
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    /// The move that led to the node, `None` for the root.
+    pub fn move_at(&self, id: NodeId) -> Option<Move> {
+        self.nodes.get(id.0).and_then(|node| node.mv)
+    }
+
+    pub fn position_at(&self, id: NodeId) -> Option<&Position> {
+        self.nodes.get(id.0).map(|node| &node.position)
+    }
+
+    /// Nodes from the root to the given one, both included.
+    fn path(&self, id: NodeId) -> Vec<NodeId> {
+        let mut path = vec![id];
+        while let Some(parent) = self.node(*path.last().unwrap()).parent {
+            path.push(parent);
+        }
+        path.reverse();
+        path
+    }
+
+    /// The moves played to reach the current position.
+    pub fn moves(&self) -> Vec<Move> {
+        self.path(self.current).iter().filter_map(|id| self.node(*id).mv).collect()
+    }
+
+    /// The moves of the current line, including those after the current position that `redo` would follow.
+    pub fn line(&self) -> Vec<Move> {
+        let mut moves = self.moves();
+        let mut node = self.node(self.current);
+        while let Some(child) = node.children.get(node.selected) {
+            node = self.node(*child);
+            moves.extend(node.mv);
+        }
+        moves
+    }
+
+    /// The main line, following the first continuation from the start.
+    pub fn mainline(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        let mut node = self.node(NodeId(0));
+        while let Some(child) = node.children.first() {
+            node = self.node(*child);
+            moves.extend(node.mv);
+        }
+        moves
+    }
+
+    /// The continuations played from the given node. The first one is the main line.
+    pub fn variations(&self, id: NodeId) -> Vec<NodeId> {
+        self.nodes.get(id.0).map(|node| node.children.clone()).unwrap_or_default()
+    }
+
+    /// Makes the node the first continuation of its parent, so the main line goes through it.
+    pub fn promote_variation(&mut self, id: NodeId) -> Result<(), ChessError> {
+        self.check_node(id)?;
+        let parent = match self.node(id).parent {
+            Some(parent) => parent,
+            None => return Err(ChessError::invalid_navigation("The start of the game is not a variation")),
+        };
+        let node = &mut self.nodes[parent.0];
+        let selected = node.children[node.selected];
+        let index = node.children.iter().position(|child| *child == id).unwrap();
+        let promoted = node.children.remove(index);
+        node.children.insert(0, promoted);
+        node.selected = node.children.iter().position(|child| *child == selected).unwrap();
+        Ok(())
+    }
+
+    pub fn set_comment(&mut self, id: NodeId, comment: &str) -> Result<(), ChessError> {
+        self.check_node(id)?;
+        self.nodes[id.0].comment = if comment.is_empty() { None } else { Some(comment.to_string()) };
+        Ok(())
+    }
+
+    pub fn comment(&self, id: NodeId) -> Option<&str> {
+        self.nodes.get(id.0).and_then(|node| node.comment.as_deref())
+    }
+
+    /// Marks the move that led to the node as good or bad, an empty annotation removes it.
+    pub fn set_annotation(&mut self, id: NodeId, annotation: &str) -> Result<(), ChessError> {
+        self.check_node(id)?;
+        self.nodes[id.0].annotation = if annotation.is_empty() { None } else { Some(annotation.to_string()) };
+        Ok(())
+    }
+
+    pub fn annotation(&self, id: NodeId) -> Option<&str> {
+        self.nodes.get(id.0).and_then(|node| node.annotation.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Chessboard, Square};
+    use crate::chess_piece::Color;
+    use crate::error::ChessError;
+    use crate::game::{Game, GameRecord};
+    use crate::moves::Move;
+    use crate::position::Position;
+    use crate::variant::{Crazyhouse, KingOfTheHill, Outcome, Standard};
+
+    fn normal(from: &str, to: &str) -> Move {
+        Move::new(Square::algebraic_to_coords(from).unwrap(), Square::algebraic_to_coords(to).unwrap())
+    }
+
+    fn play_all(game: &mut Game, moves: &[(&str, &str)]) {
+        for (from, to) in moves {
+            game.play(normal(from, to)).unwrap();
+        }
+    }
+
+    #[test]
+    fn new_game_has_twenty_moves() {
+        let game = Game::new();
+        assert_eq!(game.side_to_move(), Color::White);
+        assert_eq!(game.legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn playing_moves_alternates_sides() {
+        let mut game = Game::new();
+        game.play(normal("e2", "e4")).unwrap();
+        assert_eq!(game.side_to_move(), Color::Black);
+        assert!(game.play(normal("d2", "d4")).is_err());
+        game.play(normal("e7", "e5")).unwrap();
+        assert_eq!(game.side_to_move(), Color::White);
+    }
+
+    #[test]
+    fn game_uses_its_variant() {
+        let board = Chessboard::from_fen("4k3/8/8/8/8/4K3/8/8").unwrap();
+        let mut game = Game::from_position(Position::new(board, Color::White), Box::new(KingOfTheHill));
+        game.play(normal("e3", "d4")).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::Win(Color::White)));
+        assert_eq!(game.variant().name(), "King of the Hill");
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let mut game = Game::new();
+        assert!(!game.can_undo());
+        play_all(&mut game, &[("e2", "e4"), ("e7", "e5")]);
+        assert_eq!(game.undo(), Some(normal("e7", "e5")));
+        assert_eq!(game.side_to_move(), Color::Black);
+        assert_eq!(game.undo(), Some(normal("e2", "e4")));
+        assert_eq!(game.undo(), None);
+        assert_eq!(game.legal_moves().len(), 20);
+        assert_eq!(game.redo(), Some(normal("e2", "e4")));
+        assert_eq!(game.redo(), Some(normal("e7", "e5")));
+        assert!(!game.can_redo());
+    }
+
+    #[test]
+    fn jumping_to_a_ply() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("e2", "e4"), ("e7", "e5"), ("g1", "f3")]);
+        game.go_to_ply(1).unwrap();
+        assert_eq!(game.moves(), vec![normal("e2", "e4")]);
+        assert_eq!(game.line().len(), 3);
+        game.go_to_end();
+        assert_eq!(game.ply(), 3);
+        game.go_to_start();
+        assert_eq!(game.ply(), 0);
+        assert_eq!(game.go_to_ply(4), Err(ChessError::invalid_navigation("The line is not that long")));
+    }
+
+    #[test]
+    fn playing_after_undo_creates_a_variation() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("e2", "e4"), ("e7", "e5")]);
+        game.undo();
+        let branch = game.current_node();
+        play_all(&mut game, &[("c7", "c5")]);
+        let sicilian = game.current_node();
+
+        assert_eq!(game.variations(branch).len(), 2);
+        assert_eq!(game.mainline(), vec![normal("e2", "e4"), normal("e7", "e5")]);
+        assert_eq!(game.line(), vec![normal("e2", "e4"), normal("c7", "c5")]);
+
+        game.promote_variation(sicilian).unwrap();
+        assert_eq!(game.mainline(), vec![normal("e2", "e4"), normal("c7", "c5")]);
+    }
+
+    #[test]
+    fn replaying_a_move_follows_the_existing_branch() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("e2", "e4")]);
+        let first = game.current_node();
+        game.undo();
+        play_all(&mut game, &[("e2", "e4")]);
+        assert_eq!(game.current_node(), first);
+        assert_eq!(game.variations(game.root()).len(), 1);
+    }
+
+    #[test]
+    fn going_to_a_node_in_another_variation() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("e2", "e4"), ("e7", "e5")]);
+        let open_game = game.current_node();
+        game.undo();
+        play_all(&mut game, &[("c7", "c5")]);
+        game.go_to(open_game).unwrap();
+        assert_eq!(game.moves(), vec![normal("e2", "e4"), normal("e7", "e5")]);
+        game.go_to_start();
+        game.go_to_end();
+        assert_eq!(game.current_node(), open_game);
+    }
+
+    #[test]
+    fn comments_on_moves() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("f2", "f3")]);
+        let node = game.current_node();
+        game.set_comment(node, "Weakens the king").unwrap();
+        assert_eq!(game.comment(node), Some("Weakens the king"));
+        game.set_comment(node, "").unwrap();
+        assert_eq!(game.comment(node), None);
+        game.set_annotation(node, "?!").unwrap();
+        assert_eq!(game.annotation(node), Some("?!"));
+        game.set_annotation(node, "").unwrap();
+        assert_eq!(game.annotation(node), None);
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("e2", "e4"), ("d7", "d5"), ("e4", "d5")]);
+        game.undo();
+        let record = game.record();
+        assert_eq!(record.fen, crate::position::STANDARD_START_FEN);
+        assert_eq!(record.moves, vec!["e2e4", "d7d5", "e4d5"]);
+
+        let replayed = Game::from_record(&record, Box::new(Standard)).unwrap();
+        assert_eq!(replayed.ply(), 3);
+        assert_eq!(replayed.position().to_fen(), "rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2");
+    }
+
+    #[test]
+    fn record_with_drops() {
+        let record = GameRecord { fen: "4k3/8/8/8/8/8/8/4K3[N] w - - 0 1".to_string(), moves: vec!["N@f3".to_string()] };
+        let game = Game::from_record(&record, Box::new(Crazyhouse)).unwrap();
+        assert_eq!(game.record(), record);
+    }
+
+    #[test]
+    fn illegal_moves_in_a_record_are_rejected() {
+        let record = GameRecord { fen: crate::position::STANDARD_START_FEN.to_string(), moves: vec!["e2e5".to_string()] };
+        assert!(matches!(Game::from_record(&record, Box::new(Standard)), Err(ChessError::IllegalMove { .. })));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn record_as_json() {
+        let mut game = Game::new();
+        play_all(&mut game, &[("e2", "e4")]);
+        let json = serde_json::to_string(&game.record()).unwrap();
+        assert_eq!(json, r#"{"fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1","moves":["e2e4"]}"#);
+        let record: GameRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(record, game.record());
+    }
+
+    #[test]
+    fn threefold_repetition_is_a_draw() {
+        let mut game = Game::new();
+        let shuffle = [("g1", "f3"), ("g8", "f6"), ("f3", "g1"), ("f6", "g8")];
+        play_all(&mut game, &shuffle);
+        assert_eq!(game.outcome(), None);
+        play_all(&mut game, &shuffle);
+        assert_eq!(game.repetitions(), 3);
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+}
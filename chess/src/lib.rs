@@ -0,0 +1,74 @@
+//! Chess board, move generation and rules, with support for variants and fairy pieces.
+//!
+//! ```
+//! use chess::{Game, Move, Square};
+//!
+//! let mut game = Game::new();
+//! let e2 = Square::algebraic_to_coords("e2").unwrap();
+//! let e4 = Square::algebraic_to_coords("e4").unwrap();
+//! game.play(Move::new(e2, e4)).unwrap();
+//! ```
+
+mod analysis;
+mod annotate;
+mod attacks;
+pub mod board;
+pub mod builder;
+mod chess_piece;
+mod database;
+mod elo;
+mod epd;
+mod error;
+pub mod eval;
+mod explain;
+pub mod fairy;
+mod game;
+mod moves;
+mod pgn;
+pub mod position;
+mod puzzle;
+pub mod search;
+mod see;
+#[cfg(feature = "server")]
+pub mod server;
+mod tournament;
+mod tt;
+mod tuning;
+pub mod uci;
+pub mod variant;
+
+pub use board::Square;
+pub use chess_piece::{Color, Piece, PieceMovement, PieceType};
+pub use error::ChessError;
+pub use game::{Game, GameRecord, NodeId};
+pub use moves::Move;
+pub use position::Position;
+pub use variant::Outcome;
+
+/// The board type, under the name most callers look for.
+pub type Board = board::Chessboard;
+
+/// Questions about a position that the move rules answer: attacks, checks, captures and why a
+/// move is illegal.
+pub mod rules {
+    pub use crate::attacks::{attack_counts, attackers_of, contested_squares, hanging_pieces, is_square_attacked};
+    pub use crate::explain::{explain_illegal_move, IllegalReason};
+    pub use crate::moves::{is_capture, is_in_check};
+    pub use crate::see::is_capture_safe;
+}
+
+/// Working with whole games and collections of them: analysis and annotation, PGN, EPD test
+/// suites, puzzles, game databases, engine tournaments and evaluation tuning.
+pub mod tools {
+    pub use crate::analysis::{analyze, analyze_position, numbered_line, san_line, Analysis, AnalysisLine, Score};
+    pub use crate::annotate::{annotate_game, Judgement};
+    pub use crate::database::{DatabaseGame, Explorer, ExplorerMove, GameDatabase, ResultStats};
+    pub use crate::elo::{MatchScore, Sprt, SprtResult};
+    pub use crate::epd::{parse_epd, EpdEntry, EpdResult};
+    pub use crate::pgn::{parse_pgn, strip_move_number, Pgn};
+    pub use crate::puzzle::{load_puzzles, Puzzle, PuzzleAttempt, PuzzleScore, PuzzleVerdict};
+    pub use crate::tournament::{
+        load_openings, play_game, Adjudication, EnginePlayer, GameSummary, Player, Termination, Tournament, UciPlayer,
+    };
+    pub use crate::tuning::{load_labeled_positions, LabeledPosition, Tuner};
+}
@@ -1,10 +1,105 @@
-use crate::board::Chessboard;
+use std::env;
+use std::io;
+use std::process;
 
-mod board;
-mod chess_piece;
-mod moves;
+use chess::Game;
+
+use crate::cli::play::PlaySession;
+use crate::cli::puzzle::PuzzleSession;
+use crate::cli::Options;
+
+mod cli;
+
+const USAGE: &str = "Usage:
+  chess                      print the starting position
+  chess play [options]       play against the engine
+      --side white|black     the side you play, white by default
+      --depth N              engine search depth
+      --time SECONDS         engine thinking time per move
+      --nodes N              engine node budget per move
+      --fen FEN              start from this position
+      --variant NAME         standard, king-of-the-hill, three-check, atomic or crazyhouse
+      --eval FILE            evaluation weights written by chess tune
+      --threads N            search threads, 1 by default
+  chess analyze [options]    show the best moves of a position with their scores and lines
+      --fen FEN              the position, the starting position by default
+      --variant NAME         as for play
+      --lines N              how many moves to show, 3 by default
+      --depth, --time, --nodes, --eval and --threads as for play, depth 4 by default
+  chess annotate [options]   mark inaccuracies, mistakes and blunders in games with ?!, ? and ??
+      --pgn FILE             the games to annotate
+      --output FILE          where to write the annotated games, stdout by default
+      --depth, --time, --nodes, --eval and --threads as for play, depth 3 by default
+  chess puzzles [options]    solve puzzles, type moves like e2e4, 'skip' or 'quit'
+      --file FILE            puzzles in the Lichess CSV format
+      --theme NAME           only puzzles with this theme, like mateIn2
+      --count N              at most this many puzzles
+  chess import [options]     add games to a local database indexed by position
+      --pgn FILE             the games to add
+      --db FILE              the database, created if it does not exist
+  chess explore [options]    show the moves played from a position in the database and how they scored
+      --db FILE              the database written by chess import
+      --fen FEN              the position, the starting position by default
+      --variant NAME         as for play
+      --moves MOVES          moves in SAN played from there first, like \"1. e4 e5 2. Nf3\"
+      --games N              how many of the games reaching the position to list, 5 by default
+  chess epd [options]        run a test suite like WAC or STS and count the best moves found
+      --file FILE            positions in EPD with bm, am and id operations
+      --depth, --time, --nodes, --eval and --threads as for play, a second per position by default
+  chess uci                  talk UCI on stdin/stdout, for GUIs and tournament runners
+  chess tournament [options] play a match between two engines
+      --first SPEC           e.g. depth=3, nodes=20000,name=small,eval=FILE,threads=2 or uci=/path/to/engine,time=0.1
+                             search features switch with e.g. lmr=off or null-move=off
+      --second SPEC
+      --games N              number of games, 10 by default
+      --openings FILE        one FEN per line, each played with both colors
+      --sprt ELO0,ELO1       stop once the SPRT accepts either bound
+      --max-plies N          adjudicate longer games as draws
+  chess tune [options]       tune the evaluation weights on positions labeled with results
+      --positions FILE       one FEN per line followed by 1-0, 0-1 or 1/2-1/2
+      --eval FILE            weights to start from, the standard ones by default
+      --passes N             at most this many passes over the weights, 500 by default
+      --output FILE          where to write the weights, stdout by default
+  chess bench [--depth N]    count the nodes searched with each search feature";
 
 fn main() {
-    let board = Chessboard::new_with_pieces();
-    board.print_board();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        None => {
+            Game::new().board().print_board();
+            Ok(())
+        }
+        Some("play") => play(&args[1..]),
+        Some("uci") => cli::uci::run(io::stdin().lock(), &mut io::stdout()).map_err(|error| error.to_string()),
+        Some("tournament") => Options::parse(&args[1..]).and_then(|options| cli::tournament::run(&options)),
+        Some("analyze") => Options::parse(&args[1..]).and_then(|options| cli::analyze::run(&options)),
+        Some("annotate") => Options::parse(&args[1..]).and_then(|options| cli::annotate::run(&options)),
+        Some("puzzles") => puzzles(&args[1..]),
+        Some("import") => Options::parse(&args[1..]).and_then(|options| cli::database::import(&options)),
+        Some("explore") => Options::parse(&args[1..]).and_then(|options| cli::database::explore(&options)),
+        Some("epd") => Options::parse(&args[1..]).and_then(|options| cli::epd::run(&options)),
+        Some("bench") => Options::parse(&args[1..]).and_then(|options| cli::bench::run(&options)),
+        Some("tune") => Options::parse(&args[1..]).and_then(|options| cli::tune::run(&options)),
+        Some("help") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(format!("Unknown command '{}'", command)),
+    };
+    if let Err(error) = result {
+        eprintln!("{}\n\n{}", error, USAGE);
+        process::exit(2);
+    }
+}
+
+fn play(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let mut session = PlaySession::from_options(&options)?;
+    session.run(io::stdin().lock(), &mut io::stdout()).map_err(|error| error.to_string())
+}
+
+fn puzzles(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let mut session = PuzzleSession::from_options(&options)?;
+    session.run(io::stdin().lock(), &mut io::stdout()).map_err(|error| error.to_string())
 }
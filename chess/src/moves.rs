@@ -1,181 +1,447 @@
+use crate::attacks::is_square_attacked;
 use crate::board::{Chessboard, Square};
 use crate::chess_piece::Color::{Black, White};
-use crate::chess_piece::Piece;
+use crate::chess_piece::PieceType::{Bishop, King, Knight, Pawn, Queen, Rook};
+use crate::chess_piece::{Color, Piece, PieceType};
+use crate::error::ChessError;
+use crate::position::Position;
+use crate::variant::Variant;
 
-pub fn pawn_movements(board: Chessboard, piece: Piece) -> Vec<Square>{
-    let row= piece.location.y;
-    let col = piece.location.x;
-    let mut possible_moves: Vec<Square> = vec![];
-    // Awkward out of bonds check...
-    if(0..8).contains(&(row + 1)) && (0..8).contains(&(row as i8 - 1)) &&
-        (0..8).contains(&(col + 1)) && (0..8).contains(&(col as i8 - 1)) {
-        match piece.color {
-            Black => {
-                let right_capture = board.board[row + 1][col + 1];
-                let left_capture = board.board[row + 1][col - 1];
-                let forward_once = board.board[row + 1][col];
-                let forward_twice = board.board[row + 2][col];
-
-                if let Some(right_cap) = right_capture {
-                    if right_cap.color == White {
-                        possible_moves.push(right_cap.location);
-                    }
-                }
-                if let Some(left_cap) = left_capture {
-                    if left_cap.color == White {
-                        possible_moves.push(left_cap.location);
-                    }
-                }
-                if forward_once.is_none() {
-                    possible_moves.push(Square { y: row + 1, x: col });
-                }
-                if forward_twice.is_none() && piece.location.y == 1 {
-                    possible_moves.push(Square { y: row + 2, x: col });
-                }
+pub const PROMOTION_PIECES: [PieceType; 4] = [Queen, Rook, Bishop, Knight];
+
+/// Castling is a king move of two squares and en passant a pawn capture onto the
+/// en passant square, the rook and the captured pawn follow when the move is played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Move {
+    Normal { from: Square, to: Square, promotion: Option<PieceType> },
+    /// Placing a piece from the pocket, as in Crazyhouse
+    Drop { piece_type: PieceType, to: Square },
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move::Normal { from, to, promotion: None }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceType) -> Move {
+        Move::Normal { from, to, promotion: Some(promotion) }
+    }
+
+    pub fn from(&self) -> Option<Square> {
+        match *self {
+            Move::Normal { from, .. } => Some(from),
+            Move::Drop { .. } => None,
+        }
+    }
+
+    pub fn to(&self) -> Square {
+        match *self {
+            Move::Normal { to, .. } => to,
+            Move::Drop { to, .. } => to,
+        }
+    }
+
+    /// Coordinate notation as spoken by UCI engines: "e2e4", "e7e8q", and "N@f3" for drops.
+    pub fn to_uci(&self, board: &Chessboard) -> String {
+        match *self {
+            Move::Normal { from, to, promotion } => {
+                let promotion = promotion.map(|piece_type| piece_type.get_symbol().to_lowercase()).unwrap_or_default();
+                format!("{}{}{}", board.square_to_algebraic(from), board.square_to_algebraic(to), promotion)
             }
-            White => {
-                let left_capture = board.board[row - 1][col - 1];
-                let right_capture = board.board[row - 1][col + 1];
-                let forward_once = board.board[row - 1][col];
-                let forward_twice = board.board[row - 2][col];
-                if let Some(left_cap) = left_capture {
-                    if left_cap.color == Black {
-                        possible_moves.push(left_cap.location);
-                    }
-                }
-                if let Some(right_cap) = right_capture {
-                    if right_cap.color == Black {
-                        possible_moves.push(right_cap.location);
-                    }
+            Move::Drop { piece_type, to } => format!("{}@{}", piece_type.get_symbol(), board.square_to_algebraic(to)),
+        }
+    }
+
+    /// Reads the notation written by `to_uci`. Only checks that the squares are on the board, not that the move is legal.
+    pub fn from_uci(board: &Chessboard, uci: &str) -> Result<Move, ChessError> {
+        let square = |algebraic: &str| {
+            board
+                .algebraic_to_square(algebraic)
+                .map_err(|_| ChessError::invalid_notation(uci, "Not a square on the board"))
+        };
+        let piece = |symbol: &str| {
+            let mut chars = symbol.chars();
+            match (chars.next().and_then(PieceType::from_symbol), chars.next()) {
+                (Some(piece_type), None) if piece_type != King => Ok(piece_type),
+                _ => Err(ChessError::invalid_notation(uci, "Not a valid piece")),
+            }
+        };
+
+        if let Some((piece_type, to)) = uci.split_once('@') {
+            return Ok(Move::Drop { piece_type: piece(piece_type)?, to: square(to)? });
+        }
+        // The second square starts at the second letter, ranks may have two digits on big boards
+        let split = match uci.char_indices().skip(1).find(|(_, c)| c.is_ascii_lowercase()) {
+            Some((index, _)) => index,
+            None => return Err(ChessError::invalid_notation(uci, "Expected two squares")),
+        };
+        let (from, rest) = uci.split_at(split);
+        let promotion_at = rest.char_indices().skip(1).find(|(_, c)| c.is_ascii_lowercase()).map(|(index, _)| index);
+        let (to, promotion) = match promotion_at {
+            Some(index) => (&rest[..index], Some(piece(&rest[index..])?)),
+            None => (rest, None),
+        };
+        Ok(Move::Normal { from: square(from)?, to: square(to)?, promotion })
+    }
+
+    /// Reads standard algebraic notation as written by `to_san`. Check marks and annotations like
+    /// "!?" are ignored, "0-0" is accepted for castling, promotions may leave out the "=" and
+    /// pieces may be named by more of their square than needed. The move has to be legal.
+    pub fn from_san(variant: &dyn Variant, position: &Position, san: &str) -> Result<Move, ChessError> {
+        let board = &position.board;
+        let text = match san.trim_end_matches(['+', '#', '!', '?']) {
+            "0-0" => "O-O",
+            "0-0-0" => "O-O-O",
+            text => text,
+        };
+        let legal_moves = variant.legal_moves(position);
+        let illegal = || ChessError::invalid_notation(san, "Not a legal move here");
+
+        if text == "O-O" || text == "O-O-O" {
+            return legal_moves
+                .into_iter()
+                .find(|mv| match *mv {
+                    Move::Normal { from, to, .. } => board
+                        .get_piece_at(from)
+                        .is_some_and(|piece| is_castling(piece, from, to) && (to.x > from.x) == (text == "O-O")),
+                    Move::Drop { .. } => false,
+                })
+                .ok_or_else(illegal);
+        }
+        if let Some((piece, to)) = text.split_once('@') {
+            let piece_type = match piece.chars().next() {
+                None => Pawn,
+                Some(symbol) => PieceType::from_symbol(symbol).unwrap_or(PieceType::Fairy(symbol)),
+            };
+            let to = board
+                .algebraic_to_square(to)
+                .map_err(|_| ChessError::invalid_notation(san, "Not a square on the board"))?;
+            let mv = Move::Drop { piece_type, to };
+            return if legal_moves.contains(&mv) { Ok(mv) } else { Err(illegal()) };
+        }
+
+        // Piece letter, what is given of the starting square, the target square and the promotion
+        let mut rest = text.replace(['x', '-', '='], "");
+        let piece_type = match rest.chars().next() {
+            Some(symbol) if symbol.is_ascii_uppercase() => {
+                rest.remove(0);
+                PieceType::from_symbol(symbol).unwrap_or(PieceType::Fairy(symbol))
+            }
+            _ => Pawn,
+        };
+        let promotion = match rest.chars().last() {
+            Some(symbol) if symbol.is_ascii_uppercase() => {
+                rest.pop();
+                let piece_type = PieceType::from_symbol(symbol).filter(|piece_type| *piece_type != King);
+                Some(piece_type.ok_or_else(|| ChessError::invalid_notation(san, "Not a valid piece"))?)
+            }
+            _ => None,
+        };
+        let target_at = rest
+            .rfind(|c: char| c.is_ascii_lowercase())
+            .ok_or_else(|| ChessError::invalid_notation(san, "Expected a square"))?;
+        let (hint, target) = rest.split_at(target_at);
+        let square = |algebraic: &str| {
+            board.algebraic_to_square(algebraic).map_err(|_| ChessError::invalid_notation(san, "Not a square on the board"))
+        };
+        let to = square(target)?;
+        let (file_hint, rank_hint) = hint.split_at(hint.find(|c: char| c.is_ascii_digit()).unwrap_or(hint.len()));
+
+        let mut candidates = legal_moves.into_iter().filter(|mv| match *mv {
+            Move::Normal { from, to: other_to, promotion: other_promotion } => {
+                let algebraic = board.square_to_algebraic(from);
+                let (file, rank) = algebraic.split_at(1);
+                other_to == to
+                    && other_promotion == promotion
+                    && board.get_piece_at(from).is_some_and(|piece| piece.piece_type == piece_type)
+                    && (file_hint.is_empty() || file == file_hint)
+                    && (rank_hint.is_empty() || rank == rank_hint)
+            }
+            Move::Drop { .. } => false,
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(mv), None) => Ok(mv),
+            (Some(_), Some(_)) => Err(ChessError::invalid_notation(san, "Ambiguous, more than one piece can move there")),
+            (None, _) => Err(illegal()),
+        }
+    }
+
+    /// Standard algebraic notation as used in PGN: "Nbd7", "exd5", "e8=Q+", "O-O" and "N@f3" for
+    /// drops, with "+" for check and "#" for mate. The move has to be legal in `position`.
+    pub fn to_san(&self, variant: &dyn Variant, position: &Position) -> String {
+        let board = &position.board;
+        let mut san = match *self {
+            Move::Drop { piece_type, to } => format!("{}@{}", piece_type.get_symbol(), board.square_to_algebraic(to)),
+            Move::Normal { from, to, promotion } => match board.get_piece_at(from) {
+                Some(piece) if is_castling(piece, from, to) => {
+                    (if to.x > from.x { "O-O" } else { "O-O-O" }).to_string()
                 }
-                if forward_once.is_none() {
-                    possible_moves.push(Square { y: row - 1, x: col });
+                Some(piece) => {
+                    let file = |square: Square| board.square_to_algebraic(square)[..1].to_string();
+                    let capture = if is_capture(position, *self) { "x" } else { "" };
+                    let promotion =
+                        promotion.map(|piece_type| format!("={}", piece_type.get_symbol())).unwrap_or_default();
+                    let prefix = if piece.piece_type == Pawn {
+                        if capture.is_empty() { String::new() } else { file(from) }
+                    } else {
+                        // Name the file, the rank or both when another such piece could go there too
+                        let others: Vec<Square> = variant
+                            .legal_moves(position)
+                            .into_iter()
+                            .filter_map(|mv| match mv {
+                                Move::Normal { from: other, to: other_to, .. } if other != from && other_to == to => Some(other),
+                                _ => None,
+                            })
+                            .filter(|other| board.get_piece_at(*other).map(|other| other.piece_type) == Some(piece.piece_type))
+                            .collect();
+                        let algebraic = board.square_to_algebraic(from);
+                        let disambiguation = if others.is_empty() {
+                            String::new()
+                        } else if others.iter().all(|other| other.x != from.x) {
+                            file(from)
+                        } else if others.iter().all(|other| other.y != from.y) {
+                            algebraic[1..].to_string()
+                        } else {
+                            algebraic
+                        };
+                        format!("{}{}", piece.piece_type.get_symbol(), disambiguation)
+                    };
+                    format!("{}{}{}{}", prefix, capture, board.square_to_algebraic(to), promotion)
                 }
+                None => self.to_uci(board),
+            },
+        };
+        let mut next = position.clone();
+        if variant.apply_move(&mut next, *self).is_ok() && is_in_check(&next.board, next.side_to_move) {
+            san.push(if variant.legal_moves(&next).is_empty() { '#' } else { '+' });
+        }
+        san
+    }
+}
 
-                if forward_twice.is_none() && piece.location.y == 6 {
-                    possible_moves.push(Square { y: row - 2, x: col });
-                }
+pub fn pawn_movements(board: &Chessboard, piece: Piece) -> Vec<Square>{
+    let mut possible_moves: Vec<Square> = vec![];
+    let (direction, start_row, enemy) = match piece.color {
+        Black => (1, 1, White),
+        White => (-1, board.height() - 2, Black),
+    };
+
+    let forward = match board.offset(piece.location, 0, direction) {
+        Some(square) => square,
+        None => return possible_moves,
+    };
+    for side in [-1, 1] {
+        if let Some(capture) = board.offset(forward, side, 0).and_then(|square| board.get_piece_at(square)) {
+            if capture.color == enemy {
+                possible_moves.push(capture.location);
             }
         }
     }
-    possible_moves
-}
+    if board.get_piece_at(forward).is_none() {
+        possible_moves.push(forward);
 
-pub fn rook_movements(board: Chessboard, piece: Piece) -> Vec<Square> {
-    let mut possible_moves: Vec<Square>= vec![];
-    for (r, c) in &[(1,0), (-1, 0), (0, 1), (0, -1)] {
-        let mut row = piece.location.y as i8 + r;
-        let mut col = piece.location.x as i8 + c;
-
-        while (0..8).contains(&(row as usize)) && (0..8).contains(&(col as usize)) {
-            let location = board.board[row as usize][col as usize];
-            if location.is_none() {
-                possible_moves.push(Square { y: row as usize, x: col as usize });
-            } else {
-                if let Some(obstacle_piece) = location {
-                    if obstacle_piece.color != piece.color {
-                        possible_moves.push(obstacle_piece.location);
-                    }
-                }
-                break; 
+        // The double step may not cross the middle of the board, which rules it out on small boards
+        if let Some(twice) = board.offset(forward, 0, direction) {
+            let stays_in_own_half = match piece.color {
+                Black => twice.y < board.height() / 2,
+                White => twice.y >= board.height().div_ceil(2),
+            };
+            if piece.location.y == start_row && stays_in_own_half && board.get_piece_at(twice).is_none() {
+                possible_moves.push(twice);
             }
-            row += r;
-            col += c;
         }
     }
     possible_moves
 }
 
-pub fn bishop_movements(board: Chessboard, piece: Piece) -> Vec<Square> {
+/// Moves along the given lines up to the first piece, which is included if it can be captured
+fn sliding_movements(board: &Chessboard, piece: Piece, directions: &[(i8, i8)]) -> Vec<Square> {
     let mut possible_moves: Vec<Square> = vec![];
-    for (r, c) in &[(1, -1), (1, 1), (-1, 1), (-1, -1)] {
-        let mut row = piece.location.y as i8 + r;
-        let mut col = piece.location.x as i8 + c;
-
-        while (0..8).contains(&(row as usize)) && (0..8).contains(&(col as usize)) {
-            let location = board.board[row as usize][col as usize];
-            if location.is_none(){
-                possible_moves.push(Square { y: row as usize, x: col as usize});
-            } else if let Some(obstacle_piece) = location {
-                if obstacle_piece.color != piece.color {
-                    possible_moves.push(obstacle_piece.location);
+    for (dx, dy) in directions {
+        for square in board.ray(piece.location, *dx, *dy) {
+            match board.get_piece_at(square) {
+                None => possible_moves.push(square),
+                Some(obstacle_piece) => {
+                    if obstacle_piece.color != piece.color {
+                        possible_moves.push(square);
+                    }
+                    break;
                 }
             }
-            row += r;
-            col += c;
         }
     }
     possible_moves
 }
 
-pub fn queen_movements(board: Chessboard, piece: Piece) -> Vec<Square> {
+/// Single steps to each of the given offsets that are empty or hold an enemy piece
+fn leaping_movements(board: &Chessboard, piece: Piece, offsets: &[(i8, i8)]) -> Vec<Square> {
+    offsets
+        .iter()
+        .filter_map(|(dx, dy)| board.offset(piece.location, *dx, *dy))
+        .filter(|square| board.get_piece_at(*square).is_none_or(|obstacle_piece| obstacle_piece.color != piece.color))
+        .collect()
+}
+
+pub fn rook_movements(board: &Chessboard, piece: Piece) -> Vec<Square> {
+    sliding_movements(board, piece, &[(0, 1), (0, -1), (1, 0), (-1, 0)])
+}
+
+pub fn bishop_movements(board: &Chessboard, piece: Piece) -> Vec<Square> {
+    sliding_movements(board, piece, &[(-1, 1), (1, 1), (1, -1), (-1, -1)])
+}
+
+pub fn queen_movements(board: &Chessboard, piece: Piece) -> Vec<Square> {
     let mut possible_moves = rook_movements(board, piece);
     possible_moves.append(&mut bishop_movements(board, piece));
     possible_moves
 }
 
-pub fn knight_movements(board: Chessboard, piece: Piece) -> Vec<Square> {
+pub fn knight_movements(board: &Chessboard, piece: Piece) -> Vec<Square> {
     //All possible movement combinations for the knight
     let knight_moves: [(i8, i8); 8] = [
-        (1, 2),
-        (1, -2),
         (2, 1),
-        (2, -1),
+        (-2, 1),
+        (1, 2),
         (-1, 2),
-        (-1, -2),
+        (2, -1),
         (-2, -1),
-        (-2, 1),
+        (-1, -2),
+        (1, -2),
     ];
-    let mut possible_moves: Vec<Square> = vec![];
-    
-    for(r, c) in knight_moves{
-        let row = piece.location.y as i8 + r;
-        let col = piece.location.x as i8 + c;
-        
-        // If neither row nor col is out of bonds
-        if(0..8).contains(&(row as usize)) && (0..8).contains(&(col as usize)) {
-            let location = board.board[row as usize][col as usize];
-            if location.is_none() {
-                possible_moves.push(Square{ y: row as usize, x: col as usize});
-            }
-            if let Some(obstacle_piece) = location {
-                if obstacle_piece.color != piece.color {
-                    possible_moves.push(obstacle_piece.location);
-                }
-            }
+    leaping_movements(board, piece, &knight_moves)
+}
+
+pub fn king_movements(board: &Chessboard, piece: Piece) -> Vec<Square> {
+    let king_moves: [(i8, i8); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+    leaping_movements(board, piece, &king_moves)
+}
+
+/// Movement of a user-defined piece, as described by the board's piece registry
+pub fn fairy_movements(board: &Chessboard, piece: Piece) -> Vec<Square> {
+    match piece.piece_type {
+        PieceType::Fairy(symbol) => match board.fairy_pieces().get(symbol) {
+            Some(fairy) => fairy.movement.movements(board, piece),
+            None => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// The last rank as seen from `color`, where pawns promote
+pub fn promotion_rank(board: &Chessboard, color: Color) -> usize {
+    match color {
+        White => 0,
+        Black => board.height() - 1,
+    }
+}
+
+/// Home rank of `color`, where its king and rooks start
+pub fn home_rank(board: &Chessboard, color: Color) -> usize {
+    match color {
+        White => board.height() - 1,
+        Black => 0,
+    }
+}
+
+pub fn en_passant_moves(position: &Position) -> Vec<Move> {
+    let mut possible_moves: Vec<Move> = vec![];
+    let target = match position.en_passant {
+        Some(square) => square,
+        None => return possible_moves,
+    };
+    let board = &position.board;
+    let direction: i8 = match position.side_to_move {
+        White => -1,
+        Black => 1,
+    };
+    for pawn in board.pieces(position.side_to_move) {
+        let is_pawn = pawn.piece_type == Pawn;
+        let steps_onto_target = board.offset(pawn.location, 0, direction).is_some_and(|ahead| ahead.y == target.y)
+            && pawn.location.x.abs_diff(target.x) == 1;
+        if is_pawn && steps_onto_target && board.get_piece_at(target).is_none() {
+            possible_moves.push(Move::new(pawn.location, target));
         }
     }
     possible_moves
 }
 
-pub fn king_movements(board: Chessboard, piece: Piece) -> Vec<Square> {
-    let mut possible_moves: Vec<Square> = vec![];
-    let row = piece.location.y;
-    let col = piece.location.x;
-    for i in 0..3 {
-        let row = row + i - 1;
-        for j in 0..3 {
-            let col = col + j - 1;
-            if(0..8).contains(&{ row }) && (0..8).contains(&{ col }) {
-                let location = board.board[row][col];
-                if location.is_none(){
-                    possible_moves.push(Square { y: row, x: col})
-                }
-                if let Some(obstacle_piece) = location {
-                    if obstacle_piece.color != piece.color{
-                        possible_moves.push(obstacle_piece.location)
-                    }
-                }
-            } 
+/// File the king starts on and castles from: the e-file on 8x8, the middle file on other widths
+pub fn king_start_file(board: &Chessboard) -> usize {
+    board.width() / 2
+}
+
+/// The king moves two squares towards the rook, on boards wider than 8 it ends up on the
+/// second file from the corner, the rook jumping over it.
+pub fn castling_moves(position: &Position) -> Vec<Move> {
+    let mut possible_moves: Vec<Move> = vec![];
+    let color = position.side_to_move;
+    let board = &position.board;
+    let rank = home_rank(board, color);
+    let king = match board.find_king(color) {
+        Some(square) if square == (Square { x: king_start_file(board), y: rank }) => square,
+        _ => return possible_moves,
+    };
+    if board.width() < 5 || is_in_check(board, color) {
+        return possible_moves;
+    }
+
+    let sides = [
+        (position.castling.king_side(color), board.width() - 1, board.width() - 2),
+        (position.castling.queen_side(color), 0, 2),
+    ];
+    for (allowed, rook_x, king_to_x) in sides {
+        let rook_square = Square { x: rook_x, y: rank };
+        let has_rook = board.get_piece_at(rook_square).is_some_and(|rook| rook.piece_type == Rook && rook.color == color);
+        if !allowed || !has_rook {
+            continue;
+        }
+        let rook_to_x = if king_to_x > king.x { king_to_x - 1 } else { king_to_x + 1 };
+        let lowest = king.x.min(king_to_x).min(rook_x).min(rook_to_x);
+        let highest = king.x.max(king_to_x).max(rook_x).max(rook_to_x);
+        let path_clear = (lowest..=highest)
+            .filter(|x| *x != king.x && *x != rook_x)
+            .all(|x| board.get_piece_at(Square { x, y: rank }).is_none());
+        let king_path_safe = (king.x.min(king_to_x)..=king.x.max(king_to_x))
+            .all(|x| !is_square_attacked(board, Square { x, y: rank }, color.opposite()));
+        if path_clear && king_path_safe {
+            possible_moves.push(Move::new(king, Square { x: king_to_x, y: rank }));
         }
     }
     possible_moves
 }
 
+/// True if a king move on `from`'s rank spans more than one file, which only castling does
+pub fn is_castling(piece: Piece, from: Square, to: Square) -> bool {
+    piece.piece_type == King && from.y == to.y && from.x.abs_diff(to.x) > 1
+}
+
+/// Whether the move takes something, en passant included.
+pub fn is_capture(position: &Position, mv: Move) -> bool {
+    match mv {
+        Move::Normal { from, to, .. } => {
+            let pawn_moving = position.board.get_piece_at(from).is_some_and(|piece| piece.piece_type == Pawn);
+            position.board.get_piece_at(to).is_some() || (pawn_moving && from.x != to.x && Some(to) == position.en_passant)
+        }
+        Move::Drop { .. } => false,
+    }
+}
+
+/// True if any piece of the opposite color attacks the king of `color`.
+pub fn is_in_check(board: &Chessboard, color: Color) -> bool {
+    match board.find_king(color) {
+        Some(king) => is_square_attacked(board, king, color.opposite()),
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::board::{Chessboard, Square};
-    use crate::chess_piece::PieceMovement;
+    use crate::chess_piece::{PieceMovement, PieceType};
+    use crate::error::ChessError;
+    use crate::moves::Move;
+    use crate::position::Position;
+    use crate::variant::{Crazyhouse, Standard, Variant};
     
     #[test]
     fn moving_pawn_within_bounds(){
@@ -310,17 +576,133 @@ mod tests {
         test_moves("8/8/8/8/8/8/8/7K", "h1", vec!["g1", "g2", "h2"])
     }
 
+    #[test]
+    fn moving_rook_on_wide_board(){
+        let expected_moves: Vec<&str> = vec![
+            "a4", "b4", "c4", "d4", "f4", "g4", "h4", "i4", "j4", "e1", "e2", "e3", "e5", "e6", "e7", "e8"
+        ];
+        test_moves("10/10/10/10/4R5/10/10/10", "e4", expected_moves);
+    }
+
+    #[test]
+    fn moving_rook_stays_within_small_board(){
+        let board = Chessboard::from_fen("5/5/5/5/R4").unwrap();
+        let rook = board.get_piece_at(board.algebraic_to_square("a1").unwrap()).unwrap();
+        assert_eq!(rook.can_move(&board).len(), 8);
+    }
+
+    #[test]
+    fn moving_knight_within_bounds_on_small_board(){
+        test_moves("6/6/6/6/6/5N", "f1", vec!["e3", "d2"]);
+    }
+
+    #[test]
+    fn moving_pawn_double_step_on_wide_board(){
+        test_moves("10/10/10/10/10/10/4P5/10", "e2", vec!["e3", "e4"]);
+    }
+
+    #[test]
+    fn moving_pawn_no_double_step_on_small_board(){
+        let board = Chessboard::from_fen("6/6/6/6/3P2/6").unwrap();
+        let pawn = board.get_piece_at(board.algebraic_to_square("d2").unwrap()).unwrap();
+        assert_eq!(pawn.can_move(&board), vec![board.algebraic_to_square("d3").unwrap()]);
+    }
+
+    #[test]
+    fn uci_notation() {
+        let board = Chessboard::new();
+        let e2 = board.algebraic_to_square("e2").unwrap();
+        let e4 = board.algebraic_to_square("e4").unwrap();
+        assert_eq!(Move::from_uci(&board, "e2e4"), Ok(Move::new(e2, e4)));
+        assert_eq!(Move::new(e2, e4).to_uci(&board), "e2e4");
+
+        let promotion = Move::from_uci(&board, "a7a8n").unwrap();
+        assert_eq!(promotion.to_uci(&board), "a7a8n");
+        let drop = Move::from_uci(&board, "N@f3").unwrap();
+        assert_eq!(drop, Move::Drop { piece_type: PieceType::Knight, to: board.algebraic_to_square("f3").unwrap() });
+        assert_eq!(drop.to_uci(&board), "N@f3");
+
+        let big = Chessboard::with_size(10, 10).unwrap();
+        assert_eq!(Move::from_uci(&big, "a10j1").unwrap().to_uci(&big), "a10j1");
+    }
+
+    #[test]
+    fn invalid_uci_notation() {
+        let board = Chessboard::new();
+        assert_eq!(Move::from_uci(&board, "e2"), Err(ChessError::invalid_notation("e2", "Expected two squares")));
+        assert_eq!(Move::from_uci(&board, "e2e9"), Err(ChessError::invalid_notation("e2e9", "Not a square on the board")));
+        assert_eq!(Move::from_uci(&board, "e7e8k"), Err(ChessError::invalid_notation("e7e8k", "Not a valid piece")));
+        assert_eq!(Move::from_uci(&board, "X@e4"), Err(ChessError::invalid_notation("X@e4", "Not a valid piece")));
+    }
+
+    #[test]
+    fn standard_algebraic_notation() {
+        let san = |fen: &str, uci: &str| {
+            let position = Position::from_fen(fen).unwrap();
+            let mv = Move::from_uci(&position.board, uci).unwrap();
+            let drops = position.pockets.iter().any(|pocket| !pocket.is_empty());
+            let variant: &dyn Variant = if drops { &Crazyhouse } else { &Standard };
+            mv.to_san(variant, &position)
+        };
+        assert_eq!(san("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "g1f3"), "Nf3");
+        assert_eq!(san("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "e4d5"), "exd5");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "e5d6"), "exd6");
+        assert_eq!(san("r3k3/8/8/8/8/8/8/R3K2R w KQq - 0 1", "e1g1"), "O-O");
+        assert_eq!(san("r3k3/8/8/8/8/8/8/R3K2R b KQq - 0 1", "e8c8"), "O-O-O");
+        assert_eq!(san("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), "a8=Q+");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"), "Ra8#");
+        assert_eq!(san("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1", "N@f6"), "N@f6+");
+        // Another knight reaches d2 from the same rank, then the same file, then both
+        assert_eq!(san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(san("4k3/8/8/8/8/5N2/8/4KN2 w - - 0 1", "f1d2"), "N1d2");
+        assert_eq!(san("4k3/8/8/8/8/1N6/8/1N2KN2 w - - 0 1", "b1d2"), "Nb1d2");
+        // A pinned knight does not count
+        assert_eq!(san("4k3/4r3/8/8/8/8/4N3/1N2K3 w - - 0 1", "b1c3"), "Nc3");
+    }
+
+    #[test]
+    fn reading_algebraic_notation() {
+        let read = |fen: &str, san: &str| {
+            let position = Position::from_fen(fen).unwrap();
+            Move::from_san(&Crazyhouse, &position, san).map(|mv| mv.to_uci(&position.board))
+        };
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(read(start, "Nf3"), Ok("g1f3".to_string()));
+        assert_eq!(read(start, "e4!?"), Ok("e2e4".to_string()));
+        assert_eq!(read(start, "Ng1-f3"), Ok("g1f3".to_string()));
+        assert_eq!(read("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2", "exd6"), Ok("e5d6".to_string()));
+        assert_eq!(read("r3k3/8/8/8/8/8/8/R3K2R w KQq - 0 1", "0-0"), Ok("e1g1".to_string()));
+        assert_eq!(read("r3k3/8/8/8/8/8/8/R3K2R b KQq - 0 1", "O-O-O+"), Ok("e8c8".to_string()));
+        assert_eq!(read("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8=N"), Ok("a7a8n".to_string()));
+        assert_eq!(read("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8Q+"), Ok("a7a8q".to_string()));
+        assert_eq!(read("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1", "N@f6"), Ok("N@f6".to_string()));
+        assert_eq!(read("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "Nbd2"), Ok("b1d2".to_string()));
+        assert_eq!(read("4k3/8/8/8/8/1N6/8/1N2KN2 w - - 0 1", "Nb1d2"), Ok("b1d2".to_string()));
+
+        assert!(read("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "Nd2").is_err());
+        assert!(read(start, "e5").is_err());
+        assert!(read(start, "O-O").is_err());
+        assert!(read(start, "Nf9").is_err());
+        assert!(read(start, "").is_err());
+        assert!(read("4k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a8=K").is_err());
+
+        // Zeros are only letters in castling, "a10" is a square
+        let tall = "9k/10/10/10/10/10/10/10/10/R8K w - - 0 1";
+        assert_eq!(read(tall, "Ra10+"), Ok("a1a10".to_string()));
+        assert_eq!(read(tall, "R1a10"), Ok("a1a10".to_string()));
+    }
+
     fn test_moves(fen_state: &str, piece_pos_alg: &str, possible_moves: Vec<&str>){
         let board = Chessboard::from_fen(fen_state).unwrap();
-        let test_piece_location = Square::algebraic_to_coords(piece_pos_alg).unwrap();
+        let test_piece_location = board.algebraic_to_square(piece_pos_alg).unwrap();
         let test_piece = board.get_piece_at(test_piece_location).unwrap();
 
         let mut moves_to_test: Vec<Square> = vec![];
         for square in possible_moves {
-            moves_to_test.push(Square::algebraic_to_coords(square).unwrap())
+            moves_to_test.push(board.algebraic_to_square(square).unwrap())
         }
 
-        let possible_moves = test_piece.can_move(board);
+        let possible_moves = test_piece.can_move(&board);
         assert!(moves_to_test.iter().all(|moves| possible_moves.contains(moves)));
     }
 }
@@ -0,0 +1,433 @@
+use std::sync::Arc;
+
+use crate::chess_piece::Color;
+use crate::error::ChessError;
+use crate::fairy::PieceRegistry;
+use crate::game::{Game, NodeId};
+use crate::moves::Move;
+use crate::position::{Position, STANDARD_START_FEN};
+use crate::variant::{variant_from_name, Standard, Variant};
+
+/// Longest line written in the move text
+const LINE_WIDTH: usize = 80;
+/// Move assessments written right after the move, other annotations become "$n" glyphs
+const GLYPHS: [(&str, &str); 6] =
+    [("$1", "!"), ("$2", "?"), ("$3", "!!"), ("$4", "??"), ("$5", "!?"), ("$6", "?!")];
+
+/// A game in Portable Game Notation: the tag pairs and the moves with their comments,
+/// annotations and variations, kept in a `Game`. The result is the "Result" tag.
+pub struct Pgn {
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+}
+
+impl Pgn {
+    /// The game with the seven tags PGN requires, unknown values as "?".
+    pub fn new(game: Game) -> Pgn {
+        let tags = [
+            ("Event", "?"),
+            ("Site", "?"),
+            ("Date", "????.??.??"),
+            ("Round", "?"),
+            ("White", "?"),
+            ("Black", "?"),
+            ("Result", "*"),
+        ];
+        let tags = tags.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        Pgn { tags, game }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Changes the tag, or adds it at the end when the game does not have it yet.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// The game as PGN text, main line first with the variations in parentheses.
+    pub fn to_pgn(&self) -> String {
+        let mut text = String::new();
+        let root = self.game.root();
+        let start = self.game.position_at(root).expect("the root always exists");
+        let mut tags = self.tags.clone();
+        if start.to_fen() != STANDARD_START_FEN && self.tag("FEN").is_none() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start.to_fen()));
+        }
+        if self.game.variant().name() != Standard.name() && self.tag("Variant").is_none() {
+            tags.push(("Variant".to_string(), self.game.variant().name().to_string()));
+        }
+        for (name, value) in &tags {
+            text.push_str(&format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")));
+        }
+        text.push('\n');
+
+        let mut tokens = vec![];
+        if let Some(comment) = self.game.comment(root) {
+            tokens.push(comment_token(comment));
+        }
+        write_line(&self.game, root, &mut tokens, true);
+        tokens.push(self.tag("Result").unwrap_or("*").to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            let glued = line.is_empty() || line.ends_with('(') || token == ")";
+            if !glued && line.len() + 1 + token.len() > LINE_WIDTH {
+                text.push_str(&line);
+                text.push('\n');
+                line.clear();
+            } else if !glued {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        text.push_str(&line);
+        text.push('\n');
+        text
+    }
+}
+
+/// The moves after `parent`, each main move followed by the variations that replace it.
+fn write_line(game: &Game, mut parent: NodeId, tokens: &mut Vec<String>, mut needs_number: bool) {
+    while let Some(&main) = game.variations(parent).first() {
+        needs_number = write_move(game, parent, main, tokens, needs_number);
+        for &variation in &game.variations(parent)[1..] {
+            tokens.push("(".to_string());
+            let after_comment = write_move(game, parent, variation, tokens, true);
+            write_line(game, variation, tokens, after_comment);
+            tokens.push(")".to_string());
+            needs_number = true;
+        }
+        parent = main;
+    }
+}
+
+/// Writes the move that leads from `parent` to `node`, with its number when white moves or
+/// `needs_number` is set. Returns whether the next move needs its number.
+fn write_move(game: &Game, parent: NodeId, node: NodeId, tokens: &mut Vec<String>, needs_number: bool) -> bool {
+    let position = game.position_at(parent).expect("the node's parent exists");
+    let mv = game.move_at(node).expect("only the root has no move");
+    let number = match position.side_to_move {
+        Color::White => format!("{}. ", position.fullmove_number),
+        Color::Black if needs_number => format!("{}... ", position.fullmove_number),
+        Color::Black => String::new(),
+    };
+    let mut token = format!("{}{}", number, mv.to_san(game.variant(), position));
+    match game.annotation(node) {
+        Some(annotation) if annotation.starts_with('$') => {
+            tokens.push(token);
+            token = annotation.to_string();
+        }
+        Some(annotation) => token.push_str(annotation),
+        None => {}
+    }
+    tokens.push(token);
+    match game.comment(node) {
+        Some(comment) => {
+            tokens.push(comment_token(comment));
+            true
+        }
+        None => false,
+    }
+}
+
+fn comment_token(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Open,
+    Close,
+    Glyph(String),
+    Move(String),
+    Result(String),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ChessError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+        match c {
+            c if c.is_whitespace() => {}
+            // Escaped lines are for other programs
+            '%' if at_line_start => {
+                chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+                line_start = true;
+            }
+            ';' => {
+                let comment: String = chars.by_ref().take_while(|c| *c != '\n').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+                line_start = true;
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(ChessError::invalid_notation(comment, "Comment is not closed")),
+                    }
+                }
+                tokens.push(Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" ")));
+            }
+            '[' => {
+                let mut tag = String::new();
+                let mut quoted = false;
+                loop {
+                    match chars.next() {
+                        Some('\\') if quoted => tag.extend(chars.next()),
+                        Some('"') => {
+                            quoted = !quoted;
+                            tag.push('"');
+                        }
+                        Some(']') if !quoted => break,
+                        Some(c) => tag.push(c),
+                        None => return Err(ChessError::invalid_notation(tag, "Tag is not closed")),
+                    }
+                }
+                let (name, value) = tag
+                    .trim()
+                    .split_once(char::is_whitespace)
+                    .map(|(name, value)| (name, value.trim()))
+                    .filter(|(_, value)| value.len() >= 2 && value.starts_with('"') && value.ends_with('"'))
+                    .ok_or_else(|| ChessError::invalid_notation(tag.clone(), "Expected a tag name and a quoted value"))?;
+                tokens.push(Token::Tag(name.to_string(), value[1..value.len() - 1].to_string()));
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "(){}[];".contains(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.extend(word_tokens(&word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// A result, a glyph, or a move with its number and assessment, like "12.Nf3!?"
+fn word_tokens(word: &str) -> Vec<Token> {
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&word) {
+        return vec![Token::Result(word.to_string())];
+    }
+    if word.starts_with('$') {
+        return vec![Token::Glyph(word.to_string())];
+    }
+    let san = strip_move_number(word);
+    if san.is_empty() {
+        return vec![];
+    }
+    let assessment_at = san.find(['!', '?']).unwrap_or(san.len());
+    let (san, assessment) = san.split_at(assessment_at);
+    let mut tokens = vec![Token::Move(san.to_string())];
+    if !assessment.is_empty() {
+        tokens.push(Token::Glyph(assessment.to_string()));
+    }
+    tokens
+}
+
+/// The move without the number in front of it, "Nf3" for "12.Nf3" and "0-0" for "4...0-0".
+/// Words that are only a number, like "12.", come back empty.
+pub fn strip_move_number(word: &str) -> &str {
+    let rest = word.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        word
+    }
+}
+
+/// Reads all games in the text. Moves are in standard algebraic notation from the "FEN" tag or
+/// the standard starting position, under the rules named by the "Variant" tag. Each game ends
+/// at the last move of its main line, and "$1" to "$6" are read as "!", "?", "!!", "??", "!?"
+/// and "?!".
+pub fn parse_pgn(text: &str) -> Result<Vec<Pgn>, ChessError> {
+    let mut games = vec![];
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+    while tokens.peek().is_some() {
+        let mut tags = vec![];
+        while let Some(Token::Tag(name, value)) = tokens.peek().cloned() {
+            tags.push((name, value));
+            tokens.next();
+        }
+        let tag = |name: &str| tags.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        let variant = match tag("Variant") {
+            Some(name) => variant_from_name(name)
+                .or_else(|| name.eq_ignore_ascii_case("chess").then(|| Box::new(Standard) as _))
+                .ok_or_else(|| ChessError::invalid_notation(name, "Unknown variant"))?,
+            None => Box::new(Standard),
+        };
+        let fen = tag("FEN").unwrap_or(STANDARD_START_FEN);
+        let position = Position::from_fen_with_registry(fen, Arc::new(PieceRegistry::with_common_fairies()))?;
+        let mut game = Game::from_position(position, variant);
+        let mut variations: Vec<NodeId> = vec![];
+        let mut result = None;
+
+        while let Some(token) = tokens.next_if(|token| !matches!(token, Token::Tag(..))) {
+            let current = game.current_node();
+            match token {
+                Token::Move(san) => {
+                    let mv = Move::from_san(game.variant(), game.position(), &san)?;
+                    game.play(mv)?;
+                }
+                Token::Glyph(glyph) if current != game.root() => {
+                    let glyph = GLYPHS.iter().find(|(nag, _)| *nag == glyph).map_or(glyph.as_str(), |(_, glyph)| glyph);
+                    game.set_annotation(current, glyph)?;
+                }
+                Token::Glyph(_) => {}
+                Token::Comment(comment) => {
+                    let comment = match game.comment(current) {
+                        Some(earlier) => format!("{} {}", earlier, comment),
+                        None => comment,
+                    };
+                    game.set_comment(current, &comment)?;
+                }
+                // A variation replaces the move before it
+                Token::Open => {
+                    variations.push(current);
+                    if game.undo().is_none() {
+                        return Err(ChessError::invalid_notation("(", "A variation needs a move to replace"));
+                    }
+                }
+                Token::Close => {
+                    let end = variations.pop().ok_or_else(|| ChessError::invalid_notation(")", "No variation to close"))?;
+                    game.go_to(end)?;
+                }
+                Token::Tag(..) => unreachable!("tags end the game"),
+                Token::Result(text) => {
+                    result = Some(text);
+                    break;
+                }
+            }
+        }
+        if !variations.is_empty() {
+            return Err(ChessError::invalid_notation("(", "Variation is not closed"));
+        }
+
+        let mut end = game.root();
+        while let Some(&main) = game.variations(end).first() {
+            end = main;
+        }
+        game.go_to(end)?;
+        let mut pgn = Pgn { tags, game };
+        if let Some(result) = result {
+            pgn.set_tag("Result", &result);
+        }
+        games.push(pgn);
+    }
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::Game;
+    use crate::pgn::{parse_pgn, strip_move_number, Pgn};
+
+    const GAME: &str = r#"[Event "Casual game"]
+[Site "?"]
+[White "Anderssen, Adolf"]
+[Black "Kieseritzky, Lionel"]
+[Result "1-0"]
+
+{Opening comment} 1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5?! {Bryan's countergambit} (4... Nf6 5. Nf3
+(5. Nc3) 5... Qh6) 5. Bxb5 $2 ; rest of line comment
+Nf6 6. Nf3 Qh6 7. d3 Nh5 8. Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6
+13. h5 Qg5 14. Qf3 Ng8 15. Bxf4 Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 19. e5
+Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8 22. Qf6+ Nxf6 23. Be7# 1-0
+"#;
+
+    #[test]
+    fn reading_a_game() {
+        let games = parse_pgn(GAME).unwrap();
+        assert_eq!(games.len(), 1);
+        let pgn = &games[0];
+        assert_eq!(pgn.tag("White"), Some("Anderssen, Adolf"));
+        assert_eq!(pgn.tag("Result"), Some("1-0"));
+        let game = &pgn.game;
+        assert_eq!(game.mainline().len(), 45);
+        assert_eq!(game.ply(), 45);
+        assert_eq!(game.comment(game.root()), Some("Opening comment"));
+
+        let mut kf1 = game.root();
+        for _ in 0..7 {
+            kf1 = game.variations(kf1)[0];
+        }
+        let replies = game.variations(kf1);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(game.annotation(replies[0]), Some("?!"));
+        assert_eq!(game.comment(replies[0]), Some("Bryan's countergambit"));
+        let bxb5 = game.variations(replies[0])[0];
+        assert_eq!(game.annotation(bxb5), Some("?"));
+        assert_eq!(game.comment(bxb5), Some("rest of line comment"));
+    }
+
+    #[test]
+    fn writing_reads_back_the_same() {
+        let games = parse_pgn(GAME).unwrap();
+        let text = games[0].to_pgn();
+        assert!(text.starts_with("[Event \"Casual game\"]\n"));
+        let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(flat.contains("{Opening comment} 1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5?! {Bryan's countergambit}"));
+        assert!(flat.contains("(4... Nf6 5. Nf3 (5. Nc3) 5... Qh6) 5. Bxb5? {rest of line comment} 5... Nf6"));
+        assert!(text.trim_end().ends_with("23. Be7# 1-0"));
+        assert!(text.lines().all(|line| line.len() <= 80));
+        let again = parse_pgn(&text).unwrap();
+        assert_eq!(again[0].to_pgn(), text);
+    }
+
+    #[test]
+    fn several_games_and_setups() {
+        let text = "[Event \"First\"]\n\n1. e4 *\n\n\
+                    [Event \"Second\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n\n1... Kd7 2. Ra7+ 1/2-1/2\n";
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].game.mainline().len(), 1);
+        assert_eq!(games[1].tag("Result"), Some("1/2-1/2"));
+        assert_eq!(games[1].game.position().to_fen(), "8/R2k4/8/8/8/8/8/4K3 b - - 2 2");
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        game.play(crate::moves::Move::from_san(game.variant(), game.position(), "Kd7").unwrap()).unwrap();
+        let text = Pgn::new(game).to_pgn();
+        assert!(text.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]"));
+        assert!(text.contains("\n1... Kd7 *"));
+    }
+
+    #[test]
+    fn castling_written_with_zeros() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5.d3 0-0 *").unwrap();
+        let position = games[0].game.position();
+        assert_eq!(position.to_fen(), "r1bq1rk1/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQ1RK1 w - - 1 6");
+        assert_eq!(strip_move_number("12...0-0-0"), "0-0-0");
+        assert_eq!(strip_move_number("12."), "");
+        assert_eq!(strip_move_number("0-0"), "0-0");
+    }
+
+    #[test]
+    fn invalid_pgn() {
+        assert!(parse_pgn("1. e4 e4").is_err());
+        assert!(parse_pgn("1. e4 {unclosed").is_err());
+        assert!(parse_pgn("[Event \"x]\n1. e4").is_err());
+        assert!(parse_pgn("[Event x]\n1. e4").is_err());
+        assert!(parse_pgn("(1. e4)").is_err());
+        assert!(parse_pgn("1. e4 (1. d4").is_err());
+        assert!(parse_pgn("1. e4 )").is_err());
+        assert!(parse_pgn("[Variant \"Losers\"]\n1. e4").is_err());
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::{Color, PieceType};
+use crate::error::ChessError;
+use crate::fairy::PieceRegistry;
+
+pub const STANDARD_START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights { white_king_side: true, white_queen_side: true, black_king_side: true, black_queen_side: true }
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights::default()
+    }
+
+    pub fn king_side(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_king_side,
+            Color::Black => self.black_king_side,
+        }
+    }
+
+    pub fn queen_side(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_queen_side,
+            Color::Black => self.black_queen_side,
+        }
+    }
+
+    pub fn remove_all(&mut self, color: Color) {
+        match color {
+            Color::White => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            Color::Black => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+        }
+    }
+}
+
+/// A board together with the state the rules need to continue the game from it.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub(crate) board: Chessboard,
+    pub(crate) side_to_move: Color,
+    pub(crate) castling: CastlingRights,
+    /// The square a pawn skipped over with its double step on the previous move
+    pub(crate) en_passant: Option<Square>,
+    /// Half moves since the last capture or pawn move, for the fifty-move rule
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    /// Captured pieces available for dropping, indexed by `Color::index`
+    pub(crate) pockets: [Vec<PieceType>; 2],
+    /// Number of checks delivered by each color, indexed by `Color::index`
+    pub(crate) checks_given: [u8; 2],
+    /// Squares of the pieces that were pawns before promoting, which Crazyhouse pockets as
+    /// pawns again when captured. Kept in FEN order and written with a '~' after the piece.
+    pub(crate) promoted: Vec<Square>,
+}
+
+impl Position {
+    /// A position without castling rights or en passant square, at move 1.
+    pub fn new(board: Chessboard, side_to_move: Color) -> Position {
+        Position {
+            board,
+            side_to_move,
+            castling: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            pockets: [vec![], vec![]],
+            checks_given: [0, 0],
+            promoted: vec![],
+        }
+    }
+
+    /// Parses all six FEN fields. Fields after the piece placement may be left out,
+    /// they then default to "w - - 0 1". A Crazyhouse pocket can follow the placement
+    /// in brackets, as in "rnbqkbnr/.../RNBQKBNR[Qn] b KQkq - 0 3", and promoted pieces are
+    /// marked with a '~' after their letter.
+    pub fn from_fen(fen: &str) -> Result<Position, ChessError> {
+        Position::from_fen_with_registry(fen, Arc::new(PieceRegistry::new()))
+    }
+
+    pub fn from_fen_with_registry(fen: &str, fairy_pieces: Arc<PieceRegistry>) -> Result<Position, ChessError> {
+        let mut fields: Vec<(usize, &str)> = vec![];
+        let mut field_start: Option<usize> = None;
+        for (index, c) in fen.char_indices().chain([(fen.len(), ' ')]) {
+            match (c.is_whitespace(), field_start) {
+                (true, Some(start)) => {
+                    fields.push((start, &fen[start..index]));
+                    field_start = None;
+                }
+                (false, None) => field_start = Some(index),
+                _ => {}
+            }
+        }
+        if fields.is_empty() {
+            return Err(ChessError::invalid_fen(0, 0, "Empty FEN"));
+        }
+        if fields.len() > 6 {
+            return Err(ChessError::invalid_fen(fields[6].0, 6, "Too many fields"));
+        }
+
+        let (placement_start, placement) = fields[0];
+        let (placement, pocket) = match placement.find('[') {
+            Some(bracket) if placement.ends_with(']') => (&placement[..bracket], Some((bracket, &placement[bracket + 1..placement.len() - 1]))),
+            Some(bracket) => return Err(ChessError::invalid_fen(placement_start + bracket, 0, "Unclosed pocket")),
+            None => (placement, None),
+        };
+        let (board, promoted) = Chessboard::from_fen_with_promotions(placement, fairy_pieces).map_err(|error| match error {
+            ChessError::InvalidFen { position, field, reason } => {
+                ChessError::InvalidFen { position: position + placement_start, field, reason }
+            }
+            other => other,
+        })?;
+        let mut position = Position::new(board, Color::White);
+        position.promoted = promoted;
+
+        if let Some((bracket, pocket)) = pocket {
+            for (index, c) in pocket.char_indices() {
+                let at = placement_start + bracket + 1 + index;
+                let color = if c.is_lowercase() { Color::Black } else { Color::White };
+                let piece_type = match c.to_ascii_lowercase() {
+                    'p' => PieceType::Pawn,
+                    'n' => PieceType::Knight,
+                    'b' => PieceType::Bishop,
+                    'r' => PieceType::Rook,
+                    'q' => PieceType::Queen,
+                    _ => return Err(ChessError::invalid_fen(at, 0, "Invalid pocket piece")),
+                };
+                position.add_to_pocket(color, piece_type);
+            }
+        }
+
+        if let Some(&(start, active)) = fields.get(1) {
+            position.side_to_move = match active {
+                "w" => Color::White,
+                "b" => Color::Black,
+                _ => return Err(ChessError::invalid_fen(start, 1, "Active color must be 'w' or 'b'")),
+            };
+        }
+
+        if let Some(&(start, castling)) = fields.get(2) {
+            if castling != "-" {
+                for (index, c) in castling.char_indices() {
+                    let right = match c {
+                        'K' => &mut position.castling.white_king_side,
+                        'Q' => &mut position.castling.white_queen_side,
+                        'k' => &mut position.castling.black_king_side,
+                        'q' => &mut position.castling.black_queen_side,
+                        _ => return Err(ChessError::invalid_fen(start + index, 2, "Invalid castling right")),
+                    };
+                    *right = true;
+                }
+            }
+        }
+
+        if let Some(&(start, en_passant)) = fields.get(3) {
+            if en_passant != "-" {
+                let square = position
+                    .board
+                    .algebraic_to_square(en_passant)
+                    .map_err(|_| ChessError::invalid_fen(start, 3, "Invalid en passant square"))?;
+                position.en_passant = Some(square);
+            }
+        }
+
+        if let Some(&(start, halfmove)) = fields.get(4) {
+            position.halfmove_clock = halfmove
+                .parse()
+                .map_err(|_| ChessError::invalid_fen(start, 4, "Halfmove clock must be a number"))?;
+        }
+
+        if let Some(&(start, fullmove)) = fields.get(5) {
+            position.fullmove_number = match fullmove.parse() {
+                Ok(number) if number > 0 => number,
+                _ => return Err(ChessError::invalid_fen(start, 5, "Fullmove number must be a positive number")),
+            };
+        }
+
+        Ok(position)
+    }
+
+    /// The full six field FEN, with the pockets in brackets when there is anything in them.
+    pub fn to_fen(&self) -> String {
+        let mut fen = self.board.to_fen_with_promotions(&self.promoted);
+        if self.pockets.iter().any(|pocket| !pocket.is_empty()) {
+            fen.push('[');
+            for color in [Color::White, Color::Black] {
+                for piece_type in self.pocket(color) {
+                    let symbol = piece_type.get_symbol();
+                    match color {
+                        Color::White => fen.push_str(symbol),
+                        Color::Black => fen.push_str(&symbol.to_lowercase()),
+                    }
+                }
+            }
+            fen.push(']');
+        }
+
+        let active = match self.side_to_move {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+        let mut castling = String::new();
+        for (right, symbol) in [
+            (self.castling.white_king_side, 'K'),
+            (self.castling.white_queen_side, 'Q'),
+            (self.castling.black_king_side, 'k'),
+            (self.castling.black_queen_side, 'q'),
+        ] {
+            if right {
+                castling.push(symbol);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        let en_passant = match self.en_passant {
+            Some(square) => self.board.square_to_algebraic(square),
+            None => "-".to_string(),
+        };
+        format!("{} {} {} {} {} {}", fen, active, castling, en_passant, self.halfmove_clock, self.fullmove_number)
+    }
+
+    /// Same pieces, side to move, castling rights and en passant square, as counted for repetitions.
+    pub fn is_repetition_of(&self, other: &Position) -> bool {
+        (self.board.width(), self.board.height()) == (other.board.width(), other.board.height())
+            && self.board.contents().eq(other.board.contents())
+            && self.side_to_move == other.side_to_move
+            && self.castling == other.castling
+            && self.en_passant == other.en_passant
+            && self.pockets == other.pockets
+            && self.promoted == other.promoted
+    }
+
+    /// A hash of everything `is_repetition_of` compares plus the checks given, so positions the
+    /// rules treat alike share a key. Stable between runs, for transposition tables and indexes.
+    pub fn hash_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (self.board.width(), self.board.height()).hash(&mut hasher);
+        for square in self.board.contents() {
+            square.map(|piece| (piece.piece_type, piece.color)).hash(&mut hasher);
+        }
+        (self.side_to_move, self.castling, self.en_passant, &self.pockets, self.checks_given, &self.promoted).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The same position with the colors swapped: the board turned upside down with white and
+    /// black exchanged, the other side to move and castling rights, en passant square, pockets
+    /// and checks given following along. Evaluations from the side to move should not change.
+    pub fn color_flipped(&self) -> Position {
+        let flip = |square: Square| Square { x: square.x, y: self.board.height() - 1 - square.y };
+        Position {
+            board: self.board.flip_vertical().swap_colors(),
+            side_to_move: self.side_to_move.opposite(),
+            castling: CastlingRights {
+                white_king_side: self.castling.black_king_side,
+                white_queen_side: self.castling.black_queen_side,
+                black_king_side: self.castling.white_king_side,
+                black_queen_side: self.castling.white_queen_side,
+            },
+            en_passant: self.en_passant.map(flip),
+            pockets: [self.pockets[1].clone(), self.pockets[0].clone()],
+            checks_given: [self.checks_given[1], self.checks_given[0]],
+            promoted: sorted(self.promoted.iter().copied().map(flip).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// The position mirrored left to right. Castling is not symmetric, so this is only the
+    /// same game without castling rights, and any are dropped.
+    pub fn mirrored(&self) -> Position {
+        Position {
+            board: self.board.flip_horizontal(),
+            castling: CastlingRights::none(),
+            en_passant: self.en_passant.map(|square| Square { x: self.board.width() - 1 - square.x, y: square.y }),
+            promoted: sorted(self.promoted.iter().map(|square| Square { x: self.board.width() - 1 - square.x, y: square.y }).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// One position standing for all the ones that play the same up to symmetry, to find
+    /// duplicates in a data set: the color flipped version when black is to move, then,
+    /// without castling rights, the mirrored version if its FEN sorts first.
+    pub fn canonical(&self) -> Position {
+        let position = match self.side_to_move {
+            Color::White => self.clone(),
+            Color::Black => self.color_flipped(),
+        };
+        if position.castling != CastlingRights::none() {
+            return position;
+        }
+        let mirrored = position.mirrored();
+        if mirrored.to_fen() < position.to_fen() {
+            mirrored
+        } else {
+            position
+        }
+    }
+
+    /// Marks the piece on the square as promoted or not, keeping `promoted` in FEN order.
+    pub fn set_promoted(&mut self, square: Square, promoted: bool) {
+        self.promoted.retain(|other| *other != square);
+        if promoted {
+            self.promoted.push(square);
+            self.promoted = sorted(std::mem::take(&mut self.promoted));
+        }
+    }
+
+    pub fn board(&self) -> &Chessboard {
+        &self.board
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn castling(&self) -> CastlingRights {
+        self.castling
+    }
+
+    /// The square a pawn skipped over with its double step on the previous move
+    pub fn en_passant(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    /// Half moves since the last capture or pawn move, for the fifty-move rule
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Number of checks `color` has delivered, for Three-check
+    pub fn checks_given(&self, color: Color) -> u8 {
+        self.checks_given[color.index()]
+    }
+
+    /// Whether the piece on the square was a pawn before promoting
+    pub fn is_promoted(&self, square: Square) -> bool {
+        self.promoted.contains(&square)
+    }
+
+    pub fn pocket(&self, color: Color) -> &Vec<PieceType> {
+        &self.pockets[color.index()]
+    }
+
+    pub fn add_to_pocket(&mut self, color: Color, piece_type: PieceType) {
+        self.pockets[color.index()].push(piece_type);
+    }
+
+    /// Removes one piece of the given type from the pocket. Returns false if there was none.
+    pub fn take_from_pocket(&mut self, color: Color, piece_type: PieceType) -> bool {
+        let pocket = &mut self.pockets[color.index()];
+        match pocket.iter().position(|pocketed| *pocketed == piece_type) {
+            Some(index) => {
+                pocket.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Squares in FEN order, rank by rank from the top.
+fn sorted(mut squares: Vec<Square>) -> Vec<Square> {
+    squares.sort_by_key(|square| (square.y, square.x));
+    squares
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Square;
+    use crate::chess_piece::{Color, PieceType};
+    use crate::error::ChessError;
+    use crate::position::{CastlingRights, Position, STANDARD_START_FEN};
+
+    #[test]
+    fn parsing_all_fen_fields() {
+        let position = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K2R w Kq d6 3 42").unwrap();
+        assert_eq!(position.side_to_move, Color::White);
+        assert_eq!(position.castling, CastlingRights { white_king_side: true, black_queen_side: true, ..CastlingRights::none() });
+        assert_eq!(position.en_passant, Some(Square::algebraic_to_coords("d6").unwrap()));
+        assert_eq!(position.halfmove_clock, 3);
+        assert_eq!(position.fullmove_number, 42);
+    }
+
+    #[test]
+    fn missing_fen_fields_use_defaults() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3").unwrap();
+        assert_eq!(position.side_to_move, Color::White);
+        assert_eq!(position.castling, CastlingRights::none());
+        assert_eq!(position.en_passant, None);
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (0, 1));
+    }
+
+    #[test]
+    fn standard_start_position() {
+        let position = Position::from_fen(STANDARD_START_FEN).unwrap();
+        assert_eq!(position.castling, CastlingRights::all());
+        assert_eq!(position.board.pieces(Color::Black).len(), 16);
+    }
+
+    #[test]
+    fn crazyhouse_pocket_in_fen() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/4K3[QNp] b - - 0 1").unwrap();
+        assert_eq!(position.pocket(Color::White), &vec![PieceType::Queen, PieceType::Knight]);
+        assert_eq!(position.pocket(Color::Black), &vec![PieceType::Pawn]);
+    }
+
+    #[test]
+    fn promoted_pieces_in_fen() {
+        let fen = "rQ~2k3/8/8/8/8/8/8/4K2n~[Pq] b - - 0 1";
+        let position = Position::from_fen(fen).unwrap();
+        assert_eq!(position.promoted, vec![Square { x: 1, y: 0 }, Square { x: 7, y: 7 }]);
+        assert_eq!(position.to_fen(), fen);
+        assert_eq!(position.board.width(), 8);
+        assert_ne!(position.hash_key(), Position::from_fen("rQ2k3/8/8/8/8/8/8/4K2n~[Pq] b - - 0 1").unwrap().hash_key());
+        assert_eq!(Position::from_fen("~4k3/8/8/8/8/8/8/4K3").err(), Some(ChessError::invalid_fen(0, 0, "'~' has to follow a piece")));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/Q~~3K3").err(), Some(ChessError::invalid_fen(18, 0, "'~' has to follow a piece")));
+    }
+
+    #[test]
+    fn writing_fen() {
+        for fen in [STANDARD_START_FEN, "4k3/8/8/3pP3/8/8/8/4K2R w Kq d6 3 42", "4k3/8/8/8/8/8/8/4K3[QNp] b - - 0 1"] {
+            assert_eq!(Position::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn invalid_fen_fields_are_reported() {
+        let fen = "4k3/8/8/8/8/8/8/4K3";
+        assert_eq!(Position::from_fen(&format!("{} x", fen)).err(), Some(ChessError::invalid_fen(20, 1, "Active color must be 'w' or 'b'")));
+        assert_eq!(Position::from_fen(&format!("{} w KX", fen)).err(), Some(ChessError::invalid_fen(23, 2, "Invalid castling right")));
+        assert_eq!(Position::from_fen(&format!("{} w - z9", fen)).err(), Some(ChessError::invalid_fen(24, 3, "Invalid en passant square")));
+        assert_eq!(Position::from_fen(&format!("{} w - - x", fen)).err(), Some(ChessError::invalid_fen(26, 4, "Halfmove clock must be a number")));
+        assert_eq!(Position::from_fen(&format!("{} w - - 0 0", fen)).err(), Some(ChessError::invalid_fen(28, 5, "Fullmove number must be a positive number")));
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/8/4K2X w").err(), Some(ChessError::invalid_fen(19, 0, "Invalid piece")));
+
+        assert_eq!(Position::from_fen("P w - - 0 1").err(), Some(ChessError::invalid_fen(0, 0, "Board needs at least 2 files and 2 ranks")));
+        assert_eq!(Position::from_fen("27 w").err(), Some(ChessError::invalid_fen(0, 0, "Board is larger than 26 files or ranks")));
+    }
+
+    #[test]
+    fn hash_keys() {
+        let key = |fen: &str| Position::from_fen(fen).unwrap().hash_key();
+        assert_eq!(key("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), key("4k3/8/8/8/8/8/8/4K2R w K - 12 40"));
+        assert_ne!(key("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), key("4k3/8/8/8/8/8/8/4K2R b K - 0 1"));
+        assert_ne!(key("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), key("4k3/8/8/8/8/8/8/4K2R w - - 0 1"));
+        assert_ne!(key("4k3/8/8/8/8/8/8/4K3[N] w - - 0 1"), key("4k3/8/8/8/8/8/8/4K3[n] w - - 0 1"));
+    }
+
+    #[test]
+    fn flipping_colors() {
+        let position = Position::from_fen("r3k2r/pp3ppp/8/3pP3/8/8/PPP2PPP/R3K2R w Kq d6 0 12").unwrap();
+        let flipped = position.color_flipped();
+        assert_eq!(flipped.to_fen(), "r3k2r/ppp2ppp/8/8/3Pp3/8/PP3PPP/R3K2R b Qk d3 0 12");
+        assert_eq!(flipped.color_flipped().to_fen(), position.to_fen());
+
+        let crazyhouse = Position::from_fen("4k3/8/8/8/8/8/8/4K3[QNp] w - - 0 1").unwrap();
+        assert_eq!(crazyhouse.color_flipped().to_fen(), "4k3/8/8/8/8/8/8/4K3[Pqn] b - - 0 1");
+    }
+
+    #[test]
+    fn canonical_positions() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/1Q6/6K1 w - - 0 1").unwrap();
+        let mirrored = Position::from_fen("1k6/ppp5/8/8/8/8/6Q1/1K6 w - - 0 1").unwrap();
+        let black_to_move = Position::from_fen("6k1/1q6/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        let canonical = position.canonical();
+        assert_eq!(mirrored.canonical().to_fen(), canonical.to_fen());
+        assert_eq!(black_to_move.canonical().to_fen(), canonical.to_fen());
+        assert_eq!(canonical.canonical().to_fen(), canonical.to_fen());
+
+        // Castling rights keep the position from being mirrored
+        let castling = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(castling.canonical().to_fen(), castling.to_fen());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::error::ChessError;
+use crate::moves::{is_in_check, Move};
+use crate::position::Position;
+use crate::variant::{Standard, Variant};
+
+/// A tactic to find: the position the solver starts from and the moves that solve it, the
+/// solver's moves alternating with the forced replies.
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub id: String,
+    pub position: Position,
+    pub solution: Vec<Move>,
+    pub rating: Option<u32>,
+    pub themes: Vec<String>,
+}
+
+/// Reads puzzles in the Lichess CSV format:
+/// `PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags`.
+/// The FEN is the position before the opponent's last move, which is the first of the UCI moves,
+/// so the puzzle starts after it. Only the first three columns are required and a header line
+/// is skipped. Every move is checked to be legal.
+pub fn load_puzzles(text: &str) -> Result<Vec<Puzzle>, ChessError> {
+    let mut puzzles = vec![];
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with("PuzzleId,")) {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() < 3 {
+            return Err(ChessError::invalid_notation(line, "Expected an id, a FEN and the moves"));
+        }
+        let mut position = Position::from_fen(fields[1])?;
+        let mut moves = vec![];
+        let mut replay = position.clone();
+        for uci in fields[2].split_whitespace() {
+            let mv = Move::from_uci(&replay.board, uci)?;
+            Standard.play(&mut replay, mv)?;
+            moves.push(mv);
+        }
+        if moves.len() < 2 {
+            return Err(ChessError::invalid_notation(line, "Expected the opponent's move and at least one more"));
+        }
+        Standard.apply_move(&mut position, moves.remove(0))?;
+        puzzles.push(Puzzle {
+            id: fields[0].to_string(),
+            position,
+            solution: moves,
+            rating: fields.get(3).and_then(|rating| rating.parse().ok()),
+            themes: fields
+                .get(7)
+                .map(|themes| themes.split_whitespace().map(str::to_string).collect())
+                .unwrap_or_default(),
+        });
+    }
+    Ok(puzzles)
+}
+
+/// What a move in a puzzle turned out to be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PuzzleVerdict {
+    /// The right move, the opponent answered with `reply` and the solver moves again
+    Correct { reply: Move },
+    /// The last move of the solution, or any other move that mates
+    Solved,
+    /// Not the move of the solution, `expected` was
+    Wrong { expected: Move },
+}
+
+/// Someone working through a puzzle, one move at a time.
+#[derive(Debug, Clone)]
+pub struct PuzzleAttempt<'a> {
+    puzzle: &'a Puzzle,
+    position: Position,
+    /// Index of the solver's next move in the solution
+    ply: usize,
+}
+
+impl<'a> PuzzleAttempt<'a> {
+    pub fn new(puzzle: &'a Puzzle) -> PuzzleAttempt<'a> {
+        PuzzleAttempt { puzzle, position: puzzle.position.clone(), ply: 0 }
+    }
+
+    /// The position the solver has to move in.
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+
+    /// Checks the solver's move against the solution. Moves that are not legal are errors and
+    /// change nothing. After a correct move the opponent's reply is played too, a wrong move
+    /// leaves the position as it was.
+    pub fn play(&mut self, mv: Move) -> Result<PuzzleVerdict, ChessError> {
+        let expected = match self.puzzle.solution.get(self.ply) {
+            Some(expected) => *expected,
+            None => return Err(ChessError::illegal_move("The puzzle is already solved")),
+        };
+        let mut next = self.position.clone();
+        Standard.play(&mut next, mv)?;
+        let mates = is_in_check(&next.board, next.side_to_move) && Standard.legal_moves(&next).is_empty();
+        if mv != expected && !mates {
+            return Ok(PuzzleVerdict::Wrong { expected });
+        }
+        self.position = next;
+        match self.puzzle.solution.get(self.ply + 1) {
+            Some(reply) if !mates => {
+                Standard.apply_move(&mut self.position, *reply)?;
+                self.ply += 2;
+                if self.ply >= self.puzzle.solution.len() {
+                    // Solutions end with the solver's move, one ending in a reply counts as done
+                    return Ok(PuzzleVerdict::Solved);
+                }
+                Ok(PuzzleVerdict::Correct { reply: *reply })
+            }
+            _ => {
+                self.ply = self.puzzle.solution.len();
+                Ok(PuzzleVerdict::Solved)
+            }
+        }
+    }
+
+    /// The solver's moves still to find, with the replies between them.
+    pub fn remaining(&self) -> &[Move] {
+        &self.puzzle.solution[self.ply.min(self.puzzle.solution.len())..]
+    }
+}
+
+/// Puzzles solved and failed so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PuzzleScore {
+    pub solved: usize,
+    pub failed: usize,
+    /// Puzzles solved in a row since the last failure
+    pub streak: usize,
+    pub best_streak: usize,
+}
+
+impl PuzzleScore {
+    pub fn record(&mut self, solved: bool) {
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.failed += 1;
+            self.streak = 0;
+        }
+    }
+
+    pub fn attempted(&self) -> usize {
+        self.solved + self.failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::Move;
+    use crate::puzzle::{load_puzzles, Puzzle, PuzzleAttempt, PuzzleScore, PuzzleVerdict};
+
+    const PUZZLES: &str = "PuzzleId,FEN,Moves,Rating,RatingDeviation,Popularity,NbPlays,Themes,GameUrl,OpeningTags\n\
+        00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760,80,83,72,\
+        mate mateIn2 middlegame short,https://lichess.org/yyznGmXs/black#34,Italian_Game\n\
+        mate1,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8h8 a1a8\n";
+
+    fn mv(puzzle: &Puzzle, uci: &str) -> Move {
+        Move::from_uci(&puzzle.position.board, uci).unwrap()
+    }
+
+    #[test]
+    fn reading_lichess_puzzles() {
+        let puzzles = load_puzzles(PUZZLES).unwrap();
+        assert_eq!(puzzles.len(), 2);
+        assert_eq!(puzzles[0].id, "00sHx");
+        assert_eq!(puzzles[0].position.to_fen(), "q5nr/1ppknQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 w - - 1 18");
+        assert_eq!(puzzles[0].solution.len(), 3);
+        assert_eq!(puzzles[0].rating, Some(1760));
+        assert!(puzzles[0].themes.contains(&"mateIn2".to_string()));
+        assert_eq!((puzzles[1].rating, puzzles[1].themes.len()), (None, 0));
+    }
+
+    #[test]
+    fn invalid_puzzles() {
+        assert!(load_puzzles("x,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1").is_err());
+        assert!(load_puzzles("x,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8h8").is_err());
+        assert!(load_puzzles("x,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8h8 a1a9").is_err());
+        assert!(load_puzzles("x,6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1,g8h8 a1b2").is_err());
+    }
+
+    #[test]
+    fn solving_a_puzzle() {
+        let puzzles = load_puzzles(PUZZLES).unwrap();
+        let puzzle = &puzzles[0];
+        let mut attempt = PuzzleAttempt::new(puzzle);
+        let wrong = mv(puzzle, "a3b4");
+        assert_eq!(attempt.play(wrong), Ok(PuzzleVerdict::Wrong { expected: puzzle.solution[0] }));
+        assert!(attempt.play(mv(puzzle, "a2a4")).is_err());
+        assert_eq!(attempt.play(puzzle.solution[0]), Ok(PuzzleVerdict::Correct { reply: puzzle.solution[1] }));
+        assert_eq!(attempt.remaining().len(), 1);
+        assert_eq!(attempt.play(puzzle.solution[2]), Ok(PuzzleVerdict::Solved));
+        assert!(attempt.play(puzzle.solution[2]).is_err());
+    }
+
+    #[test]
+    fn other_mates_count() {
+        // Rb8 mates as well as Ra8
+        let puzzles = load_puzzles("x,6k1/5ppp/8/8/8/8/1R6/R5K1 b - - 0 1,g8h8 a1a8").unwrap();
+        let mut attempt = PuzzleAttempt::new(&puzzles[0]);
+        assert_eq!(attempt.play(mv(&puzzles[0], "b2b8")), Ok(PuzzleVerdict::Solved));
+    }
+
+    #[test]
+    fn keeping_score() {
+        let mut score = PuzzleScore::default();
+        for solved in [true, true, false, true] {
+            score.record(solved);
+        }
+        assert_eq!(score, PuzzleScore { solved: 3, failed: 1, streak: 1, best_streak: 2 });
+        assert_eq!(score.attempted(), 4);
+    }
+}
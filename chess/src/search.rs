@@ -0,0 +1,849 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chess_piece::{Color, PieceType, PieceType::Pawn};
+use crate::eval::EvalParams;
+use crate::moves::{is_capture, is_in_check, Move};
+use crate::position::Position;
+use crate::see::static_exchange;
+use crate::tt::{Bound, Entry, TranspositionTable, DEFAULT_ENTRIES};
+use crate::variant::{Outcome, Variant};
+
+/// Score of being checkmated right now. Mates further away score closer to zero,
+/// so the search prefers the fastest mate and the slowest defeat.
+pub const MATE_SCORE: i32 = 30_000;
+/// Scores beyond this are mates rather than material
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000;
+const INFINITY: i32 = MATE_SCORE + 1;
+/// Depth used when only time or nodes limit the search
+pub const MAX_DEPTH: u32 = 64;
+
+/// When to stop searching. Whichever limit is reached first ends the search, the
+/// result is then that of the last depth that was searched completely.
+/// Depth 1 is always completed so there is a move to play.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchLimits {
+    pub depth: u32,
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits { depth, time: None, nodes: None }
+    }
+
+    pub fn time(time: Duration) -> SearchLimits {
+        SearchLimits { depth: MAX_DEPTH, time: Some(time), nodes: None }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits { depth: MAX_DEPTH, time: None, nodes: Some(nodes) }
+    }
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits::depth(3)
+    }
+}
+
+/// How the search goes about it, apart from when to stop. The move ordering heuristics, pruning
+/// and extensions can be switched off one by one to measure what each of them is worth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchOptions {
+    pub eval: EvalParams,
+    /// Try the best move from earlier, shallower searches of the position first
+    pub hash_move: bool,
+    /// Try captures first, most valuable victim and least valuable attacker first, and captures
+    /// that lose material according to the static exchange after the quiet moves. Quiescence
+    /// search always orders its captures this way.
+    pub mvv_lva: bool,
+    /// Try quiet moves that caused a cutoff at the same distance from the root early
+    pub killers: bool,
+    /// Order the remaining quiet moves by how often they caused cutoffs anywhere in the tree
+    pub history: bool,
+    /// Skip the move when searching a reduced depth still fails high, outside of check and
+    /// pawn endings where passing could be the best move
+    pub null_move: bool,
+    /// Search quiet moves late in the ordering less deep, and again at full depth if they turn out good
+    pub late_move_reductions: bool,
+    /// Skip quiet moves close to the horizon when the evaluation is too far below alpha to catch up
+    pub futility: bool,
+    /// Search each depth in a narrow window around the previous score first
+    pub aspiration_windows: bool,
+    /// Search one ply deeper when in check
+    pub check_extensions: bool,
+    /// Threads searching together, sharing a transposition table (Lazy SMP). With one thread
+    /// the search runs on the calling thread and always gives the same result.
+    pub threads: usize,
+}
+
+impl SearchOptions {
+    /// Names of the switches, as `feature_mut` takes them
+    pub const FEATURES: [&'static str; 9] =
+        ["hash-move", "mvv-lva", "killers", "history", "null-move", "lmr", "futility", "aspiration", "check-extensions"];
+
+    /// Plain alpha-beta: moves in the order the move generator gives them, no pruning and no extensions.
+    pub fn plain() -> SearchOptions {
+        let mut options = SearchOptions::default();
+        for feature in SearchOptions::FEATURES {
+            if let Some(enabled) = options.feature_mut(feature) {
+                *enabled = false;
+            }
+        }
+        options
+    }
+
+    /// The switch for one of the `FEATURES` by name.
+    pub fn feature_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "hash-move" => Some(&mut self.hash_move),
+            "mvv-lva" => Some(&mut self.mvv_lva),
+            "killers" => Some(&mut self.killers),
+            "history" => Some(&mut self.history),
+            "null-move" => Some(&mut self.null_move),
+            "lmr" => Some(&mut self.late_move_reductions),
+            "futility" => Some(&mut self.futility),
+            "aspiration" => Some(&mut self.aspiration_windows),
+            "check-extensions" => Some(&mut self.check_extensions),
+            _ => None,
+        }
+    }
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            eval: EvalParams::STANDARD,
+            hash_move: true,
+            mvv_lva: true,
+            killers: true,
+            history: true,
+            null_move: true,
+            late_move_reductions: true,
+            futility: true,
+            aspiration_windows: true,
+            check_extensions: true,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// `None` when the game is already over
+    pub best_move: Option<Move>,
+    /// Centipawns from the point of view of the side to move, see `mate_in` for mate scores
+    pub score: i32,
+    /// The expected continuation, starting with the best move
+    pub pv: Vec<Move>,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+impl SearchResult {
+    /// Moves until mate when the score is a mate score, negative when the side to move gets mated.
+    pub fn mate_in(&self) -> Option<i32> {
+        mate_in(self.score)
+    }
+}
+
+/// Full moves to mate for a mate score, negative when the side to move is the one getting mated.
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score > MATE_THRESHOLD {
+        Some((MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_THRESHOLD {
+        Some(-(MATE_SCORE + score) / 2)
+    } else {
+        None
+    }
+}
+
+/// Finds the best move with iterative deepening alpha-beta, followed by a capture-only
+/// quiescence search so the evaluation is not taken in the middle of an exchange.
+pub fn search(variant: &dyn Variant, position: &Position, limits: SearchLimits) -> SearchResult {
+    search_with_options(variant, position, limits, &SearchOptions::default())
+}
+
+/// `search` with other options, such as tuned evaluation weights, more threads or some move
+/// ordering switched off.
+///
+/// Extra threads run their own iterative deepening on the same position, half of them a depth
+/// ahead, and only help by filling the shared transposition table. The result is that of the
+/// calling thread, which tells the helpers to stop once it is done.
+pub fn search_with_options(
+    variant: &dyn Variant,
+    position: &Position,
+    limits: SearchLimits,
+    options: &SearchOptions,
+) -> SearchResult {
+    search_excluding(variant, position, limits, options, vec![])
+}
+
+/// The best `lines` moves with their scores and continuations, best first. Each line after the
+/// first comes from a new search with the moves of the lines before it left out at the root, so
+/// all scores are exact, though pruning may rank a later line above an earlier one and the lines
+/// are sorted again at the end. Fewer lines come back when there are fewer legal moves.
+pub fn search_lines(
+    variant: &dyn Variant,
+    position: &Position,
+    limits: SearchLimits,
+    options: &SearchOptions,
+    lines: usize,
+) -> Vec<SearchResult> {
+    let legal_moves = variant.legal_moves(position).len();
+    let mut results: Vec<SearchResult> = vec![];
+    while results.len() < lines.min(legal_moves.max(1)) {
+        let excluded = results.iter().filter_map(|result| result.best_move).collect();
+        let result = search_excluding(variant, position, limits, options, excluded);
+        let done = result.best_move.is_none();
+        results.push(result);
+        if done {
+            break;
+        }
+    }
+    results.sort_by_key(|result| -result.score);
+    results
+}
+
+fn search_excluding(
+    variant: &dyn Variant,
+    position: &Position,
+    limits: SearchLimits,
+    options: &SearchOptions,
+    excluded: Vec<Move>,
+) -> SearchResult {
+    let shared = SharedState {
+        table: TranspositionTable::new(DEFAULT_ENTRIES),
+        nodes: AtomicU64::new(0),
+        stop: AtomicBool::new(false),
+        excluded,
+    };
+    let deadline = limits.time.map(|time| Instant::now() + time);
+    let mut result = thread::scope(|scope| {
+        for helper in 1..options.threads {
+            let shared = &shared;
+            scope.spawn(move || {
+                let mut searcher = Searcher::new(variant, options, shared, false);
+                searcher.iterative_deepening(position, 1 + helper as u32 % 2, limits, deadline);
+            });
+        }
+        let mut searcher = Searcher::new(variant, options, &shared, true);
+        let result = searcher.iterative_deepening(position, 1, limits, deadline);
+        shared.stop.store(true, Ordering::Relaxed);
+        result
+    });
+    result.nodes = shared.nodes.load(Ordering::Relaxed);
+    result
+}
+
+/// What the threads of one search share.
+struct SharedState {
+    table: TranspositionTable,
+    /// Nodes searched by all threads together, the node limit applies to these
+    nodes: AtomicU64,
+    /// Set by the main thread when it is done, so the helpers stop too
+    stop: AtomicBool,
+    /// Moves not searched at the root
+    excluded: Vec<Move>,
+}
+
+/// Null move searches are this much shallower, one more from depth 7 on
+const NULL_MOVE_REDUCTION: u32 = 2;
+const NULL_MOVE_MIN_DEPTH: u32 = 3;
+/// Moves searched at full depth before the rest are reduced
+const FULL_DEPTH_MOVES: usize = 3;
+const LMR_MIN_DEPTH: u32 = 3;
+/// How far below alpha the evaluation may be at depth 1 and 2 before quiet moves are skipped
+const FUTILITY_MARGINS: [i32; 2] = [200, 500];
+/// Half width of the first aspiration window, it grows four times on each failure
+const ASPIRATION_WINDOW: i32 = 50;
+const ASPIRATION_LIMIT: i32 = 1_000;
+
+// Move ordering scores, each group above the next
+const HASH_MOVE_SCORE: i32 = 4_000_000;
+const GOOD_CAPTURE_SCORE: i32 = 3_000_000;
+const KILLER_SCORE: i32 = 2_000_000;
+const HISTORY_LIMIT: i32 = 1_000_000;
+const BAD_CAPTURE_SCORE: i32 = -1_000_000;
+
+/// One thread's search, with its own move ordering statistics.
+struct Searcher<'a> {
+    variant: &'a dyn Variant,
+    options: &'a SearchOptions,
+    shared: &'a SharedState,
+    /// The main thread reports the result, the others only help
+    main: bool,
+    /// Deadline and node limit, left out while the main thread searches depth 1
+    limits: Option<(Option<Instant>, Option<u64>)>,
+    /// Set once a limit is hit, the scores of the unfinished depth are then meaningless
+    stopped: bool,
+    /// Depth of the current iteration, check extensions stop at twice this many plies
+    root_depth: u32,
+    /// Set right before searching a null move, so the reply does not pass as well
+    after_null_move: bool,
+    /// Two quiet moves per ply that recently caused a cutoff there
+    killers: Vec<[Option<Move>; 2]>,
+    /// Bonus per side and move for the cutoffs it caused, growing with the depth of the cutoff
+    history: HashMap<(Color, Move), i32>,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(variant: &'a dyn Variant, options: &'a SearchOptions, shared: &'a SharedState, main: bool) -> Searcher<'a> {
+        Searcher {
+            variant,
+            options,
+            shared,
+            main,
+            limits: None,
+            stopped: false,
+            root_depth: 0,
+            after_null_move: false,
+            killers: vec![],
+            history: HashMap::new(),
+        }
+    }
+
+    fn iterative_deepening(
+        &mut self,
+        position: &Position,
+        first_depth: u32,
+        limits: SearchLimits,
+        deadline: Option<Instant>,
+    ) -> SearchResult {
+        let mut result = SearchResult { best_move: None, score: 0, pv: vec![], depth: 0, nodes: 0 };
+        for depth in first_depth..=limits.depth.max(first_depth) {
+            if depth == 2 || !self.main {
+                self.limits = Some((deadline, limits.nodes));
+            }
+            self.root_depth = depth;
+            let mut pv = vec![];
+            let use_window = self.options.aspiration_windows && depth >= 3 && result.best_move.is_some();
+            let score = if use_window {
+                self.aspiration_search(position, depth, result.score, &mut pv)
+            } else {
+                self.negamax(position, depth, 0, -INFINITY, INFINITY, &mut pv)
+            };
+            if self.stopped {
+                break;
+            }
+            result = SearchResult { best_move: pv.first().copied(), score, pv, depth, nodes: 0 };
+            if result.best_move.is_none() || mate_in(score).is_some() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Searches the root in a window around the previous iteration's score, widening it until
+    /// the score falls inside.
+    fn aspiration_search(&mut self, position: &Position, depth: u32, previous: i32, pv: &mut Vec<Move>) -> i32 {
+        let mut window = ASPIRATION_WINDOW;
+        while window < ASPIRATION_LIMIT {
+            let (alpha, beta) = (previous - window, previous + window);
+            pv.clear();
+            let score = self.negamax(position, depth, 0, alpha, beta, pv);
+            if self.stopped || (score > alpha && score < beta) {
+                return score;
+            }
+            window *= 4;
+        }
+        pv.clear();
+        self.negamax(position, depth, 0, -INFINITY, INFINITY, pv)
+    }
+
+    fn out_of_budget(&mut self) -> bool {
+        if let Some((deadline, node_limit)) = self.limits {
+            let out_of_time = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let out_of_nodes = node_limit.is_some_and(|limit| self.shared.nodes.load(Ordering::Relaxed) >= limit);
+            self.stopped = self.stopped || out_of_time || out_of_nodes;
+        }
+        if !self.main && self.shared.stop.load(Ordering::Relaxed) {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    fn count_node(&self) {
+        self.shared.nodes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Score of a finished game for the side to move, `ply` moves from the root.
+    fn outcome_score(&self, outcome: Outcome, position: &Position, ply: u32) -> i32 {
+        match outcome {
+            Outcome::Draw => 0,
+            Outcome::Win(color) if color == position.side_to_move => MATE_SCORE - ply as i32,
+            Outcome::Win(_) => -MATE_SCORE + ply as i32,
+        }
+    }
+
+    /// Checks the variant's own rules and the fifty-move rule, then returns the legal moves
+    /// or the score of the game being over.
+    fn moves_or_score(&self, position: &Position, ply: u32) -> Result<Vec<Move>, i32> {
+        if let Some(outcome) = self.variant.rule_outcome(position) {
+            return Err(self.outcome_score(outcome, position, ply));
+        }
+        let moves = self.variant.legal_moves(position);
+        if moves.is_empty() {
+            if is_in_check(&position.board, position.side_to_move) {
+                return Err(-MATE_SCORE + ply as i32);
+            }
+            return Err(0);
+        }
+        if position.halfmove_clock >= 100 {
+            return Err(0);
+        }
+        Ok(moves)
+    }
+
+    fn negamax(&mut self, position: &Position, depth: u32, ply: u32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        let after_null_move = std::mem::take(&mut self.after_null_move);
+        if self.out_of_budget() {
+            return 0;
+        }
+        self.count_node();
+        let mut moves = match self.moves_or_score(position, ply) {
+            Ok(moves) => moves,
+            Err(score) => return score,
+        };
+        if ply == 0 {
+            moves.retain(|mv| !self.shared.excluded.contains(mv));
+        }
+        let in_check = is_in_check(&position.board, position.side_to_move);
+        let depth = if in_check && self.options.check_extensions && ply < 2 * self.root_depth { depth + 1 } else { depth };
+        if depth == 0 {
+            return self.quiescence(position, ply, alpha, beta, moves);
+        }
+
+        let key = position.hash_key();
+        let entry = self.shared.table.probe(key);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = score_from_table(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                pv.extend(entry.best_move.filter(|_| entry.bound == Bound::Exact));
+                return score;
+            }
+        }
+
+        let may_prune = ply > 0 && !in_check && alpha.abs() < MATE_THRESHOLD && beta.abs() < MATE_THRESHOLD;
+        if self.options.null_move && may_prune && !after_null_move && depth >= NULL_MOVE_MIN_DEPTH && has_pieces(position) {
+            let reduction = NULL_MOVE_REDUCTION + u32::from(depth > 6);
+            self.after_null_move = true;
+            let score = -self.negamax(&pass(position), depth - 1 - reduction.min(depth - 1), ply + 1, -beta, -beta + 1, &mut vec![]);
+            if score >= beta && !self.stopped {
+                return beta;
+            }
+        }
+        let futile = self.options.futility
+            && may_prune
+            && depth <= FUTILITY_MARGINS.len() as u32
+            && self.options.eval.evaluate(position) + FUTILITY_MARGINS[depth as usize - 1] <= alpha;
+
+        let original_alpha = alpha;
+        let hash_move = entry.and_then(|entry| entry.best_move).filter(|_| self.options.hash_move);
+        let mut best_move = None;
+        let mut searched = 0;
+        for mv in self.order_moves(position, moves, hash_move, ply) {
+            let mut next = position.clone();
+            if self.variant.apply_move(&mut next, mv).is_err() {
+                continue;
+            }
+            let quiet = !is_capture(position, mv) && !matches!(mv, Move::Normal { promotion: Some(_), .. });
+            let late = self.options.late_move_reductions
+                && depth >= LMR_MIN_DEPTH
+                && !in_check
+                && searched >= FULL_DEPTH_MOVES
+                && quiet
+                && !self.is_killer(mv, ply);
+            let gives_check = (futile || late) && quiet && is_in_check(&next.board, next.side_to_move);
+            if futile && quiet && searched > 0 && !gives_check {
+                continue;
+            }
+            searched += 1;
+
+            let mut line = vec![];
+            let reduced_fails_low =
+                late && !gives_check && -self.negamax(&next, depth - 2, ply + 1, -alpha - 1, -alpha, &mut line) <= alpha;
+            if reduced_fails_low {
+                continue;
+            }
+            line.clear();
+            let score = -self.negamax(&next, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                pv.clear();
+                pv.push(mv);
+                pv.append(&mut line);
+                if alpha >= beta {
+                    if !is_capture(position, mv) {
+                        self.record_cutoff(position.side_to_move, mv, depth, ply);
+                    }
+                    break;
+                }
+            }
+        }
+        // The root's score without some of its moves is not the position's score
+        if !self.stopped && (ply > 0 || self.shared.excluded.is_empty()) {
+            let bound = if alpha >= beta {
+                Bound::Lower
+            } else if alpha > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            let best_move = best_move.or(entry.and_then(|entry| entry.best_move));
+            self.shared.table.store(key, Entry { depth, score: score_to_table(alpha, ply), bound, best_move });
+        }
+        alpha
+    }
+
+    /// The moves sorted so the ones most likely to be best come first, as far as the options allow.
+    fn order_moves(&self, position: &Position, moves: Vec<Move>, hash_move: Option<Move>, ply: u32) -> Vec<Move> {
+        let mut scored: Vec<(i32, Move)> =
+            moves.into_iter().map(|mv| (self.move_score(position, mv, hash_move, ply), mv)).collect();
+        scored.sort_by_key(|(score, _)| -score);
+        scored.into_iter().map(|(_, mv)| mv).collect()
+    }
+
+    fn move_score(&self, position: &Position, mv: Move, hash_move: Option<Move>, ply: u32) -> i32 {
+        if Some(mv) == hash_move {
+            return HASH_MOVE_SCORE;
+        }
+        if self.options.mvv_lva {
+            if let Some(score) = capture_score(position, mv) {
+                return score;
+            }
+        }
+        if self.options.killers {
+            let killers = self.killers.get(ply as usize);
+            if let Some(index) = killers.and_then(|killers| killers.iter().position(|killer| *killer == Some(mv))) {
+                return KILLER_SCORE - index as i32;
+            }
+        }
+        if self.options.history {
+            return self.history.get(&(position.side_to_move, mv)).copied().unwrap_or(0);
+        }
+        0
+    }
+
+    fn is_killer(&self, mv: Move, ply: u32) -> bool {
+        self.options.killers && self.killers.get(ply as usize).is_some_and(|killers| killers.contains(&Some(mv)))
+    }
+
+    /// Remembers a quiet move that was too good for the opponent to allow.
+    fn record_cutoff(&mut self, side: Color, mv: Move, depth: u32, ply: u32) {
+        if self.options.killers {
+            let ply = ply as usize;
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None, None]);
+            }
+            let killers = &mut self.killers[ply];
+            if killers[0] != Some(mv) {
+                killers[1] = killers[0];
+                killers[0] = Some(mv);
+            }
+        }
+        if self.options.history {
+            let bonus = (depth * depth) as i32;
+            let entry = self.history.entry((side, mv)).or_insert(0);
+            *entry = (*entry + bonus).min(HISTORY_LIMIT);
+        }
+    }
+
+    fn quiescence(&mut self, position: &Position, ply: u32, mut alpha: i32, beta: i32, moves: Vec<Move>) -> i32 {
+        let stand_pat = self.options.eval.evaluate(position);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        // Captures are always ordered here, without it quiescence searches explode
+        let mut captures: Vec<(i32, Move)> = moves
+            .into_iter()
+            .filter(|mv| is_capture(position, *mv))
+            .map(|mv| (capture_score(position, mv).unwrap_or(0), mv))
+            .collect();
+        captures.sort_by_key(|(score, _)| -score);
+        for (_, mv) in captures {
+            if self.out_of_budget() {
+                return 0;
+            }
+            let mut next = position.clone();
+            if self.variant.apply_move(&mut next, mv).is_err() {
+                continue;
+            }
+            self.count_node();
+            let score = match self.moves_or_score(&next, ply + 1) {
+                Ok(replies) => -self.quiescence(&next, ply + 1, -beta, -alpha, replies),
+                Err(score) => -score,
+            };
+            if score > alpha {
+                alpha = score;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        alpha
+    }
+}
+
+/// The position with the side to move passing, for null move pruning.
+fn pass(position: &Position) -> Position {
+    let mut passed = position.clone();
+    passed.side_to_move = passed.side_to_move.opposite();
+    passed.en_passant = None;
+    passed.halfmove_clock += 1;
+    passed
+}
+
+/// Whether the side to move has more than pawns and a king, without them zugzwang is common.
+fn has_pieces(position: &Position) -> bool {
+    position.board.pieces(position.side_to_move).iter().any(|piece| !matches!(piece.piece_type, Pawn | PieceType::King))
+}
+
+/// Mate scores count from the root, in the table they count from the position itself
+/// as it may be reached at another ply.
+fn score_to_table(score: i32, ply: u32) -> i32 {
+    if score > MATE_THRESHOLD {
+        score + ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: u32) -> i32 {
+    if score > MATE_THRESHOLD {
+        score - ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Ordering score of a capture or promotion: most valuable victim first, then least valuable
+/// attacker. Captures of a less valuable piece that lose material in the exchange go last.
+fn capture_score(position: &Position, mv: Move) -> Option<i32> {
+    let (from, to, promotion) = match mv {
+        Move::Normal { from, to, promotion } => (from, to, promotion),
+        Move::Drop { .. } => return None,
+    };
+    let promotion_value = promotion.map_or(0, |piece_type| piece_type.value());
+    if !is_capture(position, mv) {
+        return promotion.map(|_| GOOD_CAPTURE_SCORE + promotion_value);
+    }
+    let victim = position.board.get_piece_at(to).map_or(PieceType::Pawn.value(), |piece| piece.piece_type.value());
+    let attacker = position.board.get_piece_at(from).map_or(0, |piece| piece.piece_type.value());
+    if attacker > victim && static_exchange(&position.board, from, to).is_ok_and(|gain| gain < 0) {
+        return Some(BAD_CAPTURE_SCORE + victim - attacker / 100);
+    }
+    Some(GOOD_CAPTURE_SCORE + promotion_value + victim * 10 - attacker / 100)
+}
+
+/// A score for people: "+1.25" in pawns, or "#3" / "#-2" for mates.
+pub fn format_score(score: i32) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("#{}", moves),
+        None => format!("{:+.2}", score as f64 / 100.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Square;
+    use crate::moves::Move;
+    use crate::position::{Position, STANDARD_START_FEN};
+    use std::time::{Duration, Instant};
+
+    use crate::eval::EvalParams;
+    use crate::search::{capture_score, format_score, mate_in, search, search_with_options, SearchLimits, SearchOptions, MATE_SCORE};
+    use crate::variant::{KingOfTheHill, Standard};
+
+    fn normal(from: &str, to: &str) -> Move {
+        Move::new(Square::algebraic_to_coords(from).unwrap(), Square::algebraic_to_coords(to).unwrap())
+    }
+
+    fn best_move(fen: &str, depth: u32) -> Option<Move> {
+        search(&Standard, &Position::from_fen(fen).unwrap(), SearchLimits::depth(depth)).best_move
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = search(&Standard, &position, SearchLimits::depth(2));
+        assert_eq!(result.best_move, Some(normal("a1", "a8")));
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn takes_a_free_queen() {
+        assert_eq!(best_move("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 2), Some(normal("d1", "d5")));
+    }
+
+    #[test]
+    fn avoids_losing_the_queen() {
+        // Taking the defended pawn on d5 loses the queen to the pawn on e6
+        let result = best_move("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", 2);
+        assert_ne!(result, Some(normal("d1", "d5")));
+    }
+
+    #[test]
+    fn no_move_when_the_game_is_over() {
+        let mated = Position::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
+        let result = search(&Standard, &mated, SearchLimits::depth(2));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE_SCORE);
+    }
+
+    #[test]
+    fn search_follows_the_variant() {
+        let position = Position::from_fen("4k3/8/8/8/8/4K3/8/8 w - - 0 1").unwrap();
+        let result = search(&KingOfTheHill, &position, SearchLimits::depth(1));
+        assert!([normal("e3", "d4"), normal("e3", "e4")].contains(&result.best_move.unwrap()));
+        assert_eq!(result.mate_in(), Some(1));
+    }
+
+    #[test]
+    fn principal_variation_starts_with_best_move() {
+        let position = Position::from_fen(STANDARD_START_FEN).unwrap();
+        let result = search(&Standard, &position, SearchLimits::depth(2));
+        assert_eq!(result.pv.len(), 2);
+        assert_eq!(result.pv.first().copied(), result.best_move);
+        assert!(result.nodes > 20);
+    }
+
+    #[test]
+    fn node_limit_stops_the_search() {
+        let position = Position::from_fen(STANDARD_START_FEN).unwrap();
+        let result = search(&Standard, &position, SearchLimits::nodes(500));
+        assert!(result.best_move.is_some());
+        assert!(result.depth < 64);
+        assert!(result.nodes <= 500 || result.depth == 1);
+    }
+
+    #[test]
+    fn time_limit_stops_the_search() {
+        let position = Position::from_fen(STANDARD_START_FEN).unwrap();
+        let start = Instant::now();
+        let result = search(&Standard, &position, SearchLimits::time(Duration::from_millis(200)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn scores_for_people() {
+        assert_eq!(format_score(125), "+1.25");
+        assert_eq!(format_score(-40), "-0.40");
+        assert_eq!(format_score(MATE_SCORE - 5), "#3");
+        assert_eq!(format_score(-MATE_SCORE + 4), "#-2");
+    }
+
+    #[test]
+    fn mate_distances() {
+        assert_eq!(mate_in(MATE_SCORE - 1), Some(1));
+        assert_eq!(mate_in(MATE_SCORE - 3), Some(2));
+        assert_eq!(mate_in(-MATE_SCORE + 2), Some(-1));
+        assert_eq!(mate_in(150), None);
+    }
+
+    #[test]
+    fn search_uses_the_given_weights() {
+        let position = Position::from_fen("4k3/8/8/2n1b3/3P4/8/8/4K3 w - - 0 1").unwrap();
+        let mut options = SearchOptions::default();
+        options.eval.piece_values[1] = 1000;
+        let result = search_with_options(&Standard, &position, SearchLimits::depth(1), &options);
+        assert_eq!(result.best_move, Some(normal("d4", "c5")));
+        options.eval.piece_values[2] = 2000;
+        let result = search_with_options(&Standard, &position, SearchLimits::depth(1), &options);
+        assert_eq!(result.best_move, Some(normal("d4", "e5")));
+    }
+
+    #[test]
+    fn move_ordering_saves_nodes_without_changing_the_score() {
+        let position = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        let limits = SearchLimits::depth(3);
+        let unordered = search_with_options(&Standard, &position, limits, &SearchOptions::plain());
+        let ordering = SearchOptions { hash_move: true, mvv_lva: true, killers: true, history: true, ..SearchOptions::plain() };
+        let ordered = search_with_options(&Standard, &position, limits, &ordering);
+        assert_eq!(ordered.score, unordered.score);
+        assert!(ordered.nodes < unordered.nodes);
+    }
+
+    #[test]
+    fn captures_by_victim_then_attacker() {
+        let position = Position::from_fen("4k3/8/2p1q3/1p1P4/Q7/8/8/4K3 w - - 0 1").unwrap();
+        let pawn_takes_queen = capture_score(&position, normal("d5", "e6")).unwrap();
+        let pawn_takes_pawn = capture_score(&position, normal("d5", "c6")).unwrap();
+        let queen_takes_defended_pawn = capture_score(&position, normal("a4", "b5")).unwrap();
+        assert!(pawn_takes_queen > pawn_takes_pawn);
+        assert!(pawn_takes_pawn > 0 && queen_takes_defended_pawn < 0);
+        assert_eq!(capture_score(&position, normal("a4", "a5")), None);
+    }
+
+    #[test]
+    fn single_thread_search_is_deterministic() {
+        let position = Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let first = search(&Standard, &position, SearchLimits::depth(2));
+        assert_eq!(search(&Standard, &position, SearchLimits::depth(2)), first);
+    }
+
+    #[test]
+    fn threads_search_together() {
+        let options = SearchOptions { threads: 4, ..SearchOptions::default() };
+        let position = Position::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let result = search_with_options(&Standard, &position, SearchLimits::depth(3), &options);
+        assert_eq!(result.best_move, Some(normal("a1", "a8")));
+        assert_eq!(result.mate_in(), Some(1));
+
+        let position = Position::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let result = search_with_options(&Standard, &position, SearchLimits::nodes(2_000), &options);
+        assert_eq!(result.best_move, Some(normal("d1", "d5")));
+        assert!(result.nodes < 2_500);
+    }
+
+    #[test]
+    fn every_feature_can_be_switched() {
+        let plain = SearchOptions::plain();
+        let mut options = SearchOptions::default();
+        for feature in SearchOptions::FEATURES {
+            *options.feature_mut(feature).unwrap() = false;
+        }
+        assert_eq!(options, plain);
+        assert!(plain.eval == EvalParams::STANDARD && plain.threads == 1);
+        assert_eq!(options.feature_mut("tablebases"), None);
+    }
+
+    #[test]
+    fn mates_are_found_with_any_feature_switched_off() {
+        let position = Position::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        for feature in SearchOptions::FEATURES {
+            let mut options = SearchOptions::default();
+            *options.feature_mut(feature).unwrap() = false;
+            let result = search_with_options(&Standard, &position, SearchLimits::depth(4), &options);
+            assert_eq!(result.mate_in(), Some(2), "without {}", feature);
+        }
+    }
+
+    #[test]
+    fn pruning_saves_nodes() {
+        // Check extensions cost nodes of their own, in sharp endgames more than pruning saves
+        let position = Position::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let limits = SearchLimits::depth(4);
+        let ordering = SearchOptions { hash_move: true, mvv_lva: true, killers: true, history: true, ..SearchOptions::plain() };
+        let unpruned = search_with_options(&Standard, &position, limits, &ordering);
+        let pruned = search_with_options(&Standard, &position, limits, &SearchOptions::default());
+        assert!(pruned.nodes < unpruned.nodes, "{} vs {}", pruned.nodes, unpruned.nodes);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::attacks::attackers_of;
+use crate::board::{Chessboard, Square};
+use crate::error::ChessError;
+
+/// Static exchange evaluation of capturing on `to` with the piece on `from`, in centipawns
+/// from the capturing side's point of view.
+///
+/// Both sides keep recapturing with their least valuable attacker and may stop whenever
+/// continuing would lose material. Since every capturer leaves its square, sliders lined
+/// up behind it (x-rays) join the exchange. Pins are not taken into account.
+pub(crate) fn static_exchange(board: &Chessboard, from: Square, to: Square) -> Result<i32, ChessError> {
+    let mut board = board.clone();
+    let attacker = match board.get_piece_at(from) {
+        Some(piece) => piece,
+        None => return Err(ChessError::illegal_move("No piece on the starting square")),
+    };
+
+    let mut gains: Vec<i32> = vec![board.get_piece_at(to).map_or(0, |piece| piece.piece_type.value())];
+    let mut on_square_value = attacker.piece_type.value();
+    board.move_piece(from, to)?;
+    let mut side = attacker.color.opposite();
+
+    loop {
+        let least_valuable = attackers_of(&board, to, side)
+            .into_iter()
+            .min_by_key(|piece| piece.piece_type.value());
+        let recapturer = match least_valuable {
+            Some(piece) => piece,
+            None => break,
+        };
+        gains.push(on_square_value - gains[gains.len() - 1]);
+        on_square_value = recapturer.piece_type.value();
+        board.move_piece(recapturer.location, to)?;
+        side = side.opposite();
+    }
+
+    // Each side picks the better of stopping or continuing, starting from the end of the sequence
+    for index in (1..gains.len()).rev() {
+        gains[index - 1] = -(-gains[index - 1]).max(gains[index]);
+    }
+    Ok(gains[0])
+}
+
+/// A capture is safe when the exchange it starts does not lose material.
+pub fn is_capture_safe(board: &Chessboard, from: Square, to: Square) -> Result<bool, ChessError> {
+    Ok(static_exchange(board, from, to)? >= 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Chessboard, Square};
+    use crate::error::ChessError;
+    use crate::see::{is_capture_safe, static_exchange};
+
+    fn see(fen: &str, from: &str, to: &str) -> i32 {
+        let board = Chessboard::from_fen(fen).unwrap();
+        static_exchange(
+            &board,
+            Square::algebraic_to_coords(from).unwrap(),
+            Square::algebraic_to_coords(to).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn capturing_undefended_piece_wins_it() {
+        assert_eq!(see("8/8/8/3n4/4P3/8/8/8", "e4", "d5"), 320);
+    }
+
+    #[test]
+    fn capturing_defended_pawn_with_rook_loses_exchange() {
+        assert_eq!(see("8/8/3p4/4p3/8/8/8/4R3", "e1", "e5"), -400);
+    }
+
+    #[test]
+    fn recapture_stops_when_it_would_lose() {
+        // Qxd5 would be answered by Rxd5, so black keeps the queen and is just a knight down
+        assert_eq!(see("8/8/8/R2n4/8/8/6B1/3q4", "g2", "d5"), 320);
+        assert_eq!(see("8/8/8/3n4/8/8/6B1/3q4", "g2", "d5"), -10);
+    }
+
+    #[test]
+    fn least_valuable_attacker_recaptures_first() {
+        // Black can take back with queen or pawn, the pawn goes first and white stops
+        assert_eq!(see("8/8/2p5/3n4/8/8/6B1/3q4", "g2", "d5"), -10);
+    }
+
+    #[test]
+    fn x_ray_attackers_behind_sliders_join() {
+        assert_eq!(see("4r3/8/8/4p3/8/8/4R3/4R3", "e2", "e5"), 100);
+        assert_eq!(see("4r3/8/8/4p3/8/8/4R3/8", "e2", "e5"), -400);
+    }
+
+    #[test]
+    fn capture_safety() {
+        let board = Chessboard::from_fen("8/8/3p4/4p3/8/8/8/4R3").unwrap();
+        let e1 = Square::algebraic_to_coords("e1").unwrap();
+        let e5 = Square::algebraic_to_coords("e5").unwrap();
+        assert_eq!(is_capture_safe(&board, e1, e5), Ok(false));
+
+        let board = Chessboard::from_fen("8/8/8/4p3/8/8/8/4R3").unwrap();
+        assert_eq!(is_capture_safe(&board, e1, e5), Ok(true));
+    }
+
+    #[test]
+    fn exchange_from_empty_square() {
+        let board = Chessboard::from_fen("8/8/8/4p3/8/8/8/8").unwrap();
+        let e1 = Square::algebraic_to_coords("e1").unwrap();
+        let e5 = Square::algebraic_to_coords("e5").unwrap();
+        assert!(matches!(static_exchange(&board, e1, e5), Err(ChessError::IllegalMove { .. })));
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chess_piece::Color;
+use crate::error::ChessError;
+use crate::fairy::PieceRegistry;
+use crate::game::Game;
+use crate::moves::Move;
+use crate::position::{Position, STANDARD_START_FEN};
+use crate::search::{search, SearchLimits};
+use crate::variant::{variant_from_name, Outcome};
+
+const DEFAULT_ENGINE_DEPTH: u32 = 3;
+/// Deeper searches take long enough to make the server look hung
+const MAX_ENGINE_DEPTH: u32 = 6;
+/// Request bodies are small JSON objects, anything bigger is refused with 413
+const MAX_BODY_SIZE: usize = 64 * 1024;
+const MAX_LINE_SIZE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+/// Games kept at most by `Server::new`, the oldest is dropped to make room for a new one
+pub const DEFAULT_MAX_GAMES: usize = 1000;
+/// Requests are answered one at a time, so a client that stops sending or reading must not hold
+/// up the others
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// Always JSON, errors are `{"error": "..."}`
+    pub body: String,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Response {
+        let body = serde_json::to_string(value).expect("responses serialize to JSON");
+        Response { status, body }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, &ErrorBody { error: message.to_string() })
+    }
+
+    fn reason(&self) -> &str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            408 => "Request Timeout",
+            413 => "Payload Too Large",
+            _ => "Unknown",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+#[derive(Deserialize, Default)]
+struct NewGameRequest {
+    fen: Option<String>,
+    variant: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct MoveRequest {
+    #[serde(rename = "move")]
+    mv: String,
+}
+
+#[derive(Deserialize, Default)]
+struct EngineRequest {
+    depth: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct GameState {
+    id: usize,
+    variant: String,
+    start_fen: String,
+    /// The current position
+    fen: String,
+    moves: Vec<String>,
+    side_to_move: &'static str,
+    /// "white", "black" or "draw" once the game is over
+    outcome: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct LegalMoves {
+    moves: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct EngineReply {
+    #[serde(rename = "move")]
+    mv: String,
+    /// Centipawns for the side that moved
+    score: i32,
+    mate_in: Option<i32>,
+    pv: Vec<String>,
+    depth: u32,
+    nodes: u64,
+    game: GameState,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+/// Reads the request line, the headers and as much body as `Content-Length` announces. Requests
+/// that can not be answered come back as the error response to send instead.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, Response> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err(Response::error(400, "Malformed request line")),
+    };
+
+    let mut content_length = 0;
+    for headers in 0.. {
+        let header = read_line(reader)?;
+        if header.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(Response::error(400, "Too many headers"));
+        }
+        let (name, value) = header.split_once(':').ok_or(Response::error(400, "Malformed header"))?;
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse().map_err(|_| Response::error(400, "Invalid Content-Length"))?;
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        return Err(Response::error(413, &format!("The body may be at most {} bytes", MAX_BODY_SIZE)));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+    Ok(Request { method, path, body: String::from_utf8_lossy(&body).into_owned() })
+}
+
+/// One line without its line ending, at most `MAX_LINE_SIZE` long.
+fn read_line(reader: &mut impl BufRead) -> Result<String, Response> {
+    let mut line = String::new();
+    reader.by_ref().take(MAX_LINE_SIZE).read_line(&mut line).map_err(read_error)?;
+    if !line.ends_with('\n') {
+        return Err(Response::error(400, "Line too long or request cut off"));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+fn read_error(error: io::Error) -> Response {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => Response::error(408, "Timed out reading the request"),
+        _ => Response::error(400, "Could not read the request"),
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+/// Games kept in memory and the REST API over them:
+///
+/// * `POST /games` with an optional `{"fen": ..., "variant": ...}` creates a game
+/// * `GET /games/{id}` returns its state
+/// * `GET /games/{id}/moves` lists the legal moves in UCI notation
+/// * `POST /games/{id}/moves` with `{"move": "e2e4"}` plays a move
+/// * `POST /games/{id}/engine` with an optional `{"depth": 4}` lets the engine move
+/// * `DELETE /games/{id}` removes the game, returning its last state
+pub struct Server {
+    games: HashMap<usize, Game>,
+    next_id: usize,
+    max_games: usize,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::with_max_games(DEFAULT_MAX_GAMES)
+    }
+
+    /// A server keeping at most `max_games` games, at least one. Creating a game beyond that
+    /// drops the oldest.
+    pub fn with_max_games(max_games: usize) -> Server {
+        Server { games: HashMap::new(), next_id: 0, max_games: max_games.max(1) }
+    }
+
+    /// Answers requests one at a time, forever.
+    pub fn serve(&mut self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            match stream {
+                // A client that hangs up or sends garbage should not take the server down
+                Ok(stream) => {
+                    let _ = self.handle_connection(stream);
+                }
+                // Neither should a connection aborted before it was accepted or running out of file handles
+                Err(error) => eprintln!("Could not accept a connection: {}", error),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let response = match read_request(&mut BufReader::new(&stream)) {
+            Ok(request) => self.handle(&request.method, &request.path, &request.body),
+            Err(response) => response,
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.status,
+            response.reason(),
+            response.body.len(),
+            response.body
+        )?;
+        stream.flush()
+    }
+
+    /// Routes a request to the API, independent of the HTTP transport.
+    pub fn handle(&mut self, method: &str, path: &str, body: &str) -> Response {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("POST", ["games"]) => self.create_game(body),
+            (method, ["games", id, rest @ ..]) => {
+                let id = match id.parse::<usize>() {
+                    Ok(id) if self.games.contains_key(&id) => id,
+                    _ => return Response::error(404, "Unknown game"),
+                };
+                match (method, rest) {
+                    ("GET", []) => Response::json(200, &self.state(id)),
+                    ("GET", ["moves"]) => self.legal_moves(id),
+                    ("POST", ["moves"]) => self.play_move(id, body),
+                    ("POST", ["engine"]) => self.engine_move(id, body),
+                    ("DELETE", []) => {
+                        let state = self.state(id);
+                        self.games.remove(&id);
+                        Response::json(200, &state)
+                    }
+                    _ => Response::error(404, "Not found"),
+                }
+            }
+            _ => Response::error(404, "Not found"),
+        }
+    }
+
+    fn create_game(&mut self, body: &str) -> Response {
+        let request: NewGameRequest = match parse_body(body) {
+            Ok(request) => request,
+            Err(response) => return response,
+        };
+        let variant = match variant_from_name(request.variant.as_deref().unwrap_or("standard")) {
+            Some(variant) => variant,
+            None => return Response::error(400, "Unknown variant"),
+        };
+        let fen = request.fen.as_deref().unwrap_or(STANDARD_START_FEN);
+        let position = match Position::from_fen_with_registry(fen, Arc::new(PieceRegistry::with_common_fairies())) {
+            Ok(position) => position,
+            Err(error) => return Response::error(400, &error.to_string()),
+        };
+
+        if self.games.len() >= self.max_games {
+            // Ids only grow, so the smallest is the oldest game
+            if let Some(oldest) = self.games.keys().min().copied() {
+                self.games.remove(&oldest);
+            }
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.games.insert(id, Game::from_position(position, variant));
+        Response::json(201, &self.state(id))
+    }
+
+    fn legal_moves(&self, id: usize) -> Response {
+        let game = &self.games[&id];
+        let moves = game.legal_moves().iter().map(|mv| mv.to_uci(game.board())).collect();
+        Response::json(200, &LegalMoves { moves })
+    }
+
+    fn play_move(&mut self, id: usize, body: &str) -> Response {
+        let request: MoveRequest = match parse_body(body) {
+            Ok(request) => request,
+            Err(response) => return response,
+        };
+        let game = self.games.get_mut(&id).expect("checked by the router");
+        let played = Move::from_uci(game.board(), &request.mv).and_then(|mv| game.play(mv));
+        match played {
+            Ok(()) => Response::json(200, &self.state(id)),
+            Err(error) => Response::error(400, &error.to_string()),
+        }
+    }
+
+    fn engine_move(&mut self, id: usize, body: &str) -> Response {
+        let request: EngineRequest = match parse_body(body) {
+            Ok(request) => request,
+            Err(response) => return response,
+        };
+        let depth = request.depth.unwrap_or(DEFAULT_ENGINE_DEPTH);
+        if !(1..=MAX_ENGINE_DEPTH).contains(&depth) {
+            return Response::error(400, &format!("Depth must be between 1 and {}", MAX_ENGINE_DEPTH));
+        }
+
+        let game = self.games.get_mut(&id).expect("checked by the router");
+        if game.outcome().is_some() {
+            return Response::error(400, &ChessError::illegal_move("Game is already over").to_string());
+        }
+        let board = game.board().clone();
+        let result = search(game.variant(), game.position(), SearchLimits::depth(depth));
+        let best_move = match result.best_move {
+            Some(mv) => mv,
+            None => return Response::error(400, "The engine found no move"),
+        };
+        if let Err(error) = game.play(best_move) {
+            return Response::error(400, &error.to_string());
+        }
+
+        Response::json(
+            200,
+            &EngineReply {
+                mv: best_move.to_uci(&board),
+                score: result.score,
+                mate_in: result.mate_in(),
+                pv: result.pv.iter().map(|mv| mv.to_uci(&board)).collect(),
+                depth: result.depth,
+                nodes: result.nodes,
+                game: self.state(id),
+            },
+        )
+    }
+
+    fn state(&self, id: usize) -> GameState {
+        let game = &self.games[&id];
+        let record = game.record();
+        GameState {
+            id,
+            variant: game.variant().name().to_string(),
+            start_fen: record.fen,
+            fen: game.position().to_fen(),
+            moves: game.moves().iter().map(|mv| mv.to_uci(game.board())).collect(),
+            side_to_move: color_name(game.side_to_move()),
+            outcome: game.outcome().map(|outcome| match outcome {
+                Outcome::Win(color) => color_name(color),
+                Outcome::Draw => "draw",
+            }),
+        }
+    }
+}
+
+/// An empty body stands for `{}`, so requests without options need no body at all.
+fn parse_body<T: for<'de> Deserialize<'de> + Default>(body: &str) -> Result<T, Response> {
+    if body.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_json::from_str(body).map_err(|error| Response::error(400, &format!("Invalid JSON: {}", error)))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::server::{read_request, Server, MAX_BODY_SIZE};
+
+    fn json(body: &str) -> Value {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn creating_and_reading_a_game() {
+        let mut server = Server::new();
+        let created = server.handle("POST", "/games", "");
+        assert_eq!(created.status, 201);
+        assert_eq!(json(&created.body)["id"], 0);
+        assert_eq!(json(&created.body)["side_to_move"], "white");
+
+        let state = server.handle("GET", "/games/0", "");
+        assert_eq!(json(&state.body)["fen"], "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(server.handle("GET", "/games/7", "").status, 404);
+    }
+
+    #[test]
+    fn listing_and_playing_moves() {
+        let mut server = Server::new();
+        server.handle("POST", "/games", r#"{"variant": "king-of-the-hill"}"#);
+        let moves = json(&server.handle("GET", "/games/0/moves", "").body);
+        assert_eq!(moves["moves"].as_array().unwrap().len(), 20);
+
+        let played = server.handle("POST", "/games/0/moves", r#"{"move": "e2e4"}"#);
+        assert_eq!(played.status, 200);
+        assert_eq!(json(&played.body)["moves"], json(r#"["e2e4"]"#));
+        assert_eq!(json(&played.body)["variant"], "King of the Hill");
+
+        let illegal = server.handle("POST", "/games/0/moves", r#"{"move": "e2e4"}"#);
+        assert_eq!(illegal.status, 400);
+        assert_eq!(json(&illegal.body)["error"], "illegal move: Piece can not move there");
+    }
+
+    #[test]
+    fn bad_requests() {
+        let mut server = Server::new();
+        assert_eq!(server.handle("POST", "/games", r#"{"variant": "bughouse"}"#).status, 400);
+        assert_eq!(server.handle("POST", "/games", r#"{"fen": "8/8/3x4"}"#).status, 400);
+        assert_eq!(server.handle("POST", "/games", "{").status, 400);
+        assert_eq!(server.handle("DELETE", "/games", "").status, 404);
+    }
+
+    #[test]
+    fn deleting_and_limiting_games() {
+        let mut server = Server::with_max_games(2);
+        for _ in 0..3 {
+            assert_eq!(server.handle("POST", "/games", "").status, 201);
+        }
+        assert_eq!(server.handle("GET", "/games/0", "").status, 404);
+        assert_eq!(server.handle("GET", "/games/1", "").status, 200);
+
+        let deleted = server.handle("DELETE", "/games/1", "");
+        assert_eq!(deleted.status, 200);
+        assert_eq!(json(&deleted.body)["id"], 1);
+        assert_eq!(server.handle("GET", "/games/1", "").status, 404);
+        assert_eq!(server.handle("DELETE", "/games/1", "").status, 404);
+        assert_eq!(server.handle("GET", "/games/2", "").status, 200);
+    }
+
+    #[test]
+    fn reading_http_requests() {
+        let mut request = "POST /games HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\n{}".as_bytes();
+        let request = read_request(&mut request).ok().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), request.body.as_str()), ("POST", "/games", "{}"));
+
+        let status = |request: &str| read_request(&mut request.as_bytes()).err().unwrap().status;
+        let huge = format!("POST /games HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_SIZE + 1);
+        assert_eq!(status(&huge), 413);
+        assert_eq!(status("POST /games HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n"), 400);
+        assert_eq!(status("POST /games HTTP/1.1\r\nContent-Length: lots\r\n\r\n"), 400);
+        assert_eq!(status("POST /games HTTP/1.1\r\nno colon\r\n\r\n"), 400);
+        assert_eq!(status("POST /games HTTP/1.1\r\nContent-Length: 5\r\n\r\n{}"), 400);
+        assert_eq!(status("GET\r\n\r\n"), 400);
+        assert_eq!(status(&format!("GET /games/0 HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(101))), 400);
+        assert_eq!(status(&format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(10_000))), 400);
+    }
+
+    #[test]
+    fn engine_reply() {
+        let mut server = Server::new();
+        server.handle("POST", "/games", r#"{"fen": "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"}"#);
+        let reply = json(&server.handle("POST", "/games/0/engine", r#"{"depth": 2}"#).body);
+        assert_eq!(reply["move"], "a1a8");
+        assert_eq!(reply["mate_in"], 1);
+        assert_eq!(reply["game"]["outcome"], "white");
+
+        let over = server.handle("POST", "/games/0/engine", "");
+        assert_eq!(over.status, 400);
+        assert_eq!(server.handle("POST", "/games/0/engine", r#"{"depth": 40}"#).status, 400);
+    }
+}
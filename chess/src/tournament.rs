@@ -0,0 +1,374 @@
+use crate::chess_piece::Color;
+use crate::elo::{MatchScore, Sprt, SprtResult};
+use crate::error::ChessError;
+use crate::game::{Game, GameRecord};
+use crate::moves::Move;
+use crate::position::{Position, STANDARD_START_FEN};
+use crate::search::{search_with_options, SearchLimits, SearchOptions};
+use crate::uci::UciEngine;
+use crate::variant::Outcome;
+
+/// Anything that can pick moves in a game: the built-in engine, an external one, a person...
+pub trait Player {
+    fn name(&self) -> String;
+
+    fn new_game(&mut self) -> Result<(), ChessError> {
+        Ok(())
+    }
+
+    /// The move to play in the current position, with the player's score for it in centipawns
+    /// from its own point of view if it has one.
+    fn choose_move(&mut self, game: &Game) -> Result<(Move, Option<i32>), ChessError>;
+}
+
+/// The built-in search with fixed limits.
+pub struct EnginePlayer {
+    pub name: String,
+    pub limits: SearchLimits,
+    pub options: SearchOptions,
+}
+
+impl EnginePlayer {
+    /// The engine with the default search options.
+    pub fn new(name: &str, limits: SearchLimits) -> EnginePlayer {
+        EnginePlayer { name: name.to_string(), limits, options: SearchOptions::default() }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Result<(Move, Option<i32>), ChessError> {
+        let result = search_with_options(game.variant(), game.position(), self.limits, &self.options);
+        match result.best_move {
+            Some(mv) => Ok((mv, Some(result.score))),
+            None => Err(ChessError::engine("No move in a finished game")),
+        }
+    }
+}
+
+/// An external engine speaking UCI, searching with fixed limits.
+pub struct UciPlayer {
+    pub engine: UciEngine,
+    pub limits: SearchLimits,
+}
+
+impl Player for UciPlayer {
+    fn name(&self) -> String {
+        self.engine.name.clone()
+    }
+
+    fn new_game(&mut self) -> Result<(), ChessError> {
+        self.engine.new_game()
+    }
+
+    fn choose_move(&mut self, game: &Game) -> Result<(Move, Option<i32>), ChessError> {
+        let board = game.board();
+        let moves: Vec<String> = game.moves().iter().map(|mv| mv.to_uci(board)).collect();
+        let reply = self.engine.go(&game.record().fen, &moves, self.limits)?;
+        Ok((Move::from_uci(board, &reply.best_move)?, reply.score))
+    }
+}
+
+/// When to end games early based on the scores the players report, to save time on
+/// games whose result is already clear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjudication {
+    /// Games still going after this many plies are drawn
+    pub max_plies: usize,
+    /// The game is won when both players agree one side is at least this far ahead...
+    pub win_score: i32,
+    /// ...for this many plies in a row
+    pub win_plies: usize,
+    /// From this ply on the game is drawn when both players see it within `draw_score`...
+    pub draw_from_ply: usize,
+    pub draw_score: i32,
+    /// ...for this many plies in a row
+    pub draw_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication { max_plies: 400, win_score: 1000, win_plies: 4, draw_from_ply: 80, draw_score: 10, draw_plies: 12 }
+    }
+}
+
+impl Adjudication {
+    /// Checks the scores of the moves so far, each from white's point of view.
+    fn adjudicate(&self, white_scores: &[Option<i32>]) -> Option<Outcome> {
+        let plies = white_scores.len();
+        let last = |count: usize| -> Option<Vec<i32>> {
+            if count == 0 || plies < count {
+                return None;
+            }
+            white_scores[plies - count..].iter().copied().collect()
+        };
+
+        if let Some(scores) = last(self.win_plies) {
+            if scores.iter().all(|score| *score >= self.win_score) {
+                return Some(Outcome::Win(Color::White));
+            }
+            if scores.iter().all(|score| *score <= -self.win_score) {
+                return Some(Outcome::Win(Color::Black));
+            }
+        }
+        if plies >= self.draw_from_ply {
+            if let Some(scores) = last(self.draw_plies) {
+                if scores.iter().all(|score| score.abs() <= self.draw_score) {
+                    return Some(Outcome::Draw);
+                }
+            }
+        }
+        if plies >= self.max_plies {
+            return Some(Outcome::Draw);
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// Checkmate, stalemate, repetition or any other rule of the game
+    Rules,
+    Adjudication,
+    /// The loser played an illegal move
+    IllegalMove,
+    /// The loser's engine crashed, stopped answering or broke the protocol
+    EngineFailure,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSummary {
+    pub white: String,
+    pub black: String,
+    pub outcome: Outcome,
+    pub termination: Termination,
+    pub record: GameRecord,
+}
+
+/// Plays one game of standard chess from the opening position.
+pub fn play_game(
+    white: &mut dyn Player,
+    black: &mut dyn Player,
+    opening: &str,
+    adjudication: &Adjudication,
+) -> Result<GameSummary, ChessError> {
+    let mut game = Game::from_fen(opening)?;
+    // A player that can not start a game, like an engine killed for hanging, loses it
+    let not_ready = match (white.new_game(), black.new_game()) {
+        (Err(_), _) => Some(Color::White),
+        (_, Err(_)) => Some(Color::Black),
+        _ => None,
+    };
+    let mut white_scores: Vec<Option<i32>> = vec![];
+
+    let (outcome, termination) = loop {
+        if let Some(color) = not_ready {
+            break (Outcome::Win(color.opposite()), Termination::EngineFailure);
+        }
+        if let Some(outcome) = game.outcome() {
+            break (outcome, Termination::Rules);
+        }
+        if let Some(outcome) = adjudication.adjudicate(&white_scores) {
+            break (outcome, Termination::Adjudication);
+        }
+
+        let mover = game.side_to_move();
+        let player: &mut dyn Player = match mover {
+            Color::White => &mut *white,
+            Color::Black => &mut *black,
+        };
+        let (mv, score) = match player.choose_move(&game) {
+            Ok(choice) => choice,
+            Err(_) => break (Outcome::Win(mover.opposite()), Termination::EngineFailure),
+        };
+        if game.play(mv).is_err() {
+            break (Outcome::Win(mover.opposite()), Termination::IllegalMove);
+        }
+        white_scores.push(score.map(|score| match mover {
+            Color::White => score,
+            Color::Black => -score,
+        }));
+    };
+
+    Ok(GameSummary { white: white.name(), black: black.name(), outcome, termination, record: game.record() })
+}
+
+/// A match between two players: every opening is played twice with colors swapped,
+/// going round the openings until enough games are played or the SPRT reaches a verdict.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tournament {
+    pub games: usize,
+    /// Starting positions as FEN, the standard start position when empty
+    pub openings: Vec<String>,
+    pub adjudication: Adjudication,
+    pub sprt: Option<Sprt>,
+}
+
+impl Tournament {
+    pub fn new(games: usize) -> Tournament {
+        Tournament { games, openings: vec![], adjudication: Adjudication::default(), sprt: None }
+    }
+
+    /// Runs the match and returns the score from `first`'s point of view. `on_game` is
+    /// called after every game with its summary and the score so far.
+    pub fn run(
+        &self,
+        first: &mut dyn Player,
+        second: &mut dyn Player,
+        mut on_game: impl FnMut(&GameSummary, &MatchScore),
+    ) -> Result<MatchScore, ChessError> {
+        let mut score = MatchScore::default();
+        for index in 0..self.games {
+            let opening = match self.openings.len() {
+                0 => STANDARD_START_FEN,
+                count => &self.openings[(index / 2) % count],
+            };
+            let first_color = if index % 2 == 0 { Color::White } else { Color::Black };
+            let summary = match first_color {
+                Color::White => play_game(first, second, opening, &self.adjudication)?,
+                Color::Black => play_game(second, first, opening, &self.adjudication)?,
+            };
+            match summary.outcome {
+                Outcome::Draw => score.draws += 1,
+                Outcome::Win(color) if color == first_color => score.wins += 1,
+                Outcome::Win(_) => score.losses += 1,
+            }
+            on_game(&summary, &score);
+
+            if let Some(sprt) = self.sprt {
+                if sprt.test(&score) != SprtResult::Continue {
+                    break;
+                }
+            }
+        }
+        Ok(score)
+    }
+}
+
+/// Reads an openings file: one FEN per line, blank lines and lines starting with '#' are skipped.
+pub fn load_openings(text: &str) -> Result<Vec<String>, ChessError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|fen| Position::from_fen(fen).map(|_| fen.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Square;
+    use crate::chess_piece::Color;
+    use crate::elo::Sprt;
+    use crate::error::ChessError;
+    use crate::game::Game;
+    use crate::moves::Move;
+    use crate::search::SearchLimits;
+    use crate::tournament::{load_openings, play_game, Adjudication, EnginePlayer, Player, Termination, Tournament};
+    use crate::variant::Outcome;
+
+    /// Always plays the first legal move and claims the given score
+    struct FirstMove {
+        score: Option<i32>,
+    }
+
+    impl Player for FirstMove {
+        fn name(&self) -> String {
+            "first move".to_string()
+        }
+
+        fn choose_move(&mut self, game: &Game) -> Result<(Move, Option<i32>), ChessError> {
+            Ok((game.legal_moves()[0], self.score))
+        }
+    }
+
+    struct Cheater;
+
+    impl Player for Cheater {
+        fn name(&self) -> String {
+            "cheater".to_string()
+        }
+
+        fn choose_move(&mut self, _game: &Game) -> Result<(Move, Option<i32>), ChessError> {
+            let square = |algebraic| Square::algebraic_to_coords(algebraic).unwrap();
+            Ok((Move::new(square("e2"), square("e6")), None))
+        }
+    }
+
+    fn engine(depth: u32) -> EnginePlayer {
+        EnginePlayer::new(&format!("depth {}", depth), SearchLimits::depth(depth))
+    }
+
+    #[test]
+    fn engine_mates_from_a_won_position() {
+        let summary = play_game(&mut engine(2), &mut engine(1), "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &Adjudication::default()).unwrap();
+        assert_eq!(summary.outcome, Outcome::Win(Color::White));
+        assert_eq!(summary.termination, Termination::Rules);
+        assert_eq!(summary.record.moves, vec!["a1a8"]);
+    }
+
+    #[test]
+    fn illegal_moves_lose() {
+        let summary = play_game(&mut Cheater, &mut engine(1), crate::position::STANDARD_START_FEN, &Adjudication::default()).unwrap();
+        assert_eq!(summary.outcome, Outcome::Win(Color::Black));
+        assert_eq!(summary.termination, Termination::IllegalMove);
+    }
+
+    #[test]
+    fn adjudicating_wins_and_draws() {
+        let adjudication = Adjudication { win_plies: 2, ..Adjudication::default() };
+        let summary =
+            play_game(&mut FirstMove { score: Some(1500) }, &mut FirstMove { score: Some(-1500) }, crate::position::STANDARD_START_FEN, &adjudication)
+                .unwrap();
+        assert_eq!((summary.outcome, summary.termination), (Outcome::Win(Color::White), Termination::Adjudication));
+        assert_eq!(summary.record.moves.len(), 2);
+
+        let adjudication = Adjudication { draw_from_ply: 4, draw_plies: 4, ..Adjudication::default() };
+        let summary =
+            play_game(&mut FirstMove { score: Some(0) }, &mut FirstMove { score: Some(0) }, crate::position::STANDARD_START_FEN, &adjudication).unwrap();
+        assert_eq!((summary.outcome, summary.termination), (Outcome::Draw, Termination::Adjudication));
+        assert_eq!(summary.record.moves.len(), 4);
+
+        let adjudication = Adjudication { max_plies: 6, ..Adjudication::default() };
+        let summary = play_game(&mut FirstMove { score: None }, &mut FirstMove { score: None }, crate::position::STANDARD_START_FEN, &adjudication).unwrap();
+        assert_eq!(summary.record.moves.len(), 6);
+        assert_eq!(summary.outcome, Outcome::Draw);
+    }
+
+    #[test]
+    fn colors_alternate_and_openings_repeat() {
+        let tournament = Tournament {
+            openings: vec!["6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()],
+            ..Tournament::new(4)
+        };
+        let mut whites = vec![];
+        let score = tournament
+            .run(&mut engine(2), &mut FirstMove { score: None }, |summary, _| whites.push(summary.white.clone()))
+            .unwrap();
+        assert_eq!(whites, vec!["depth 2", "first move", "depth 2", "first move"]);
+        assert_eq!(score.wins, 2);
+        assert_eq!(score.games(), 4);
+    }
+
+    #[test]
+    fn sprt_can_stop_a_match_early() {
+        let tournament = Tournament {
+            openings: vec!["6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string(), "r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1".to_string()],
+            sprt: Some(Sprt::new(0.0, 400.0)),
+            ..Tournament::new(1000)
+        };
+        let score = tournament.run(&mut engine(2), &mut FirstMove { score: None }, |_, _| {}).unwrap();
+        assert!(score.games() < 1000);
+        assert_eq!(score.losses, 0);
+        assert!(score.wins > score.draws);
+    }
+
+    #[test]
+    fn reading_openings() {
+        let openings = load_openings("# Italian\nr1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R b KQkq - 3 3\n\n").unwrap();
+        assert_eq!(openings.len(), 1);
+        assert!(load_openings("not a fen").is_err());
+    }
+}
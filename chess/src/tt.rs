@@ -0,0 +1,230 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::board::Square;
+use crate::chess_piece::PieceType;
+use crate::moves::Move;
+
+/// Entries in a table, a power of two so the key can be masked into an index
+pub const DEFAULT_ENTRIES: usize = 1 << 18;
+
+/// How a stored score relates to the real one, alpha-beta only proves bounds when it cuts off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The real score is at least this high, the search failed high
+    Lower,
+    /// The real score is at most this high, no move reached alpha
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+/// What the search found out about positions, by their `Position::hash_key`. It is shared
+/// between search threads without locks: each slot holds the entry packed into one word and
+/// the key xor-ed with it in another, so an entry torn by two threads writing at once no
+/// longer matches its key and is ignored. Deeper results replace shallower ones in a slot.
+pub struct TranspositionTable {
+    slots: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    pub fn new(entries: usize) -> TranspositionTable {
+        let slots = (0..entries.next_power_of_two()).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect();
+        TranspositionTable { slots }
+    }
+
+    fn slot(&self, key: u64) -> &[AtomicU64; 2] {
+        &self.slots[key as usize & (self.slots.len() - 1)]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.probe_slot(key).filter(|(stored_key, _)| *stored_key == key).map(|(_, entry)| entry)
+    }
+
+    pub fn store(&self, key: u64, entry: Entry) {
+        let data = match pack(entry) {
+            Some(data) => data,
+            None => return,
+        };
+        if let Some((stored_key, stored)) = self.probe_slot(key) {
+            if stored_key != key && stored.depth > entry.depth {
+                return;
+            }
+        }
+        let [checked_key, stored] = self.slot(key);
+        checked_key.store(key ^ data, Ordering::Relaxed);
+        stored.store(data, Ordering::Relaxed);
+    }
+
+    /// Whatever entry is in the key's slot, with the key it belongs to.
+    fn probe_slot(&self, key: u64) -> Option<(u64, Entry)> {
+        let [checked_key, data] = self.slot(key);
+        let data = data.load(Ordering::Relaxed);
+        if data & OCCUPIED == 0 {
+            return None;
+        }
+        unpack(data).map(|entry| (checked_key.load(Ordering::Relaxed) ^ data, entry))
+    }
+}
+
+// Layout of an entry: move in bits 0-28, depth 29-35, bound 36-37, score 38-53
+const OCCUPIED: u64 = 1 << 63;
+const MOVE_BITS: u64 = (1 << 29) - 1;
+/// Squares are stored in 5 bits per coordinate, moves on larger boards are not stored
+const MAX_COORDINATE: usize = 31;
+
+fn pack(entry: Entry) -> Option<u64> {
+    let mv = match entry.best_move {
+        Some(mv) => pack_move(mv)?,
+        None => 0,
+    };
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    let score = entry.score as i16 as u16 as u64;
+    Some(OCCUPIED | mv | (entry.depth.min(127) as u64) << 29 | bound << 36 | score << 38)
+}
+
+fn unpack(data: u64) -> Option<Entry> {
+    let bound = match (data >> 36) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        2 => Bound::Upper,
+        _ => return None,
+    };
+    let best_move = match data & MOVE_BITS {
+        0 => None,
+        mv => Some(unpack_move(mv)?),
+    };
+    Some(Entry { depth: ((data >> 29) & 127) as u32, score: ((data >> 38) & 0xffff) as u16 as i16 as i32, bound, best_move })
+}
+
+// A move is its kind (1 normal, 2 drop) in 2 bits, two squares in 10 bits each and a piece in 7 bits
+fn pack_move(mv: Move) -> Option<u64> {
+    let square = |square: Square| -> Option<u64> {
+        (square.x <= MAX_COORDINATE && square.y <= MAX_COORDINATE).then_some((square.x as u64) | (square.y as u64) << 5)
+    };
+    let (kind, from, to, piece) = match mv {
+        Move::Normal { from, to, promotion } => (1, square(from)?, square(to)?, promotion.map_or(Some(0), pack_piece)?),
+        Move::Drop { piece_type, to } => (2, 0, square(to)?, pack_piece(piece_type)?),
+    };
+    Some(kind | from << 2 | to << 12 | piece << 22)
+}
+
+fn unpack_move(packed: u64) -> Option<Move> {
+    let square = |bits: u64| Square { x: (bits & 31) as usize, y: ((bits >> 5) & 31) as usize };
+    let to = square(packed >> 12);
+    let piece = unpack_piece(packed >> 22 & 127);
+    match packed & 3 {
+        1 => Some(Move::Normal { from: square(packed >> 2), to, promotion: piece }),
+        2 => Some(Move::Drop { piece_type: piece?, to }),
+        _ => None,
+    }
+}
+
+fn pack_piece(piece_type: PieceType) -> Option<u64> {
+    match piece_type {
+        PieceType::Pawn => Some(1),
+        PieceType::Knight => Some(2),
+        PieceType::Bishop => Some(3),
+        PieceType::Rook => Some(4),
+        PieceType::Queen => Some(5),
+        PieceType::King => Some(6),
+        PieceType::Fairy(symbol) if symbol.is_ascii_uppercase() => Some(symbol as u64),
+        PieceType::Fairy(_) => None,
+    }
+}
+
+fn unpack_piece(packed: u64) -> Option<PieceType> {
+    match packed {
+        0 => None,
+        1 => Some(PieceType::Pawn),
+        2 => Some(PieceType::Knight),
+        3 => Some(PieceType::Bishop),
+        4 => Some(PieceType::Rook),
+        5 => Some(PieceType::Queen),
+        6 => Some(PieceType::King),
+        symbol => Some(PieceType::Fairy(symbol as u8 as char)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::board::Square;
+    use crate::chess_piece::PieceType;
+    use crate::moves::Move;
+    use crate::tt::{Bound, Entry, TranspositionTable};
+
+    fn mv(from: &str, to: &str) -> Move {
+        Move::new(Square::algebraic_to_coords(from).unwrap(), Square::algebraic_to_coords(to).unwrap())
+    }
+
+    fn entry(depth: u32, score: i32, best_move: Option<Move>) -> Entry {
+        Entry { depth, score, bound: Bound::Exact, best_move }
+    }
+
+    #[test]
+    fn storing_and_finding_entries() {
+        let table = TranspositionTable::new(16);
+        table.store(3, entry(2, 35, Some(mv("e2", "e4"))));
+        assert_eq!(table.probe(3), Some(entry(2, 35, Some(mv("e2", "e4")))));
+        assert_eq!(table.probe(19), None);
+        assert_eq!(table.probe(0), None);
+        table.store(3, Entry { depth: 1, score: -29_990, bound: Bound::Upper, best_move: None });
+        assert_eq!(table.probe(3), Some(Entry { depth: 1, score: -29_990, bound: Bound::Upper, best_move: None }));
+    }
+
+    #[test]
+    fn deeper_entries_are_kept() {
+        let table = TranspositionTable::new(16);
+        table.store(3, entry(4, 0, None));
+        table.store(19, entry(2, 0, None));
+        assert_eq!(table.probe(3), Some(entry(4, 0, None)));
+        table.store(19, entry(4, 0, None));
+        assert_eq!(table.probe(19), Some(entry(4, 0, None)));
+    }
+
+    #[test]
+    fn all_kinds_of_moves_fit() {
+        let table = TranspositionTable::new(16);
+        let moves = [
+            Move::with_promotion(Square { x: 0, y: 1 }, Square { x: 0, y: 0 }, PieceType::Queen),
+            Move::Drop { piece_type: PieceType::Knight, to: Square { x: 5, y: 2 } },
+            Move::Drop { piece_type: PieceType::Fairy('A'), to: Square { x: 9, y: 7 } },
+        ];
+        for (key, mv) in moves.into_iter().enumerate() {
+            table.store(key as u64, entry(1, 0, Some(mv)));
+            assert_eq!(table.probe(key as u64).and_then(|entry| entry.best_move), Some(mv));
+        }
+        table.store(5, entry(1, 0, Some(Move::new(Square { x: 40, y: 0 }, Square { x: 0, y: 0 }))));
+        assert_eq!(table.probe(5), None);
+    }
+
+    #[test]
+    fn threads_share_the_table() {
+        let table = TranspositionTable::new(1024);
+        thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let table = &table;
+                scope.spawn(move || {
+                    for key in 0..256 {
+                        table.store(key * 4 + thread, entry(1, thread as i32, None));
+                    }
+                });
+            }
+        });
+        for key in 0..1024 {
+            assert_eq!(table.probe(key).map(|entry| entry.score), Some((key % 4) as i32));
+        }
+    }
+}
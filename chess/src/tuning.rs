@@ -0,0 +1,273 @@
+use crate::chess_piece::{Color, PieceType};
+use crate::error::ChessError;
+use crate::eval::{table_slot, table_square, EvalParams};
+use crate::position::Position;
+
+/// The six piece values followed by the six tables
+const WEIGHTS: usize = 6 + 6 * 64;
+/// Both sides always have a king, so its value says nothing and is left alone
+const KING_VALUE: usize = 5;
+
+/// A position and the result of the game it was taken from: 1 when white won, 0.5 for a draw
+/// and 0 when black won.
+#[derive(Debug, Clone)]
+pub struct LabeledPosition {
+    pub position: Position,
+    pub result: f64,
+}
+
+/// Reads one position per line, a FEN followed by the result as "1-0", "0-1" or "1/2-1/2", or as
+/// 1.0, 0.5 and 0.0. Quotes, brackets, semicolons and a "c9" opcode around the result are allowed,
+/// so both `<fen> [0.5]` files and EPD like `<fen> c9 "1/2-1/2";` can be used. Lines starting
+/// with `#` are comments.
+pub fn load_labeled_positions(text: &str) -> Result<Vec<LabeledPosition>, ChessError> {
+    let mut positions = vec![];
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        let result = tokens
+            .pop()
+            .and_then(parse_result)
+            .ok_or_else(|| ChessError::invalid_notation(line, "Expected a game result at the end"))?;
+        if tokens.last() == Some(&"c9") {
+            tokens.pop();
+        }
+        let position = Position::from_fen(&tokens.join(" "))?;
+        positions.push(LabeledPosition { position, result });
+    }
+    Ok(positions)
+}
+
+fn parse_result(token: &str) -> Option<f64> {
+    match token.trim_matches(|c| matches!(c, '"' | '[' | ']' | ';')) {
+        "1-0" | "1.0" => Some(1.0),
+        "1/2-1/2" | "0.5" => Some(0.5),
+        "0-1" | "0.0" => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Expected score for white from an evaluation, `k` scales centipawns to winning chances.
+fn win_probability(eval: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+fn weights(params: &EvalParams) -> Vec<i32> {
+    params.piece_values.iter().chain(params.tables.iter().flatten()).copied().collect()
+}
+
+fn params_from_weights(weights: &[i32]) -> EvalParams {
+    let mut params = EvalParams::STANDARD;
+    params.piece_values.copy_from_slice(&weights[..6]);
+    for (table, values) in params.tables.iter_mut().zip(weights[6..].chunks(64)) {
+        table.copy_from_slice(values);
+    }
+    params
+}
+
+/// Tunes the evaluation weights with the Texel method: the evaluation of each position is turned
+/// into an expected result and each pass moves every weight by one centipawn in whichever
+/// direction lowers the mean squared error against the real results. The positions should be
+/// quiet, as the static evaluation is all that is looked at.
+pub struct Tuner {
+    results: Vec<f64>,
+    /// Evaluation of each position that no weight covers, like fairy piece values
+    constants: Vec<i32>,
+    /// Per position, how often each weight counts, white's pieces minus black's
+    features: Vec<Vec<(usize, i32)>>,
+    /// Per weight, the positions it counts in with how often
+    positions_by_weight: Vec<Vec<(usize, i32)>>,
+}
+
+impl Tuner {
+    pub fn new(positions: &[LabeledPosition]) -> Tuner {
+        let mut tuner = Tuner {
+            results: vec![],
+            constants: vec![],
+            features: vec![],
+            positions_by_weight: vec![vec![]; WEIGHTS],
+        };
+        for (index, labeled) in positions.iter().enumerate() {
+            let (constant, features) = features(&labeled.position);
+            for &(weight, count) in &features {
+                tuner.positions_by_weight[weight].push((index, count));
+            }
+            tuner.results.push(labeled.result);
+            tuner.constants.push(constant);
+            tuner.features.push(features);
+        }
+        tuner
+    }
+
+    fn evaluations(&self, weights: &[i32]) -> Vec<i32> {
+        self.features
+            .iter()
+            .zip(&self.constants)
+            .map(|(features, constant)| constant + features.iter().map(|&(weight, count)| weights[weight] * count).sum::<i32>())
+            .collect()
+    }
+
+    fn mean_error(&self, evaluations: &[i32], k: f64) -> f64 {
+        let total: f64 = evaluations
+            .iter()
+            .zip(&self.results)
+            .map(|(eval, result)| (result - win_probability(*eval, k)).powi(2))
+            .sum();
+        total / self.results.len().max(1) as f64
+    }
+
+    /// Mean squared difference between the results and what the evaluation predicts.
+    pub fn error(&self, params: &EvalParams, k: f64) -> f64 {
+        self.mean_error(&self.evaluations(&weights(params)), k)
+    }
+
+    /// The scaling that fits the results best for these weights, found to two decimals.
+    pub fn best_k(&self, params: &EvalParams) -> f64 {
+        let evaluations = self.evaluations(&weights(params));
+        let mut best = 1.0;
+        let mut step = 0.1;
+        for _ in 0..2 {
+            let candidates = (-10..=10).map(|offset| best + offset as f64 * step).filter(|k| *k > 0.0);
+            best = candidates
+                .map(|k| (k, self.mean_error(&evaluations, k)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map_or(best, |(k, _)| k);
+            step /= 10.0;
+        }
+        best
+    }
+
+    /// Runs up to `passes` passes over all weights, starting from `params`, and returns the tuned
+    /// weights. `on_pass` gets the pass number and the error after it. Stops early once a pass
+    /// changes nothing.
+    pub fn tune(&self, params: &EvalParams, k: f64, passes: usize, mut on_pass: impl FnMut(usize, f64)) -> EvalParams {
+        let mut weights = weights(params);
+        let mut evaluations = self.evaluations(&weights);
+        for pass in 1..=passes {
+            let mut improved = false;
+            for weight in (0..WEIGHTS).filter(|weight| *weight != KING_VALUE) {
+                if let Some(step) = [1, -1].into_iter().find(|step| self.error_change(weight, *step, &evaluations, k) < 0.0) {
+                    weights[weight] += step;
+                    for &(position, count) in &self.positions_by_weight[weight] {
+                        evaluations[position] += count * step;
+                    }
+                    improved = true;
+                }
+            }
+            on_pass(pass, self.mean_error(&evaluations, k));
+            if !improved {
+                break;
+            }
+        }
+        params_from_weights(&weights)
+    }
+
+    /// How the summed error changes when `weight` moves by `step`, only the positions it counts in matter.
+    fn error_change(&self, weight: usize, step: i32, evaluations: &[i32], k: f64) -> f64 {
+        self.positions_by_weight[weight]
+            .iter()
+            .map(|&(position, count)| {
+                let result = self.results[position];
+                let eval = evaluations[position];
+                (result - win_probability(eval + count * step, k)).powi(2) - (result - win_probability(eval, k)).powi(2)
+            })
+            .sum()
+    }
+}
+
+/// Splits the evaluation of a position into the weights it uses, see `EvalParams::evaluate_white`.
+fn features(position: &Position) -> (i32, Vec<(usize, i32)>) {
+    let mut constant = 0;
+    let mut counts = vec![0; WEIGHTS];
+    for color in [Color::White, Color::Black] {
+        let sign = match color {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+        for piece in position.board.pieces(color) {
+            let slot = table_slot(piece.piece_type);
+            match piece.piece_type {
+                PieceType::Fairy(_) => constant += sign * piece.piece_type.value(),
+                _ => counts[slot] += sign,
+            }
+            counts[6 + slot * 64 + table_square(&position.board, piece)] += sign;
+        }
+        for piece_type in position.pocket(color) {
+            match piece_type {
+                PieceType::Fairy(_) => constant += sign * piece_type.value(),
+                _ => counts[table_slot(*piece_type)] += sign,
+            }
+        }
+    }
+    let features = counts.into_iter().enumerate().filter(|(_, count)| *count != 0).collect();
+    (constant, features)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::EvalParams;
+    use crate::position::Position;
+    use crate::tuning::{load_labeled_positions, LabeledPosition, Tuner};
+
+    fn labeled(fen: &str, result: f64) -> LabeledPosition {
+        LabeledPosition { position: Position::from_fen(fen).unwrap(), result }
+    }
+
+    #[test]
+    fn reading_labeled_positions() {
+        let text = "# quiet positions\n\
+                    4k3/8/8/8/8/8/8/3QK3 w - - 0 1 [1.0]\n\
+                    4k3/8/8/8/8/8/8/4K3 b - - c9 \"1/2-1/2\";\n\
+                    \n\
+                    3qk3/8/8/8/8/8/8/4K3 w - - 0-1\n";
+        let positions = load_labeled_positions(text).unwrap();
+        let results: Vec<f64> = positions.iter().map(|labeled| labeled.result).collect();
+        assert_eq!(results, vec![1.0, 0.5, 0.0]);
+        assert_eq!(positions[1].position.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn positions_need_a_result() {
+        assert!(load_labeled_positions("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").is_err());
+        assert!(load_labeled_positions("4k3/8/8/8/8/8/8/3QK3 w - - 0 1 1-0 extra").is_err());
+        assert!(load_labeled_positions("4k3/8/8/8/8/8/8/3QX3 w - - 1-0").is_err());
+    }
+
+    #[test]
+    fn features_add_up_to_the_evaluation() {
+        let positions = vec![
+            labeled("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", 0.5),
+            labeled("r3k2r/pp3ppp/2n5/3q4/3P4/2N2N2/PP3PPP/R2QK2R w KQkq - 0 1", 0.5),
+            labeled("4k3/8/8/8/8/8/8/4K3[Qn] w - - 0 1", 1.0),
+        ];
+        let tuner = Tuner::new(&positions);
+        let mut params = EvalParams::STANDARD;
+        params.piece_values[3] = 480;
+        params.tables[1][27] = 40;
+        let evaluations = tuner.evaluations(&super::weights(&params));
+        for (labeled, eval) in positions.iter().zip(evaluations) {
+            assert_eq!(eval, params.evaluate_white(&labeled.position));
+        }
+    }
+
+    #[test]
+    fn tuning_lowers_the_error() {
+        // An extra pawn only ever draws here, so pawns should end up worth less
+        let positions = vec![
+            labeled("4k3/8/8/8/8/8/3P4/4K3 w - - 0 1", 0.5),
+            labeled("4k3/3p4/8/8/8/8/8/4K3 w - - 0 1", 0.5),
+            labeled("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", 1.0),
+            labeled("3qk3/8/8/8/8/8/8/4K3 w - - 0 1", 0.0),
+        ];
+        let tuner = Tuner::new(&positions);
+        let k = tuner.best_k(&EvalParams::STANDARD);
+        assert!(k > 0.0);
+        let before = tuner.error(&EvalParams::STANDARD, k);
+        let mut errors = vec![];
+        let tuned = tuner.tune(&EvalParams::STANDARD, k, 5, |_, error| errors.push(error));
+        assert_eq!(errors.len(), 5);
+        assert!(errors.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(tuner.error(&tuned, k) < before);
+        assert!(tuned.piece_values[0] < 100);
+        assert_eq!(tuned.piece_values[5], 20000);
+    }
+}
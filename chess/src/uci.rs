@@ -0,0 +1,199 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::ChessError;
+use crate::search::{SearchLimits, MATE_SCORE, MAX_DEPTH};
+
+/// How long an engine may take to answer "uci" and "isready"
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a search without a time limit may take
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
+/// How much longer than its move time an engine may take to send "bestmove"
+const MOVE_TIME_MARGIN: Duration = Duration::from_secs(2);
+
+/// An engine executable spoken to over the Universal Chess Interface. An engine that does
+/// not answer in time is killed, and every later request fails.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    /// The engine's output line by line, read on a thread of its own so reading can time out
+    lines: Receiver<String>,
+    /// From the engine's "id name", or the path when it does not tell
+    pub name: String,
+}
+
+/// What the engine answered to "go".
+#[derive(Debug, Clone, PartialEq)]
+pub struct UciReply {
+    /// The move in coordinate notation, "0000" or "(none)" when the engine has no move
+    pub best_move: String,
+    /// The last score the engine reported, converted to the search's scale
+    pub score: Option<i32>,
+}
+
+impl UciEngine {
+    /// Starts the executable with the given arguments and waits until it is ready.
+    pub fn start(path: &str, args: &[&str]) -> Result<UciEngine, ChessError> {
+        let mut child = Command::new(path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| ChessError::engine(&format!("Could not start {}: {}", path, error)))?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = UciEngine { child, stdin, lines, name: path.to_string() };
+
+        engine.send("uci")?;
+        for line in engine.read_until("uciok", HANDSHAKE_TIMEOUT)? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, command: &str) -> Result<(), ChessError> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| ChessError::engine("The engine stopped reading its input"))
+    }
+
+    /// Lines up to and including the first one starting with `token`. Kills the engine if
+    /// that line does not come within `timeout`.
+    fn read_until(&mut self, token: &str, timeout: Duration) -> Result<Vec<String>, ChessError> {
+        let deadline = Instant::now() + timeout;
+        let mut lines = vec![];
+        loop {
+            let line = match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    let _ = self.child.kill();
+                    return Err(ChessError::engine(&format!("The engine did not send {} in time", token)));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(ChessError::engine(&format!("The engine quit before sending {}", token)))
+                }
+            };
+            let line = line.trim().to_string();
+            let done = line.split_whitespace().next() == Some(token);
+            lines.push(line);
+            if done {
+                return Ok(lines);
+            }
+        }
+    }
+
+    pub fn wait_ready(&mut self) -> Result<(), ChessError> {
+        self.send("isready")?;
+        self.read_until("readyok", HANDSHAKE_TIMEOUT)?;
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> Result<(), ChessError> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    /// Searches the position reached by playing `moves` from `fen`. The engine gets its move time
+    /// and a little more to answer, a minute when the search has no time limit.
+    pub fn go(&mut self, fen: &str, moves: &[String], limits: SearchLimits) -> Result<UciReply, ChessError> {
+        let mut position = format!("position fen {}", fen);
+        if !moves.is_empty() {
+            position.push_str(" moves ");
+            position.push_str(&moves.join(" "));
+        }
+        self.send(&position)?;
+        self.send(&go_command(limits))?;
+
+        let timeout = limits.time.map_or(SEARCH_TIMEOUT, |time| time + MOVE_TIME_MARGIN);
+        let lines = self.read_until("bestmove", timeout)?;
+        let score = lines.iter().rev().find_map(|line| parse_score(line));
+        let best_move = lines
+            .last()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .ok_or(ChessError::engine("bestmove without a move"))?;
+        Ok(UciReply { best_move: best_move.to_string(), score })
+    }
+}
+
+impl Drop for UciEngine {
+    /// Asks the engine to quit and kills it if it is still running a second later.
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while matches!(self.child.try_wait(), Ok(None)) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// The "go" command for the limits. Without a time or node limit the depth is used as is.
+fn go_command(limits: SearchLimits) -> String {
+    let mut command = "go".to_string();
+    if limits.depth < MAX_DEPTH || (limits.time.is_none() && limits.nodes.is_none()) {
+        command.push_str(&format!(" depth {}", limits.depth));
+    }
+    if let Some(time) = limits.time {
+        command.push_str(&format!(" movetime {}", time.as_millis().max(1)));
+    }
+    if let Some(nodes) = limits.nodes {
+        command.push_str(&format!(" nodes {}", nodes));
+    }
+    command
+}
+
+/// The score of an "info" line, with "mate N" turned into the search's mate scores.
+fn parse_score(line: &str) -> Option<i32> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.first() != Some(&"info") {
+        return None;
+    }
+    let at = tokens.iter().position(|token| *token == "score")?;
+    let value: i32 = tokens.get(at + 2)?.parse().ok()?;
+    match *tokens.get(at + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE_SCORE - (2 * value - 1)),
+        "mate" => Some(-MATE_SCORE - 2 * value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::search::{mate_in, SearchLimits};
+    use crate::uci::{go_command, parse_score};
+
+    #[test]
+    fn reading_scores() {
+        assert_eq!(parse_score("info depth 5 score cp -34 nodes 1000 pv e2e4"), Some(-34));
+        assert_eq!(mate_in(parse_score("info depth 5 score mate 3 pv a1a8").unwrap()), Some(3));
+        assert_eq!(mate_in(parse_score("info score mate -2").unwrap()), Some(-2));
+        assert_eq!(parse_score("info string score cp"), None);
+        assert_eq!(parse_score("bestmove e2e4"), None);
+    }
+
+    #[test]
+    fn go_commands() {
+        assert_eq!(go_command(SearchLimits::depth(4)), "go depth 4");
+        assert_eq!(go_command(SearchLimits::time(Duration::from_millis(250))), "go movetime 250");
+        assert_eq!(go_command(SearchLimits::nodes(5000)), "go nodes 5000");
+    }
+}
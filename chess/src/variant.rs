@@ -0,0 +1,707 @@
+use crate::board::{Chessboard, Square};
+use crate::chess_piece::PieceType::{King, Pawn};
+use crate::chess_piece::{Color, Piece, PieceMovement};
+use crate::error::ChessError;
+use crate::moves::{
+    castling_moves, en_passant_moves, home_rank, is_castling, is_in_check, promotion_rank, Move, PROMOTION_PIECES,
+};
+use crate::position::Position;
+
+const CHECKS_TO_WIN: u8 = 3;
+const FIFTY_MOVE_RULE_PLIES: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Win(Color),
+    Draw,
+}
+
+/// The rules of a chess variant. Piece movement itself comes from `PieceMovement`,
+/// a variant decides which of those moves are legal, what happens when they are
+/// played and when the game is over.
+pub trait Variant: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn allows_drops(&self) -> bool {
+        false
+    }
+
+    /// Every move the side to move could make, ignoring king safety.
+    fn pseudo_legal_moves(&self, position: &Position) -> Vec<Move> {
+        let mut moves: Vec<Move> = vec![];
+        let last_rank = promotion_rank(&position.board, position.side_to_move);
+        for piece in position.board.pieces(position.side_to_move) {
+            for to in piece.can_move(&position.board) {
+                if piece.piece_type == Pawn && to.y == last_rank {
+                    for promotion in PROMOTION_PIECES {
+                        moves.push(Move::with_promotion(piece.location, to, promotion));
+                    }
+                } else {
+                    moves.push(Move::new(piece.location, to));
+                }
+            }
+        }
+        moves.append(&mut en_passant_moves(position));
+        moves.append(&mut castling_moves(position));
+        if self.allows_drops() {
+            moves.append(&mut drop_moves(position));
+        }
+        moves
+    }
+
+    fn is_legal(&self, position: &Position, mv: Move) -> bool {
+        let mut next = position.clone();
+        self.apply_move(&mut next, mv).is_ok() && !is_in_check(&next.board, position.side_to_move)
+    }
+
+    fn legal_moves(&self, position: &Position) -> Vec<Move> {
+        self.pseudo_legal_moves(position)
+            .into_iter()
+            .filter(|mv| self.is_legal(position, *mv))
+            .collect()
+    }
+
+    /// Plays the move without checking legality and hands the turn over.
+    fn apply_move(&self, position: &mut Position, mv: Move) -> Result<(), ChessError> {
+        standard_apply(position, mv)?;
+        Ok(())
+    }
+
+    /// Wins and draws that come from the variant's own rules rather than from running out of
+    /// moves, like a king reaching the hill. Cheap to call, it never generates moves.
+    fn rule_outcome(&self, _position: &Position) -> Option<Outcome> {
+        None
+    }
+
+    fn outcome(&self, position: &Position) -> Option<Outcome> {
+        self.rule_outcome(position).or_else(|| standard_outcome(self, position))
+    }
+
+    fn play(&self, position: &mut Position, mv: Move) -> Result<(), ChessError> {
+        if self.outcome(position).is_some() {
+            return Err(ChessError::illegal_move("Game is already over"));
+        }
+        if !self.pseudo_legal_moves(position).contains(&mv) {
+            return Err(ChessError::illegal_move("Piece can not move there"));
+        }
+        if !self.is_legal(position, mv) {
+            return Err(ChessError::illegal_move("Move leaves the king in check"));
+        }
+        self.apply_move(position, mv)
+    }
+}
+
+/// Moves the piece (or drops it from the pocket), switches side and returns the captured piece.
+/// Also takes care of the rook when castling, the pawn captured en passant, promotions,
+/// castling rights, the en passant square and the move counters.
+pub fn standard_apply(position: &mut Position, mv: Move) -> Result<Option<Piece>, ChessError> {
+    let mover = position.side_to_move;
+    let en_passant = position.en_passant.take();
+    let mut resets_clock = false;
+    let captured = match mv {
+        Move::Normal { from, to, promotion } => {
+            let piece = match position.board.get_piece_at(from) {
+                Some(piece) => piece,
+                None => return Err(ChessError::illegal_move("No piece on the starting square")),
+            };
+            let board = &mut position.board;
+            let mut captured = board.move_piece(from, to)?;
+
+            if piece.piece_type == Pawn {
+                resets_clock = true;
+                if Some(to) == en_passant && captured.is_none() && from.x != to.x {
+                    captured = board.remove_piece(Square { x: to.x, y: from.y })?;
+                }
+                if from.y.abs_diff(to.y) == 2 {
+                    position.en_passant = Some(Square { x: to.x, y: (from.y + to.y) / 2 });
+                }
+                if let Some(promotion) = promotion {
+                    board.add_piece(Piece { piece_type: promotion, ..piece.with_location(to) })?;
+                }
+            }
+            if is_castling(piece, from, to) {
+                let (rook_from, rook_to) = if to.x > from.x {
+                    (board.width() - 1, to.x - 1)
+                } else {
+                    (0, to.x + 1)
+                };
+                board.move_piece(Square { x: rook_from, y: to.y }, Square { x: rook_to, y: to.y })?;
+            }
+            if piece.piece_type == King {
+                position.castling.remove_all(mover);
+            }
+            update_castling_rights_for_corners(position, [from, to]);
+            captured
+        }
+        Move::Drop { piece_type, to } => {
+            if position.board.get_piece_at(to).is_some() {
+                return Err(ChessError::illegal_move("Can only drop on an empty square"));
+            }
+            if !position.take_from_pocket(mover, piece_type) {
+                return Err(ChessError::illegal_move("Piece is not in the pocket"));
+            }
+            position.board.add_piece(Piece { piece_type, color: mover, location: to })?;
+            None
+        }
+    };
+
+    if resets_clock || captured.is_some() {
+        position.halfmove_clock = 0;
+    } else {
+        position.halfmove_clock += 1;
+    }
+    if mover == Color::Black {
+        position.fullmove_number += 1;
+    }
+    position.side_to_move = mover.opposite();
+    Ok(captured)
+}
+
+/// Looks a variant up by its `name`, ignoring case, spaces and dashes ("king-of-the-hill" works too).
+pub fn variant_from_name(name: &str) -> Option<Box<dyn Variant>> {
+    let variants: Vec<Box<dyn Variant>> =
+        vec![Box::new(Standard), Box::new(KingOfTheHill), Box::new(ThreeCheck), Box::new(Atomic), Box::new(Crazyhouse)];
+    let simplify = |name: &str| -> String {
+        name.chars().filter(|c| c.is_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+    };
+    variants.into_iter().find(|variant| simplify(variant.name()) == simplify(name))
+}
+
+/// Anything leaving or arriving on a rook's starting corner ends castling on that side
+fn update_castling_rights_for_corners(position: &mut Position, squares: [Square; 2]) {
+    let board = &position.board;
+    let rights = &mut position.castling;
+    for color in [Color::White, Color::Black] {
+        let rank = home_rank(board, color);
+        for square in squares {
+            if square == (Square { x: board.width() - 1, y: rank }) {
+                match color {
+                    Color::White => rights.white_king_side = false,
+                    Color::Black => rights.black_king_side = false,
+                }
+            }
+            if square == (Square { x: 0, y: rank }) {
+                match color {
+                    Color::White => rights.white_queen_side = false,
+                    Color::Black => rights.black_queen_side = false,
+                }
+            }
+        }
+    }
+}
+
+/// Checkmate, stalemate and the fifty-move rule.
+pub fn standard_outcome<V: Variant + ?Sized>(variant: &V, position: &Position) -> Option<Outcome> {
+    if !variant.legal_moves(position).is_empty() {
+        if position.halfmove_clock >= FIFTY_MOVE_RULE_PLIES {
+            return Some(Outcome::Draw);
+        }
+        return None;
+    }
+    if is_in_check(&position.board, position.side_to_move) {
+        Some(Outcome::Win(position.side_to_move.opposite()))
+    } else {
+        Some(Outcome::Draw)
+    }
+}
+
+/// Number of leaf nodes of the legal move tree, the standard way of testing move generation.
+pub fn perft<V: Variant + ?Sized>(variant: &V, position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = variant.legal_moves(position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|mv| {
+            let mut next = position.clone();
+            variant.apply_move(&mut next, mv).map_or(0, |_| perft(variant, &next, depth - 1))
+        })
+        .sum()
+}
+
+fn drop_moves(position: &Position) -> Vec<Move> {
+    let pocket = position.pocket(position.side_to_move);
+    let mut moves: Vec<Move> = vec![];
+    for (index, piece_type) in pocket.iter().enumerate() {
+        if pocket[..index].contains(piece_type) {
+            continue;
+        }
+        for y in 0..position.board.height() {
+            // Pawns may not be dropped on the first or last rank
+            if *piece_type == Pawn && (y == 0 || y == position.board.height() - 1) {
+                continue;
+            }
+            for x in 0..position.board.width() {
+                let to = Square { x, y };
+                if position.board.get_piece_at(to).is_none() {
+                    moves.push(Move::Drop { piece_type: *piece_type, to });
+                }
+            }
+        }
+    }
+    moves
+}
+
+pub struct Standard;
+
+impl Variant for Standard {
+    fn name(&self) -> &str {
+        "Standard"
+    }
+}
+
+/// The middle file(s) and rank(s) of the board, which is d4, e4, d5 and e5 on 8x8
+fn is_on_hill(board: &Chessboard, square: Square) -> bool {
+    let is_middle = |coord: usize, size: usize| coord == size / 2 || coord == (size - 1) / 2;
+    is_middle(square.x, board.width()) && is_middle(square.y, board.height())
+}
+
+/// Bringing the king to one of the center squares wins.
+pub struct KingOfTheHill;
+
+impl Variant for KingOfTheHill {
+    fn name(&self) -> &str {
+        "King of the Hill"
+    }
+
+    fn rule_outcome(&self, position: &Position) -> Option<Outcome> {
+        for color in [Color::White, Color::Black] {
+            if let Some(king) = position.board.find_king(color) {
+                if is_on_hill(&position.board, king) {
+                    return Some(Outcome::Win(color));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Giving check three times wins.
+pub struct ThreeCheck;
+
+impl Variant for ThreeCheck {
+    fn name(&self) -> &str {
+        "Three-check"
+    }
+
+    fn apply_move(&self, position: &mut Position, mv: Move) -> Result<(), ChessError> {
+        let mover = position.side_to_move;
+        standard_apply(position, mv)?;
+        if is_in_check(&position.board, mover.opposite()) {
+            position.checks_given[mover.index()] += 1;
+        }
+        Ok(())
+    }
+
+    fn rule_outcome(&self, position: &Position) -> Option<Outcome> {
+        for color in [Color::White, Color::Black] {
+            if position.checks_given[color.index()] >= CHECKS_TO_WIN {
+                return Some(Outcome::Win(color));
+            }
+        }
+        None
+    }
+}
+
+/// Captures explode, removing the capturing piece and every non-pawn piece next to
+/// the captured one. Blowing up the enemy king wins.
+pub struct Atomic;
+
+impl Variant for Atomic {
+    fn name(&self) -> &str {
+        "Atomic"
+    }
+
+    fn pseudo_legal_moves(&self, position: &Position) -> Vec<Move> {
+        // Kings can not capture since they would blow themselves up
+        Standard
+            .pseudo_legal_moves(position)
+            .into_iter()
+            .filter(|mv| match *mv {
+                Move::Normal { from, to, .. } => {
+                    let is_king = position.board.get_piece_at(from).map(|piece| piece.piece_type) == Some(King);
+                    !(is_king && position.board.get_piece_at(to).is_some())
+                }
+                Move::Drop { .. } => true,
+            })
+            .collect()
+    }
+
+    fn is_legal(&self, position: &Position, mv: Move) -> bool {
+        let mover = position.side_to_move;
+        let mut next = position.clone();
+        if self.apply_move(&mut next, mv).is_err() {
+            return false;
+        }
+
+        if next.board.find_king(mover).is_none() {
+            return false;
+        }
+        if next.board.find_king(mover.opposite()).is_none() {
+            return true;
+        }
+        kings_touching(&next.board) || !is_in_check(&next.board, mover)
+    }
+
+    fn apply_move(&self, position: &mut Position, mv: Move) -> Result<(), ChessError> {
+        if standard_apply(position, mv)?.is_some() {
+            // The blast is centered where the capturing piece lands, also for en passant
+            let center = mv.to();
+            position.board.remove_piece(center)?;
+            let board = &mut position.board;
+            for y in center.y.saturating_sub(1)..=(center.y + 1).min(board.height() - 1) {
+                for x in center.x.saturating_sub(1)..=(center.x + 1).min(board.width() - 1) {
+                    let square = Square { x, y };
+                    if let Some(piece) = board.get_piece_at(square) {
+                        if piece.piece_type != Pawn {
+                            board.remove_piece(square)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn rule_outcome(&self, position: &Position) -> Option<Outcome> {
+        for color in [Color::White, Color::Black] {
+            if position.board.find_king(color).is_none() {
+                return Some(Outcome::Win(color.opposite()));
+            }
+        }
+        None
+    }
+
+    fn outcome(&self, position: &Position) -> Option<Outcome> {
+        self.rule_outcome(position).or_else(|| match standard_outcome(self, position) {
+            // Without moves while the kings touch the king is not attacked, so it is stalemate
+            Some(Outcome::Win(_)) if kings_touching(&position.board) => Some(Outcome::Draw),
+            outcome => outcome,
+        })
+    }
+}
+
+/// Kings next to each other can never be captured in Atomic, since the capture would blow up
+/// the capturer's king too, so they do not give check.
+fn kings_touching(board: &Chessboard) -> bool {
+    match (board.find_king(Color::White), board.find_king(Color::Black)) {
+        (Some(white), Some(black)) => white.x.abs_diff(black.x) <= 1 && white.y.abs_diff(black.y) <= 1,
+        _ => false,
+    }
+}
+
+/// Captured pieces change color and go to the capturer's pocket, from where they
+/// can be dropped on any empty square instead of moving.
+pub struct Crazyhouse;
+
+impl Variant for Crazyhouse {
+    fn name(&self) -> &str {
+        "Crazyhouse"
+    }
+
+    fn allows_drops(&self) -> bool {
+        true
+    }
+
+    /// Promoted pieces go back to the pocket as the pawns they were.
+    fn apply_move(&self, position: &mut Position, mv: Move) -> Result<(), ChessError> {
+        let mover = position.side_to_move;
+        let (to, promotes) = match mv {
+            Move::Normal { from, to, promotion } => (to, promotion.is_some() || position.promoted.contains(&from)),
+            Move::Drop { to, .. } => (to, false),
+        };
+        let captured_promoted = position.promoted.contains(&to);
+        if let Some(captured) = standard_apply(position, mv)? {
+            position.add_to_pocket(mover, if captured_promoted { Pawn } else { captured.piece_type });
+        }
+        if let Move::Normal { from, .. } = mv {
+            position.set_promoted(from, false);
+        }
+        position.set_promoted(to, promotes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Chessboard, Square};
+    use crate::chess_piece::Color::{Black, White};
+    use crate::chess_piece::PieceType;
+    use crate::error::ChessError;
+    use crate::moves::Move;
+    use crate::position::Position;
+    use crate::variant::{perft, variant_from_name, Atomic, Crazyhouse, KingOfTheHill, Outcome, Standard, ThreeCheck, Variant};
+
+    fn position(fen: &str, side_to_move: crate::chess_piece::Color) -> Position {
+        Position::new(Chessboard::from_fen(fen).unwrap(), side_to_move)
+    }
+
+    fn normal(from: &str, to: &str) -> Move {
+        Move::new(Square::algebraic_to_coords(from).unwrap(), Square::algebraic_to_coords(to).unwrap())
+    }
+
+    fn square(algebraic: &str) -> Square {
+        Square::algebraic_to_coords(algebraic).unwrap()
+    }
+
+    #[test]
+    fn variants_by_name() {
+        assert_eq!(variant_from_name("standard").unwrap().name(), "Standard");
+        assert_eq!(variant_from_name("king-of-the-hill").unwrap().name(), "King of the Hill");
+        assert_eq!(variant_from_name("Three-check").unwrap().name(), "Three-check");
+        assert!(variant_from_name("bughouse").is_none());
+    }
+
+    #[test]
+    fn castling_moves_king_and_rook() {
+        let mut position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        Standard.play(&mut position, normal("e1", "g1")).unwrap();
+        assert_eq!(position.board.get_piece_at(square("f1")).unwrap().piece_type, PieceType::Rook);
+        assert!(position.board.get_piece_at(square("h1")).is_none());
+        assert!(!position.castling.king_side(White) && !position.castling.queen_side(White));
+
+        Standard.play(&mut position, normal("e8", "c8")).unwrap();
+        assert_eq!(position.board.get_piece_at(square("d8")).unwrap().piece_type, PieceType::Rook);
+        assert!(position.board.get_piece_at(square("a8")).is_none());
+
+        // Only from the king's starting file, even with the rights left in the FEN
+        let king_on_f1 = Position::from_fen("4k3/8/8/8/8/8/8/R4K1R w KQ - 0 1").unwrap();
+        assert!(!Standard.legal_moves(&king_on_f1).contains(&normal("f1", "c1")));
+    }
+
+    #[test]
+    fn castling_is_not_allowed_through_check() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1").unwrap();
+        let moves = Standard.legal_moves(&position);
+        assert!(!moves.contains(&normal("e1", "g1")));
+        assert!(moves.contains(&normal("e1", "c1")));
+
+        let in_check = Position::from_fen("4k3/8/8/8/8/8/4r3/R3K2R w KQ - 0 1").unwrap();
+        let moves = Standard.legal_moves(&in_check);
+        assert!(!moves.contains(&normal("e1", "g1")));
+        assert!(!moves.contains(&normal("e1", "c1")));
+    }
+
+    #[test]
+    fn moving_a_rook_loses_castling_on_that_side() {
+        let mut position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        Standard.play(&mut position, normal("h1", "h8")).unwrap();
+        assert!(!position.castling.king_side(White) && position.castling.queen_side(White));
+        assert!(!position.castling.king_side(Black) && position.castling.queen_side(Black));
+    }
+
+    #[test]
+    fn en_passant_capture_removes_the_pawn() {
+        let mut position = Position::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        Standard.play(&mut position, normal("d7", "d5")).unwrap();
+        assert_eq!(position.en_passant, Some(square("d6")));
+        Standard.play(&mut position, normal("e5", "d6")).unwrap();
+        assert!(position.board.get_piece_at(square("d5")).is_none());
+        assert_eq!(position.en_passant, None);
+        assert_eq!(position.halfmove_clock, 0);
+    }
+
+    #[test]
+    fn promotion_replaces_the_pawn() {
+        let mut position = Position::from_fen("7k/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(Standard.legal_moves(&position).iter().filter(|mv| mv.from() == Some(square("a7"))).count(), 4);
+        assert!(Standard.play(&mut position, normal("a7", "a8")).is_err());
+        Standard.play(&mut position, Move::with_promotion(square("a7"), square("a8"), PieceType::Queen)).unwrap();
+        assert_eq!(position.board.get_piece_at(square("a8")).unwrap().piece_type, PieceType::Queen);
+    }
+
+    #[test]
+    fn move_counters() {
+        let mut position = Position::from_fen(crate::position::STANDARD_START_FEN).unwrap();
+        Standard.play(&mut position, normal("g1", "f3")).unwrap();
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (1, 1));
+        Standard.play(&mut position, normal("g8", "f6")).unwrap();
+        assert_eq!((position.halfmove_clock, position.fullmove_number), (2, 2));
+    }
+
+    #[test]
+    fn fifty_moves_without_progress_is_a_draw() {
+        let position = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(Standard.outcome(&position), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn perft_counts() {
+        let start = Position::from_fen(crate::position::STANDARD_START_FEN).unwrap();
+        assert_eq!(perft(&Standard, &start, 3), 8902);
+        let kiwipete = Position::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(perft(&Standard, &kiwipete, 2), 2039);
+        let endgame = Position::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+        assert_eq!(perft(&Standard, &endgame, 3), 2812);
+        let promotions = Position::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1").unwrap();
+        assert_eq!(perft(&Standard, &promotions, 2), 264);
+    }
+
+    #[test]
+    fn standard_pinned_piece_can_not_move() {
+        let position = position("4r3/8/8/8/8/8/4R3/4K3", White);
+        assert!(!Standard.is_legal(&position, normal("e2", "d2")));
+        assert!(Standard.is_legal(&position, normal("e2", "e8")));
+    }
+
+    #[test]
+    fn standard_play_explains_illegal_move() {
+        let mut position = position("4r3/8/8/8/8/8/4R3/4K3", White);
+        assert_eq!(
+            Standard.play(&mut position, normal("e2", "d2")),
+            Err(ChessError::illegal_move("Move leaves the king in check"))
+        );
+    }
+
+    #[test]
+    fn standard_back_rank_mate() {
+        let position = position("R5k1/5ppp/8/8/8/8/8/6K1", Black);
+        assert_eq!(Standard.outcome(&position), Some(Outcome::Win(White)));
+    }
+
+    #[test]
+    fn standard_stalemate_is_draw() {
+        let position = position("7k/5Q2/6K1/8/8/8/8/8", Black);
+        assert_eq!(Standard.outcome(&position), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn standard_rejects_moving_out_of_turn() {
+        let mut position = position("4k3/8/8/8/8/8/4P3/4K3", Black);
+        assert_eq!(
+            Standard.play(&mut position, normal("e2", "e4")),
+            Err(ChessError::illegal_move("Piece can not move there"))
+        );
+    }
+
+    #[test]
+    fn king_of_the_hill_center_wins() {
+        let mut position = position("4k3/8/8/8/8/4K3/8/8", White);
+        assert_eq!(KingOfTheHill.outcome(&position), None);
+        KingOfTheHill.play(&mut position, normal("e3", "e4")).unwrap();
+        assert_eq!(KingOfTheHill.outcome(&position), Some(Outcome::Win(White)));
+    }
+
+    #[test]
+    fn king_of_the_hill_center_follows_board_size() {
+        let mut position = position("3k2/6/6/6/6/K5", White);
+        let square = |algebraic: &str| Square::algebraic_to_coords_with_size(algebraic, 6, 6).unwrap();
+        KingOfTheHill.play(&mut position, Move::new(square("a1"), square("b2"))).unwrap();
+        assert_eq!(KingOfTheHill.outcome(&position), None);
+        KingOfTheHill.play(&mut position, Move::new(square("d6"), square("d5"))).unwrap();
+        KingOfTheHill.play(&mut position, Move::new(square("b2"), square("c3"))).unwrap();
+        assert_eq!(KingOfTheHill.outcome(&position), Some(Outcome::Win(White)));
+    }
+
+    #[test]
+    fn three_check_counts_checks() {
+        let mut position = position("4k3/8/8/8/8/8/8/R3K3", White);
+        ThreeCheck.play(&mut position, normal("a1", "a8")).unwrap();
+        assert_eq!(position.checks_given[White.index()], 1);
+        ThreeCheck.play(&mut position, normal("e8", "e7")).unwrap();
+        ThreeCheck.play(&mut position, normal("a8", "a7")).unwrap();
+        assert_eq!(position.checks_given[White.index()], 2);
+        assert_eq!(ThreeCheck.outcome(&position), None);
+    }
+
+    #[test]
+    fn three_check_third_check_wins() {
+        let mut position = position("4k3/8/8/8/8/8/8/R3K3", White);
+        position.checks_given[White.index()] = 2;
+        ThreeCheck.play(&mut position, normal("a1", "a8")).unwrap();
+        assert_eq!(ThreeCheck.outcome(&position), Some(Outcome::Win(White)));
+    }
+
+    #[test]
+    fn atomic_capture_explodes_neighbours() {
+        let mut position = position("4k3/8/8/2nbp3/3p4/8/8/3RK3", White);
+        Atomic.play(&mut position, normal("d1", "d4")).unwrap();
+        let board = &position.board;
+        assert!(board.get_piece_at(Square::algebraic_to_coords("d4").unwrap()).is_none());
+        assert!(board.get_piece_at(Square::algebraic_to_coords("d1").unwrap()).is_none());
+        assert!(board.get_piece_at(Square::algebraic_to_coords("c5").unwrap()).is_none());
+        assert!(board.get_piece_at(Square::algebraic_to_coords("d5").unwrap()).is_none());
+        // Pawns survive explosions they are not part of
+        assert!(board.get_piece_at(Square::algebraic_to_coords("e5").unwrap()).is_some());
+    }
+
+    #[test]
+    fn atomic_exploding_the_king_wins() {
+        let mut position = position("3qk3/8/8/8/8/8/8/3RK3", White);
+        Atomic.play(&mut position, normal("d1", "d8")).unwrap();
+        assert_eq!(Atomic.outcome(&position), Some(Outcome::Win(White)));
+    }
+
+    #[test]
+    fn atomic_king_can_not_capture() {
+        let position = position("4k3/8/8/8/8/8/4p3/4K3", White);
+        assert!(!Atomic.legal_moves(&position).contains(&normal("e1", "e2")));
+    }
+
+    #[test]
+    fn atomic_capture_that_blows_own_king_is_illegal() {
+        let position = position("4k3/8/8/8/8/8/3p4/3QK3", White);
+        assert!(!Atomic.is_legal(&position, normal("d1", "d2")));
+    }
+
+    #[test]
+    fn atomic_touching_kings_without_moves_is_stalemate() {
+        // The knight attacks a1, but the kings touch so White is not in check
+        let position = position("8/8/8/8/8/p7/Pkn5/Kb6", White);
+        assert!(Atomic.legal_moves(&position).is_empty());
+        assert_eq!(Atomic.outcome(&position), Some(Outcome::Draw));
+        assert_eq!(Standard.outcome(&position), Some(Outcome::Win(Black)));
+    }
+
+    #[test]
+    fn crazyhouse_capture_goes_to_pocket_and_can_be_dropped() {
+        let mut position = position("4k3/8/8/3n4/8/8/8/3RK3", White);
+        Crazyhouse.play(&mut position, normal("d1", "d5")).unwrap();
+        assert_eq!(position.pocket(White), &vec![PieceType::Knight]);
+
+        Crazyhouse.play(&mut position, normal("e8", "e7")).unwrap();
+        let drop = Move::Drop { piece_type: PieceType::Knight, to: Square::algebraic_to_coords("f5").unwrap() };
+        assert!(Crazyhouse.legal_moves(&position).contains(&drop));
+        Crazyhouse.play(&mut position, drop).unwrap();
+        assert!(position.pocket(White).is_empty());
+        assert_eq!(
+            position.board.get_piece_at(Square::algebraic_to_coords("f5").unwrap()).map(|piece| piece.color),
+            Some(White)
+        );
+    }
+
+    #[test]
+    fn crazyhouse_promoted_pieces_are_pocketed_as_pawns() {
+        let mut position = position("r3k3/1P6/8/8/8/8/8/4K3", White);
+        Crazyhouse.play(&mut position, Move::with_promotion(square("b7"), square("b8"), PieceType::Queen)).unwrap();
+        assert_eq!(position.to_fen(), "rQ~2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        Crazyhouse.play(&mut position, normal("a8", "b8")).unwrap();
+        assert_eq!(position.pocket(Black), &vec![PieceType::Pawn]);
+        assert!(position.promoted.is_empty());
+
+        // The mark moves with the piece and is read back from FEN
+        let mut position = Position::from_fen("k7/8/8/8/8/8/7r/Q~3K3 w - - 0 1").unwrap();
+        Crazyhouse.play(&mut position, normal("a1", "e5")).unwrap();
+        assert_eq!(position.to_fen(), "k7/8/8/4Q~3/8/8/7r/4K3 b - - 1 1");
+        Crazyhouse.play(&mut position, normal("h2", "h5")).unwrap();
+        Crazyhouse.play(&mut position, normal("e5", "h5")).unwrap();
+        assert_eq!(position.pocket(White), &vec![PieceType::Rook]);
+        assert_eq!(position.promoted, vec![Square::algebraic_to_coords("h5").unwrap()]);
+    }
+
+    #[test]
+    fn crazyhouse_pawns_can_not_be_dropped_on_back_ranks() {
+        let mut position = position("4k3/8/8/8/8/8/8/4K3", White);
+        position.add_to_pocket(White, PieceType::Pawn);
+        let moves = Crazyhouse.legal_moves(&position);
+        assert!(moves.iter().all(|mv| match mv {
+            Move::Drop { to, .. } => to.y != 0 && to.y != 7,
+            Move::Normal { .. } => true,
+        }));
+        assert!(moves.contains(&Move::Drop { piece_type: PieceType::Pawn, to: Square::algebraic_to_coords("e4").unwrap() }));
+    }
+}
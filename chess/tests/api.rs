@@ -0,0 +1,54 @@
+use std::error::Error;
+use std::sync::Arc;
+
+use chess::board::CAPABLANCA_FEN;
+use chess::fairy::PieceRegistry;
+use chess::rules::{attackers_of, is_capture_safe};
+use chess::variant::Crazyhouse;
+use chess::{Board, ChessError, Color, Game, Move, Outcome, PieceMovement, PieceType, Position, Square};
+
+fn square(algebraic: &str) -> Square {
+    Square::algebraic_to_coords(algebraic).unwrap()
+}
+
+#[test]
+fn playing_a_game_through_the_library() -> Result<(), Box<dyn Error>> {
+    let mut game = Game::new();
+    for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+        game.play(Move::new(Square::algebraic_to_coords(from)?, Square::algebraic_to_coords(to)?))?;
+    }
+    assert_eq!(game.outcome(), Some(Outcome::Win(Color::Black)));
+    Ok(())
+}
+
+#[test]
+fn errors_implement_std_error() {
+    let error: Box<dyn Error> = Box::new(Square::algebraic_to_coords("z9").unwrap_err());
+    assert_eq!(error.to_string(), "invalid square 'z9': Not a valid letter");
+
+    let fen_error = Board::from_fen("8/8/8/8/8/8/8/7X").unwrap_err();
+    assert!(matches!(fen_error, ChessError::InvalidFen { field: 0, .. }));
+}
+
+#[test]
+fn querying_a_board() {
+    let board = Board::from_fen("8/8/8/3n4/4P3/8/8/8").unwrap();
+    let pawn = board.get_piece_at(square("e4")).unwrap();
+    assert!(pawn.can_move(&board).contains(&square("d5")));
+    assert_eq!(attackers_of(&board, square("d5"), Color::White).len(), 1);
+    assert_eq!(is_capture_safe(&board, square("e4"), square("d5")), Ok(true));
+}
+
+#[test]
+fn variants_and_fairy_pieces_are_available() {
+    let registry = Arc::new(PieceRegistry::with_common_fairies());
+    let board = Board::from_fen_with_registry(CAPABLANCA_FEN, registry).unwrap();
+    let b1 = board.algebraic_to_square("b1").unwrap();
+    let c3 = board.algebraic_to_square("c3").unwrap();
+    let c1 = board.algebraic_to_square("c1").unwrap();
+
+    let mut game = Game::from_position(Position::new(board, Color::White), Box::new(Crazyhouse));
+    assert!(game.legal_moves().len() > 20);
+    game.play(Move::new(b1, c3)).unwrap();
+    assert_eq!(game.board().get_piece_at(c1).unwrap().piece_type, PieceType::Fairy('A'));
+}
@@ -0,0 +1,68 @@
+//! Talks to the API over a real socket, like any HTTP client would.
+#![cfg(feature = "server")]
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+
+use chess::server::Server;
+use serde_json::Value;
+
+fn start_server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || Server::new().serve(listener));
+    address
+}
+
+fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+
+    let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let (_, body) = response.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn playing_a_game_over_http() {
+    let address = start_server();
+
+    let (status, game) = request(address, "POST", "/games", "");
+    assert_eq!(status, 201);
+    let id = game["id"].as_u64().unwrap();
+
+    let (status, moves) = request(address, "GET", &format!("/games/{}/moves", id), "");
+    assert_eq!(status, 200);
+    assert!(moves["moves"].as_array().unwrap().contains(&Value::from("g1f3")));
+
+    let (status, game) = request(address, "POST", &format!("/games/{}/moves", id), r#"{"move": "g1f3"}"#);
+    assert_eq!(status, 200);
+    assert_eq!(game["side_to_move"], "black");
+
+    let (status, reply) = request(address, "POST", &format!("/games/{}/engine", id), r#"{"depth": 1}"#);
+    assert_eq!(status, 200);
+    assert_eq!(reply["game"]["moves"].as_array().unwrap().len(), 2);
+
+    let (status, game) = request(address, "GET", &format!("/games/{}", id), "");
+    assert_eq!(status, 200);
+    assert_eq!(game["side_to_move"], "white");
+}
+
+#[test]
+fn errors_over_http() {
+    let address = start_server();
+    let (status, body) = request(address, "GET", "/games/42", "");
+    assert_eq!(status, 404);
+    assert_eq!(body["error"], "Unknown game");
+}
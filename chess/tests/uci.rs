@@ -0,0 +1,68 @@
+//! Plays against the chess binary through the UCI client, as the tournament runner
+//! would with any external engine.
+
+use std::time::{Duration, Instant};
+
+use chess::position::STANDARD_START_FEN;
+use chess::search::SearchLimits;
+use chess::tools::{play_game, Adjudication, EnginePlayer, Termination, UciPlayer};
+use chess::uci::UciEngine;
+use chess::{Color, Outcome};
+
+fn engine() -> UciEngine {
+    UciEngine::start(env!("CARGO_BIN_EXE_chess"), &["uci"]).unwrap()
+}
+
+#[test]
+fn uci_handshake_reads_the_name() {
+    assert_eq!(engine().name, "TDD chess");
+}
+
+#[test]
+fn uci_engine_finds_the_mate() {
+    let reply = engine().go("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[], SearchLimits::depth(2)).unwrap();
+    assert_eq!(reply.best_move, "a1a8");
+    assert_eq!(chess::search::mate_in(reply.score.unwrap()), Some(1));
+}
+
+#[test]
+fn game_between_builtin_and_uci_engine() {
+    let mut uci = UciPlayer { engine: engine(), limits: SearchLimits::depth(1) };
+    let mut builtin = EnginePlayer::new("builtin", SearchLimits::depth(1));
+    let adjudication = Adjudication { max_plies: 10, ..Adjudication::default() };
+    let summary = play_game(&mut uci, &mut builtin, STANDARD_START_FEN, &adjudication).unwrap();
+    assert_eq!(summary.white, "TDD chess");
+    assert_ne!(summary.termination, Termination::IllegalMove);
+    assert_ne!(summary.termination, Termination::EngineFailure);
+    assert_eq!(summary.record.moves.len(), 10);
+}
+
+/// Answers the handshake, then never sends "bestmove"
+#[cfg(unix)]
+const SILENT_ENGINE: &str = "while read command; do
+    case $command in
+        uci) echo 'id name silent'; echo uciok ;;
+        isready) echo readyok ;;
+    esac
+done";
+
+#[cfg(unix)]
+#[test]
+fn engine_that_never_moves_loses() {
+    let silent = UciEngine::start("sh", &["-c", SILENT_ENGINE]).unwrap();
+    let limits = SearchLimits::time(Duration::from_millis(10));
+    let mut silent = UciPlayer { engine: silent, limits };
+    let mut builtin = EnginePlayer::new("builtin", SearchLimits::depth(1));
+    let started = Instant::now();
+    let summary = play_game(&mut silent, &mut builtin, STANDARD_START_FEN, &Adjudication::default()).unwrap();
+    assert_eq!(summary.white, "silent");
+    assert_eq!(summary.termination, Termination::EngineFailure);
+    assert_eq!(summary.outcome, Outcome::Win(Color::Black));
+    assert!(started.elapsed() < Duration::from_secs(10));
+    // The engine was killed, so it loses the next game too
+    assert!(silent.engine.go(STANDARD_START_FEN, &[], limits).is_err());
+    let summary = play_game(&mut builtin, &mut silent, STANDARD_START_FEN, &Adjudication::default()).unwrap();
+    assert_eq!(summary.outcome, Outcome::Win(Color::White));
+    assert_eq!(summary.termination, Termination::EngineFailure);
+    assert!(summary.record.moves.is_empty());
+}